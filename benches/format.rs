@@ -0,0 +1,63 @@
+use clap::Parser;
+use std::{hint::black_box, time::Instant};
+use termcolors::format;
+
+const ITERATIONS: u32 = 200_000;
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let nanos = start.elapsed().as_secs_f64() * 1e9 / f64::from(ITERATIONS);
+    println!("{name:<32}{nanos:>10.0} ns/call");
+}
+
+fn main() {
+    let cases: [(&str, &[&str]); 5] = [
+        ("plain", &["printcolor", "--when=always", "hi"]),
+        (
+            "foreground",
+            &["printcolor", "--when=always", "-f", "red", "hi"],
+        ),
+        (
+            "style",
+            &["printcolor", "--when=always", "-s", "debug", "hi"],
+        ),
+        (
+            "highlight",
+            &[
+                "printcolor",
+                "--when=always",
+                "--highlight",
+                "l+=warn",
+                "hello",
+            ],
+        ),
+        (
+            "colors and options",
+            &[
+                "printcolor",
+                "--when=always",
+                "-f",
+                "R",
+                "-b",
+                "k",
+                "-o",
+                "b,u,s",
+                "hello",
+                "world",
+            ],
+        ),
+    ];
+    for (name, argv) in cases {
+        bench(&format!("parse+format {name}"), || {
+            let args = format::Args::parse_from(argv);
+            black_box(format::format(args).unwrap());
+        });
+        let args = format::Args::parse_from(argv);
+        bench(&format!("format {name}"), || {
+            black_box(format::format(args.clone()).unwrap());
+        });
+    }
+}
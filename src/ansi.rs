@@ -0,0 +1,564 @@
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Kind of a scanned token.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Kind<'a> {
+    /// Plain text (may include control characters other than ESC)
+    Text,
+    /// Control sequence: `ESC [ params intermediates final`
+    Csi {
+        params: &'a [u8],
+        intermediates: &'a [u8],
+        final_byte: u8,
+    },
+    /// Operating system command: `ESC ] data` terminated by BEL or `ESC \`
+    Osc { data: &'a [u8] },
+    /// Device control, start of string, privacy message, or application program command strings
+    ControlString { introducer: u8, data: &'a [u8] },
+    /// Other escape sequence: `ESC intermediates final`
+    Esc {
+        intermediates: &'a [u8],
+        final_byte: u8,
+    },
+    /// Malformed escape sequence
+    Unknown,
+    /// Sequence truncated by the end of the input
+    Incomplete,
+}
+
+/// A scanned token and its raw bytes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub kind: Kind<'a>,
+    pub raw: &'a [u8],
+}
+
+impl Token<'_> {
+    /// If this is an SGR (select graphic rendition) sequence, its parameters.
+    pub fn sgr_params(&self) -> Option<&[u8]> {
+        match self.kind {
+            Kind::Csi {
+                params,
+                intermediates: [],
+                final_byte: b'm',
+            } if !params.first().is_some_and(|b| (b'<'..=b'?').contains(b)) => Some(params),
+            _ => None,
+        }
+    }
+
+    /// True for any escape sequence (anything other than text).
+    pub fn is_escape(&self) -> bool {
+        self.kind != Kind::Text
+    }
+}
+
+/// Scan bytes into text and escape sequences.
+///
+/// A sequence cut off by the end of the input is returned as [`Kind::Incomplete`], so that
+/// streaming callers can keep those bytes and prepend them to the next chunk.
+pub fn tokens(input: &[u8]) -> Tokens<'_> {
+    Tokens { input, position: 0 }
+}
+
+/// Iterator over scanned tokens, see [`tokens`].
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.position..];
+        if rest.is_empty() {
+            return None;
+        }
+        let (kind, len) = if rest[0] == ESC {
+            scan_escape(rest)
+        } else {
+            let len = rest.iter().position(|b| *b == ESC).unwrap_or(rest.len());
+            (Kind::Text, len)
+        };
+        self.position += len;
+        Some(Token {
+            kind,
+            raw: &rest[..len],
+        })
+    }
+}
+
+/// Scan an escape sequence at the start of input (which starts with ESC).
+fn scan_escape(input: &[u8]) -> (Kind<'_>, usize) {
+    let Some(&introducer) = input.get(1) else {
+        return (Kind::Incomplete, input.len());
+    };
+    match introducer {
+        b'[' => scan_csi(input),
+        b']' => scan_string(input, |data| Kind::Osc { data }),
+        b'P' | b'X' | b'^' | b'_' => {
+            scan_string(input, |data| Kind::ControlString { introducer, data })
+        }
+        _ => {
+            let intermediates = input[1..]
+                .iter()
+                .take_while(|b| (0x20..=0x2f).contains(*b))
+                .count();
+            match input.get(1 + intermediates) {
+                None => (Kind::Incomplete, input.len()),
+                Some(&final_byte) if (0x30..=0x7e).contains(&final_byte) => (
+                    Kind::Esc {
+                        intermediates: &input[1..1 + intermediates],
+                        final_byte,
+                    },
+                    2 + intermediates,
+                ),
+                Some(_) => (Kind::Unknown, 1 + intermediates),
+            }
+        }
+    }
+}
+
+fn scan_csi(input: &[u8]) -> (Kind<'_>, usize) {
+    let body = &input[2..];
+    let params = body
+        .iter()
+        .take_while(|b| (0x30..=0x3f).contains(*b))
+        .count();
+    let intermediates = body[params..]
+        .iter()
+        .take_while(|b| (0x20..=0x2f).contains(*b))
+        .count();
+    let end = params + intermediates;
+    match body.get(end) {
+        None => (Kind::Incomplete, input.len()),
+        Some(&final_byte) if (0x40..=0x7e).contains(&final_byte) => (
+            Kind::Csi {
+                params: &body[..params],
+                intermediates: &body[params..end],
+                final_byte,
+            },
+            2 + end + 1,
+        ),
+        Some(_) => (Kind::Unknown, 2 + end),
+    }
+}
+
+/// Scan a string sequence terminated by BEL or ST (`ESC \`).
+fn scan_string<'a>(input: &'a [u8], kind: impl Fn(&'a [u8]) -> Kind<'a>) -> (Kind<'a>, usize) {
+    let body = &input[2..];
+    for (i, byte) in body.iter().enumerate() {
+        match *byte {
+            BEL => return (kind(&body[..i]), 2 + i + 1),
+            ESC => {
+                return match body.get(i + 1) {
+                    Some(b'\\') => (kind(&body[..i]), 2 + i + 2),
+                    Some(_) => (Kind::Unknown, 2 + i),
+                    None => (Kind::Incomplete, input.len()),
+                }
+            }
+            _ => {}
+        }
+    }
+    (Kind::Incomplete, input.len())
+}
+
+/// Remove all escape sequences, including truncated ones, leaving only the text.
+pub fn strip(input: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(input.len());
+    for token in tokens(input).filter(|token| !token.is_escape()) {
+        result.extend_from_slice(token.raw);
+    }
+    result
+}
+
+/// Streaming [`strip`], for input read in chunks that may split a sequence.
+#[derive(Debug, Clone, Default)]
+pub struct Stripper {
+    only_sgr: bool,
+    pending: Vec<u8>,
+}
+
+impl Stripper {
+    /// Remove all escape sequences, or only SGR sequences (colors and formatting).
+    pub fn new(only_sgr: bool) -> Self {
+        Self {
+            only_sgr,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Strip the next chunk, holding back a sequence cut off at its end until the next chunk.
+    ///
+    /// A sequence still cut off at the end of the input is dropped.
+    pub fn strip(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let mut result = Vec::with_capacity(self.pending.len());
+        let mut consumed = 0;
+        for token in tokens(&self.pending) {
+            if token.kind == Kind::Incomplete {
+                break;
+            }
+            consumed += token.raw.len();
+            let keep = match token.kind {
+                Kind::Text => true,
+                _ => self.only_sgr && token.sgr_params().is_none(),
+            };
+            if keep {
+                result.extend_from_slice(token.raw);
+            }
+        }
+        self.pending.drain(..consumed);
+        result
+    }
+}
+
+/// First escape sequence that is malformed, truncated, or an SGR with undecodable parameters.
+pub fn first_undecodable(input: &[u8]) -> Option<Token<'_>> {
+    tokens(input).find(|token| match token.kind {
+        Kind::Unknown | Kind::Incomplete => true,
+        _ => token.sgr_params().is_some_and(|params| {
+            decode_sgr(params)
+                .iter()
+                .any(|sgr| matches!(sgr, Sgr::Unknown(_)))
+        }),
+    })
+}
+
+/// Color in an SGR sequence.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SgrColor {
+    /// Terminal default (39, 49, or 59)
+    Default,
+    /// Palette index: 0-7 basic, 8-15 bright, 16-255 extended
+    Indexed(u8),
+    /// 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+/// Decoded SGR parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sgr {
+    Reset,
+    /// Attribute code and its name (e.g. 1, "bold")
+    Attribute(u16, &'static str),
+    /// Underline style from the `4:n` subparameter form
+    UnderlineStyle(u8, &'static str),
+    Foreground(SgrColor),
+    Background(SgrColor),
+    UnderlineColor(SgrColor),
+    /// Parameters that could not be decoded
+    Unknown(String),
+}
+
+/// Decode SGR parameters (the bytes between `ESC [` and `m`).
+///
+/// Supports both the `;`-separated and the `:` subparameter forms of extended colors.
+pub fn decode_sgr(params: &[u8]) -> Vec<Sgr> {
+    let params = String::from_utf8_lossy(params);
+    if params.is_empty() {
+        return vec![Sgr::Reset];
+    }
+    let mut groups = params.split(';');
+    let mut result = Vec::new();
+    while let Some(group) = groups.next() {
+        let mut sub = group.split(':');
+        let code = sub.next().unwrap_or_default();
+        let code: u16 = match code {
+            "" => 0,
+            code => match code.parse() {
+                Ok(code) => code,
+                Err(_) => {
+                    result.push(Sgr::Unknown(group.to_owned()));
+                    continue;
+                }
+            },
+        };
+        let subparams: Vec<&str> = sub.collect();
+        let decoded = match code {
+            0 => Sgr::Reset,
+            4 if !subparams.is_empty() => match subparams[0].parse::<u8>() {
+                Ok(style) if style <= 5 => Sgr::UnderlineStyle(style, underline_style_name(style)),
+                _ => Sgr::Unknown(group.to_owned()),
+            },
+            30..=37 => Sgr::Foreground(SgrColor::Indexed((code - 30) as u8)),
+            40..=47 => Sgr::Background(SgrColor::Indexed((code - 40) as u8)),
+            90..=97 => Sgr::Foreground(SgrColor::Indexed((code - 90 + 8) as u8)),
+            100..=107 => Sgr::Background(SgrColor::Indexed((code - 100 + 8) as u8)),
+            39 => Sgr::Foreground(SgrColor::Default),
+            49 => Sgr::Background(SgrColor::Default),
+            59 => Sgr::UnderlineColor(SgrColor::Default),
+            38 | 48 | 58 => {
+                let color = if subparams.is_empty() {
+                    extended_color_semicolon(&mut groups)
+                } else {
+                    extended_color_colon(&subparams)
+                };
+                match (color, code) {
+                    (Some(color), 38) => Sgr::Foreground(color),
+                    (Some(color), 48) => Sgr::Background(color),
+                    (Some(color), _) => Sgr::UnderlineColor(color),
+                    (None, _) => Sgr::Unknown(group.to_owned()),
+                }
+            }
+            code => match attribute_name(code) {
+                Some(name) if subparams.is_empty() => Sgr::Attribute(code, name),
+                _ => Sgr::Unknown(group.to_owned()),
+            },
+        };
+        result.push(decoded);
+    }
+    result
+}
+
+/// Extended color in the `38;5;n` or `38;2;r;g;b` form.
+fn extended_color_semicolon<'a>(groups: &mut impl Iterator<Item = &'a str>) -> Option<SgrColor> {
+    match groups.next()? {
+        "5" => Some(SgrColor::Indexed(groups.next()?.parse().ok()?)),
+        "2" => {
+            let mut channel = || groups.next()?.parse().ok();
+            Some(SgrColor::Rgb(channel()?, channel()?, channel()?))
+        }
+        _ => None,
+    }
+}
+
+/// Extended color in the `38:5:n`, `38:2::r:g:b`, or `38:2:r:g:b` form.
+fn extended_color_colon(subparams: &[&str]) -> Option<SgrColor> {
+    let rgb = |r: &str, g: &str, b: &str| {
+        Some(SgrColor::Rgb(
+            r.parse().ok()?,
+            g.parse().ok()?,
+            b.parse().ok()?,
+        ))
+    };
+    match subparams {
+        ["5", index] => Some(SgrColor::Indexed(index.parse().ok()?)),
+        ["2", _colorspace, r, g, b, ..] => rgb(r, g, b),
+        ["2", r, g, b] => rgb(r, g, b),
+        _ => None,
+    }
+}
+
+/// SGR attribute codes and their names, shared by the decoder and explain.
+pub const SGR_ATTRIBUTES: [(u16, &str); 19] = [
+    (1, "bold"),
+    (2, "dim"),
+    (3, "italic"),
+    (4, "underline"),
+    (5, "blink"),
+    (6, "rapid blink"),
+    (7, "inverted"),
+    (8, "hidden"),
+    (9, "strikethrough"),
+    (21, "double underline"),
+    (22, "normal intensity"),
+    (23, "not italic"),
+    (24, "not underlined"),
+    (25, "not blinking"),
+    (27, "not inverted"),
+    (28, "not hidden"),
+    (29, "not strikethrough"),
+    (53, "overline"),
+    (55, "not overlined"),
+];
+
+fn attribute_name(code: u16) -> Option<&'static str> {
+    SGR_ATTRIBUTES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+}
+
+fn underline_style_name(style: u8) -> &'static str {
+    match style {
+        0 => "no underline",
+        1 => "straight underline",
+        2 => "double underline",
+        3 => "curly underline",
+        4 => "dotted underline",
+        _ => "dashed underline",
+    }
+}
+
+/// Name of a basic or bright palette color (index 0-15).
+pub fn palette_name(index: u8) -> Option<&'static str> {
+    const NAMES: [&str; 16] = [
+        "black",
+        "red",
+        "green",
+        "yellow",
+        "blue",
+        "magenta",
+        "cyan",
+        "white",
+        "bright black",
+        "bright red",
+        "bright green",
+        "bright yellow",
+        "bright blue",
+        "bright magenta",
+        "bright cyan",
+        "bright white",
+    ];
+    NAMES.get(usize::from(index)).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::fuzz::{Rng, CASES};
+
+    fn kinds(input: &[u8]) -> Vec<Kind<'_>> {
+        tokens(input).map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn sequences_are_split_from_text() {
+        let input = b"a\x1b[1;31mb\x1b]8;;uri\x07c\x1b(B\x1b[";
+        let raw: Vec<&[u8]> = tokens(input).map(|token| token.raw).collect();
+        assert_eq!(
+            raw,
+            [
+                &b"a"[..],
+                b"\x1b[1;31m",
+                b"b",
+                b"\x1b]8;;uri\x07",
+                b"c",
+                b"\x1b(B",
+                b"\x1b[",
+            ]
+        );
+        assert_eq!(
+            kinds(input),
+            [
+                Kind::Text,
+                Kind::Csi {
+                    params: b"1;31",
+                    intermediates: b"",
+                    final_byte: b'm',
+                },
+                Kind::Text,
+                Kind::Osc { data: b"8;;uri" },
+                Kind::Text,
+                Kind::Esc {
+                    intermediates: b"(",
+                    final_byte: b'B',
+                },
+                Kind::Incomplete,
+            ]
+        );
+    }
+
+    #[test]
+    fn osc_ends_at_string_terminator() {
+        assert_eq!(
+            kinds(b"\x1b]0;title\x1b\\x"),
+            [Kind::Osc { data: b"0;title" }, Kind::Text]
+        );
+        assert_eq!(kinds(b"\x1b]0;title"), [Kind::Incomplete]);
+    }
+
+    #[test]
+    fn only_plain_csi_m_is_sgr() {
+        let params = |input: &'static [u8]| {
+            tokens(input)
+                .next()
+                .unwrap()
+                .sgr_params()
+                .map(<[u8]>::to_vec)
+        };
+        assert_eq!(params(b"\x1b[1m"), Some(b"1".to_vec()));
+        assert_eq!(params(b"\x1b[m"), Some(b"".to_vec()));
+        assert_eq!(params(b"\x1b[>4;2m"), None);
+        assert_eq!(params(b"\x1b[2J"), None);
+        assert!(!tokens(b"text").next().unwrap().is_escape());
+    }
+
+    #[test]
+    fn extended_colors_in_both_forms() {
+        let rgb = Sgr::Foreground(SgrColor::Rgb(1, 2, 3));
+        assert_eq!(decode_sgr(b"38;2;1;2;3"), vec![rgb.clone()]);
+        assert_eq!(decode_sgr(b"38:2:1:2:3"), vec![rgb.clone()]);
+        assert_eq!(decode_sgr(b"38:2::1:2:3"), vec![rgb]);
+        assert_eq!(
+            decode_sgr(b"48;5;196;58:5:4"),
+            [
+                Sgr::Background(SgrColor::Indexed(196)),
+                Sgr::UnderlineColor(SgrColor::Indexed(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn basic_codes() {
+        assert_eq!(decode_sgr(b""), [Sgr::Reset]);
+        assert_eq!(
+            decode_sgr(b"0;1;4:3;91;49"),
+            [
+                Sgr::Reset,
+                Sgr::Attribute(1, "bold"),
+                Sgr::UnderlineStyle(3, "curly underline"),
+                Sgr::Foreground(SgrColor::Indexed(9)),
+                Sgr::Background(SgrColor::Default),
+            ]
+        );
+        assert_eq!(
+            decode_sgr(b"38;5;x;4:9"),
+            [Sgr::Unknown("38".into()), Sgr::Unknown("4:9".into())]
+        );
+    }
+
+    #[test]
+    fn tokens_cover_the_input() {
+        let mut rng = Rng::new(0xa451);
+        for _ in 0..CASES {
+            let input = rng.bytes(24);
+            let joined: Vec<u8> = tokens(&input)
+                .flat_map(|token| token.raw.to_vec())
+                .collect();
+            assert_eq!(joined, input);
+            assert!(tokens(&input).all(|token| !token.raw.is_empty()));
+        }
+    }
+
+    #[test]
+    fn stripping_in_chunks_matches_stripping_at_once() {
+        let mut rng = Rng::new(0x5791);
+        for _ in 0..CASES {
+            let input = rng.bytes(24);
+            let split = rng.below(input.len() + 1);
+            let mut stripper = Stripper::new(false);
+            let mut chunked = stripper.strip(&input[..split]);
+            chunked.extend(stripper.strip(&input[split..]));
+            assert_eq!(chunked, strip(&input), "{input:?} split at {split}");
+        }
+    }
+
+    #[test]
+    fn stripped_text_has_no_escapes() {
+        let mut rng = Rng::new(0xe5c);
+        for _ in 0..CASES {
+            let input = rng.bytes(24);
+            let stripped = strip(&input);
+            assert!(!stripped.contains(&ESC), "{input:?}");
+            assert_eq!(strip(&stripped), stripped);
+        }
+    }
+
+    #[test]
+    fn decoding_never_panics() {
+        let mut rng = Rng::new(0xdec0);
+        for _ in 0..CASES {
+            let params = rng.string(
+                &[
+                    "", "0", "1", "38", "48", "58", "5", "2", ";", ":", "255", "999",
+                ],
+                10,
+            );
+            decode_sgr(params.as_bytes());
+        }
+    }
+}
@@ -1,7 +1,23 @@
+use anyhow::Result;
 use clap::Parser;
-use termcolors::format;
+use std::io::Write;
+use termcolors::{format, terminal};
 
-fn main() {
-    let args = format::Args::parse();
-    print!("{}", format::format(args));
+fn main() -> Result<()> {
+    let segments = format::segment_args(std::env::args_os());
+    if segments.len() > 1 {
+        let mut segments: Vec<format::Args> =
+            segments.into_iter().map(format::Args::parse_from).collect();
+        if !terminal::enable_vt_processing() {
+            segments.iter_mut().for_each(format::Args::disable_colors);
+        }
+        let output = format::format_segments(segments)?;
+        std::io::stdout().lock().write_all(output.as_bytes())?;
+        return Ok(());
+    }
+    let code = format::print(format::Args::parse())?;
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
 }
@@ -0,0 +1,266 @@
+use anyhow::{bail, Result};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::{
+    io::{IsTerminal, Write},
+    time::Duration,
+};
+use termcolors::{
+    color::Rgb,
+    format::{self, When},
+    names,
+    parse::ParseError,
+    table,
+    terminal::{self, TerminalCapabilities},
+};
+
+const RESET: &str = "\x1b[m";
+const SUPPORTED: &str = "\x1b[32m";
+const UNSUPPORTED: &str = "\x1b[31m";
+/// Width of the swatch of the color info.
+const SWATCH_WIDTH: usize = 32;
+
+#[derive(Debug, Parser)]
+#[clap(about = "Terminal color tools.")]
+#[clap(author = "https://ariel.ninja")]
+#[clap(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// When to use escape sequences (auto: only to a terminal, and as each subcommand decides)
+    #[arg(long, global = true, value_enum, default_value_t = When::Auto)]
+    color: When,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Show what the terminal supports
+    Caps {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+        /// Confirm by querying the terminal (requires a controlling terminal)
+        #[arg(short, long)]
+        query: bool,
+        /// Query timeout in milliseconds
+        #[arg(long, default_value_t = 200)]
+        timeout: u64,
+    },
+    /// Format text (as printcolor)
+    Format(Box<format::Args>),
+    /// Display color tables (as colortable)
+    Table(Box<table::Args>),
+    /// Print a color in other notations
+    Convert {
+        /// The color: #RRGGBB, rgb(R, G, B), hsv(H, S, V), hsl(H, S, L), a 256-color palette
+        /// index, or a CSS name
+        #[arg(id = "input", value_name = "COLOR", value_parser = color)]
+        color: Rgb,
+        /// Notations to print, one per line (comma-separated)
+        #[arg(short, long, value_enum, value_delimiter = ',', default_value = "hex")]
+        to: Vec<Notation>,
+    },
+    /// Show a swatch of a color with its notations, nearest palette colors, and luminance
+    Info {
+        /// The color (as for convert)
+        #[arg(id = "input", value_name = "COLOR", value_parser = color)]
+        color: Rgb,
+    },
+}
+
+/// Notation of a color
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+enum Notation {
+    /// #rrggbb
+    Hex,
+    /// rgb(R, G, B)
+    Rgb,
+    /// hsv(H, S%, V%)
+    Hsv,
+    /// hsl(H, S%, L%)
+    Hsl,
+    /// Nearest code of the 6×6×6 cube or greyscale ramp of the 256-color palette
+    Ansi256,
+    /// Nearest basic color code (0 to 15)
+    Ansi16,
+    /// Nearest CSS color name
+    Name,
+}
+
+impl Notation {
+    fn format(self, color: Rgb) -> String {
+        let percent = |x: f64| (x * 100.0).round();
+        match self {
+            Notation::Hex => color.to_string(),
+            Notation::Rgb => format!("rgb({}, {}, {})", color.0, color.1, color.2),
+            Notation::Hsv => {
+                let (h, s, v) = color.to_hsv();
+                format!(
+                    "hsv({}, {}%, {}%)",
+                    (h * 360.0).round(),
+                    percent(s),
+                    percent(v)
+                )
+            }
+            Notation::Hsl => {
+                let (h, s, l) = color.to_hsl();
+                format!(
+                    "hsl({}, {}%, {}%)",
+                    (h * 360.0).round(),
+                    percent(s),
+                    percent(l)
+                )
+            }
+            Notation::Ansi256 => color.nearest_ansi_code().to_string(),
+            Notation::Ansi16 => color.nearest_basic_code().to_string(),
+            Notation::Name => names::nearest_named(color).to_owned(),
+        }
+    }
+}
+
+/// Lines of the color info, with swatches and samples if colored.
+fn info(color: Rgb, colored: bool) -> Vec<String> {
+    let paint = |codes: &str, text: &str| match colored {
+        true => format!("{codes}{text}{RESET}"),
+        false => text.to_owned(),
+    };
+    let palette = |code: u8| match colored {
+        true => format!("{code:<4}{}", paint(&format!("\x1b[48;5;{code}m"), "    ")),
+        false => code.to_string(),
+    };
+    let (readable, name) = [(Rgb(0, 0, 0), "black"), (Rgb(255, 255, 255), "white")]
+        .into_iter()
+        .max_by(|(a, _), (b, _)| {
+            color
+                .contrast_ratio(*a)
+                .total_cmp(&color.contrast_ratio(*b))
+        })
+        .expect("two candidates");
+    let sample = format!("{name} {:.1}:1", color.contrast_ratio(readable));
+    let swatch = paint(&color.bg(), &" ".repeat(SWATCH_WIDTH));
+    let swatches = if colored { 2 } else { 0 };
+    std::iter::repeat_n(swatch, swatches)
+        .chain([
+            format!("hex         {}", Notation::Hex.format(color)),
+            format!("rgb         {}", Notation::Rgb.format(color)),
+            format!("hsv         {}", Notation::Hsv.format(color)),
+            format!("hsl         {}", Notation::Hsl.format(color)),
+            format!("ansi 256    {}", palette(color.nearest_ansi_code())),
+            format!("ansi 16     {}", palette(color.nearest_basic_code())),
+            format!("luminance   {:.4}", color.relative_luminance()),
+            format!("name        {}", Notation::Name.format(color)),
+            format!(
+                "readable    {}",
+                match colored {
+                    true => paint(
+                        &format!("{}{}", color.bg(), readable.fg()),
+                        &format!(" {sample} ")
+                    ),
+                    false => sample,
+                }
+            ),
+        ])
+        .collect()
+}
+
+/// Parse a color: a 256-color palette index, a CSS name, or as `Rgb` parses it.
+fn color(text: &str) -> Result<Rgb, ParseError> {
+    let trimmed = text.trim();
+    if let Ok(index) = trimmed.parse::<u8>() {
+        return Ok(Rgb::from_ansi_code(index));
+    }
+    names::parse(text)
+}
+
+/// Whether to use escape sequences, given whether the output is a terminal.
+fn colored(when: When, terminal: bool) -> bool {
+    match when {
+        When::Always => true,
+        When::Never => false,
+        When::Auto => terminal,
+    }
+}
+
+fn main() -> Result<()> {
+    // Only format takes segments, as printcolor does
+    let mut segments = format::segment_args(std::env::args_os()).into_iter();
+    let matches = Cli::command().get_matches_from(segments.next().unwrap_or_default());
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    let segments: Vec<Vec<_>> = segments.collect();
+    if !segments.is_empty() && !matches!(cli.command, Command::Format(_)) {
+        bail!("{} can only be used with format", format::SEGMENT_SEPARATOR);
+    }
+    match cli.command {
+        Command::Caps {
+            json,
+            query,
+            timeout,
+        } => {
+            let capabilities = if query {
+                TerminalCapabilities::detect_with_queries(Duration::from_millis(timeout))
+            } else {
+                TerminalCapabilities::detect()
+            };
+            if json {
+                println!("{}", capabilities.to_json());
+                return Ok(());
+            }
+            for (name, supported) in capabilities.features() {
+                let (color, answer) = match supported {
+                    true => (SUPPORTED, "yes"),
+                    false => (UNSUPPORTED, "no"),
+                };
+                if colored(cli.color, capabilities.tty) {
+                    println!("{name:<12}{color}{answer}{RESET}");
+                } else {
+                    println!("{name:<12}{answer}");
+                }
+            }
+        }
+        Command::Convert { color, to } => {
+            for notation in to {
+                println!("{}", notation.format(color));
+            }
+        }
+        Command::Info { color } => {
+            for line in info(color, colored(cli.color, std::io::stdout().is_terminal())) {
+                println!("{line}");
+            }
+        }
+        Command::Format(args) => {
+            let mut args = *args;
+            if cli.color != When::Auto {
+                args.set_when(cli.color);
+            }
+            if segments.is_empty() {
+                let code = format::print(args)?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
+                return Ok(());
+            }
+            let mut segments: Vec<format::Args> = std::iter::once(args)
+                .chain(segments.into_iter().map(|segment| {
+                    let mut args = format::Args::parse_from(segment);
+                    if cli.color != When::Auto {
+                        args.set_when(cli.color);
+                    }
+                    args
+                }))
+                .collect();
+            if !terminal::enable_vt_processing() {
+                segments.iter_mut().for_each(format::Args::disable_colors);
+            }
+            let output = format::format_segments(segments)?;
+            std::io::stdout().lock().write_all(output.as_bytes())?;
+        }
+        // The global --color is the table's own
+        Command::Table(_) => {
+            table::print(
+                matches
+                    .subcommand_matches("table")
+                    .expect("table subcommand"),
+            )?;
+        }
+    }
+    Ok(())
+}
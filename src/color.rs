@@ -0,0 +1,1255 @@
+use crate::parse::ParseError;
+use std::{f64::consts::TAU, fmt, str::FromStr};
+
+const CODE_END: &str = "m";
+
+/// The basic and bright colors (0 to 15) of the 256-color palette, as shown by xterm.
+const BASIC_COLORS: [Rgb; 16] = [
+    Rgb(0, 0, 0),
+    Rgb(205, 0, 0),
+    Rgb(0, 205, 0),
+    Rgb(205, 205, 0),
+    Rgb(0, 0, 238),
+    Rgb(205, 0, 205),
+    Rgb(0, 205, 205),
+    Rgb(229, 229, 229),
+    Rgb(127, 127, 127),
+    Rgb(255, 0, 0),
+    Rgb(0, 255, 0),
+    Rgb(255, 255, 0),
+    Rgb(92, 92, 255),
+    Rgb(255, 0, 255),
+    Rgb(0, 255, 255),
+    Rgb(255, 255, 255),
+];
+
+/// Channel levels of the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// 24-bit color.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Parse `#RRGGBB`, `RRGGBB`, or the short form `#RGB`.
+    pub fn from_hex(hex: &str) -> Result<Self, ColorParseError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if let Some((i, digit)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+            return Err(ColorParseError::BadHex {
+                color: hex.to_owned(),
+                offset: hex.len() - digits.len() + i,
+                digit,
+            });
+        }
+        match digits.len() {
+            6 => {
+                let channel = |i: usize| {
+                    u8::from_str_radix(&digits[i..i + 2], 16).expect("validated hex digits")
+                };
+                Ok(Self(channel(0), channel(2), channel(4)))
+            }
+            3 if digits.len() < hex.len() => {
+                let channel = |i: usize| {
+                    17 * u8::from_str_radix(&digits[i..i + 1], 16).expect("validated hex digits")
+                };
+                Ok(Self(channel(0), channel(1), channel(2)))
+            }
+            _ => Err(ColorParseError::HexLength {
+                color: hex.to_owned(),
+                offset: 0,
+            }),
+        }
+    }
+
+    /// Color of red, green, and blue from 0 to 1 (clamped), rounded to the nearest levels.
+    pub fn from_channels(channels: [f64; 3]) -> Self {
+        let [r, g, b] = channels.map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Self(r, g, b)
+    }
+
+    /// Color from hue (0 to 1), saturation, and value.
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Self {
+        let c = v * s;
+        let (r, g, b) = hue_chroma(h, c, v - c);
+        Self::from_channels([r, g, b])
+    }
+
+    /// Color from hue (0 to 1), saturation, and lightness.
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Self {
+        let c = (1.0 - f64::abs(2.0 * l - 1.0)) * s;
+        let (r, g, b) = hue_chroma(h, c, l - c / 2.0);
+        Self::from_channels([r, g, b])
+    }
+
+    /// Color from OKLCH lightness (0 to 1), chroma, and hue (0 to 1).
+    ///
+    /// Colors outside of sRGB have their chroma reduced until they fit.
+    pub fn from_oklch(l: f64, c: f64, h: f64) -> Self {
+        let linear = |c: f64| oklab_to_linear_srgb(l, c * (h * TAU).cos(), c * (h * TAU).sin());
+        let in_gamut = |rgb: [f64; 3]| rgb.iter().all(|x| (-1e-6..=1.0 + 1e-6).contains(x));
+        let mut rgb = linear(c);
+        if !in_gamut(rgb) {
+            let (mut low, mut high) = (0.0, c);
+            for _ in 0..20 {
+                let mid = (low + high) / 2.0;
+                if in_gamut(linear(mid)) {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            rgb = linear(low);
+        }
+        Self::from_channels(rgb.map(|x| srgb_gamma(x.clamp(0.0, 1.0))))
+    }
+
+    /// Hue (0 to 1), saturation, and value, as [`hsv`] gives them.
+    pub fn to_hsv(self) -> (f64, f64, f64) {
+        hsv(self.channels())
+    }
+
+    /// Hue (0 to 1), saturation, and lightness, as [`hsl`] gives them.
+    pub fn to_hsl(self) -> (f64, f64, f64) {
+        hsl(self.channels())
+    }
+
+    /// Red, green, and blue from 0 to 1.
+    pub fn channels(self) -> [f64; 3] {
+        [self.0, self.1, self.2].map(|c| f64::from(c) / 255.0)
+    }
+
+    /// Color of a code of the 256-color palette, with xterm's default basic colors.
+    pub fn from_ansi_code(code: u8) -> Self {
+        if code < 16 {
+            return BASIC_COLORS[usize::from(code)];
+        }
+        if code >= 232 {
+            let grey = 8 + 10 * (code - 232);
+            return Self(grey, grey, grey);
+        }
+        let index = usize::from(code - 16);
+        Self(
+            CUBE_LEVELS[index / 36],
+            CUBE_LEVELS[index / 6 % 6],
+            CUBE_LEVELS[index % 6],
+        )
+    }
+
+    /// Nearest of the basic and bright colors (0 to 15) of the 256-color palette.
+    pub fn nearest_basic_code(self) -> u8 {
+        self.nearest_code(0..=15)
+    }
+
+    /// Nearest entry of the 6x6x6 cube or the greyscale ramp (16 to 255) of the 256-color
+    /// palette.
+    pub fn nearest_ansi_code(self) -> u8 {
+        self.nearest_code(16..=255)
+    }
+
+    /// Nearest color of the greyscale ramp (232 to 255) of the 256-color palette.
+    pub fn nearest_grey_code(self) -> u8 {
+        self.nearest_code(232..=255)
+    }
+
+    fn nearest_code(self, codes: std::ops::RangeInclusive<u8>) -> u8 {
+        let first = *codes.start();
+        codes
+            .min_by_key(|code| self.perceptual_distance(Self::from_ansi_code(*code)))
+            .unwrap_or(first)
+    }
+
+    /// Squared RGB distance weighted by the mean red level ("redmean"), scaled by 256.
+    pub fn perceptual_distance(self, other: Self) -> u32 {
+        let red_mean = (u32::from(self.0) + u32::from(other.0)) / 2;
+        let dr = u32::from(self.0.abs_diff(other.0));
+        let dg = u32::from(self.1.abs_diff(other.1));
+        let db = u32::from(self.2.abs_diff(other.2));
+        (512 + red_mean) * dr * dr + 1024 * dg * dg + (767 - red_mean) * db * db
+    }
+
+    /// Linear interpolation towards other, t from 0 to 1.
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        let channel =
+            |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+        Self(
+            channel(self.0, other.0),
+            channel(self.1, other.1),
+            channel(self.2, other.2),
+        )
+    }
+
+    /// Interpolation towards other in OKLab, which looks even to the eye, t from 0 to 1.
+    pub fn lerp_oklab(self, other: Self, t: f64) -> Self {
+        let oklab = |Rgb(r, g, b): Rgb| {
+            linear_srgb_to_oklab([r, g, b].map(|c| srgb_linear(f64::from(c) / 255.0)))
+        };
+        let (from, to) = (oklab(self), oklab(other));
+        let [l, a, b] = [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t);
+        let [r, g, b] = oklab_to_linear_srgb(l, a, b)
+            .map(|c| (srgb_gamma(c.clamp(0.0, 1.0)) * 255.0).round() as u8);
+        Self(r, g, b)
+    }
+
+    /// Interpolation towards other in HSV, along the shorter way around the hue circle, t from
+    /// 0 to 1.
+    pub fn lerp_hsv(self, other: Self, t: f64) -> Self {
+        let ((h1, s1, v1), (h2, s2, v2)) = (self.to_hsv(), other.to_hsv());
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        let delta = (h2 - h1 + 0.5).rem_euclid(1.0) - 0.5;
+        Self::from_hsv((h1 + delta * t).rem_euclid(1.0), lerp(s1, s2), lerp(v1, v2))
+    }
+
+    /// The color as seen with a color vision deficiency.
+    pub fn simulate(self, deficiency: Deficiency) -> Self {
+        let rgb = self.channels().map(srgb_linear);
+        let simulated = match deficiency {
+            Deficiency::Protanopia => multiply(&PROTANOPIA, rgb),
+            Deficiency::Deuteranopia => multiply(&DEUTERANOPIA, rgb),
+            Deficiency::Tritanopia => multiply(&TRITANOPIA, rgb),
+            Deficiency::Achromatopsia => [self.relative_luminance(); 3],
+        };
+        Self::from_channels(simulated.map(|x| srgb_gamma(x.clamp(0.0, 1.0))))
+    }
+
+    /// CIE L*a*b* lightness (0 to 100), a*, and b*, relative to the D65 white point.
+    pub fn lab(self) -> [f64; 3] {
+        let [r, g, b] = self.channels().map(srgb_linear);
+        let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+        let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175_0 * b;
+        let z = (0.019_333_9 * r + 0.119_192_0 * g + 0.950_304_1 * b) / 1.088_83;
+        let f = |t: f64| {
+            const DELTA: f64 = 6.0 / 29.0;
+            if t > DELTA.powi(3) {
+                t.cbrt()
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+    }
+
+    /// Euclidean distance between the channels, each from 0 to 255.
+    pub fn rgb_distance(self, other: Self) -> f64 {
+        let [r, g, b] = [
+            self.0.abs_diff(other.0),
+            self.1.abs_diff(other.1),
+            self.2.abs_diff(other.2),
+        ]
+        .map(f64::from);
+        (r * r + g * g + b * b).sqrt()
+    }
+
+    /// CIE76 color difference: Euclidean distance in L*a*b*.
+    pub fn delta_e76(self, other: Self) -> f64 {
+        let ([l1, a1, b1], [l2, a2, b2]) = (self.lab(), other.lab());
+        ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    }
+
+    /// CIEDE2000 color difference, see [`ciede2000`].
+    pub fn delta_e2000(self, other: Self) -> f64 {
+        ciede2000(self.lab(), other.lab())
+    }
+
+    /// Luminosity (according to EIC-1931), from 0 to 1.
+    pub fn luminosity(self) -> f64 {
+        (0.2126 * f64::from(self.0) + 0.7152 * f64::from(self.1) + 0.0722 * f64::from(self.2))
+            / 255.0
+    }
+
+    /// Relative luminance of the linearized channels (WCAG 2.1), from 0 to 1.
+    pub fn relative_luminance(self) -> f64 {
+        let [r, g, b] = self.channels().map(srgb_linear);
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// WCAG 2.1 contrast ratio (1 to 21) between two colors.
+    pub fn contrast_ratio(self, other: Self) -> f64 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Escape sequence setting this as the background color.
+    pub fn bg(self) -> String {
+        self.bg_seq().to_string()
+    }
+
+    /// Escape sequence setting this as the foreground color.
+    pub fn fg(self) -> String {
+        self.fg_seq().to_string()
+    }
+
+    /// Escape sequence setting this as the background color, written without allocating.
+    pub fn bg_seq(self) -> ColorSequence {
+        ColorSequence {
+            color: self,
+            layer: 48,
+            depth: Depth::Truecolor,
+        }
+    }
+
+    /// Escape sequence setting this as the foreground color, written without allocating.
+    pub fn fg_seq(self) -> ColorSequence {
+        ColorSequence {
+            color: self,
+            layer: 38,
+            depth: Depth::Truecolor,
+        }
+    }
+}
+
+/// Color vision deficiency, for [`Rgb::simulate`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum Deficiency {
+    /// No red cones
+    Protanopia,
+    /// No green cones
+    Deuteranopia,
+    /// No blue cones
+    Tritanopia,
+    /// No color vision
+    Achromatopsia,
+}
+
+// Full severity dichromacy in linear RGB (Machado, Oliveira, and Fernandes 2009), from a model
+// of the cone (LMS) responses
+const PROTANOPIA: [[f64; 3]; 3] = [
+    [0.152_286, 1.052_583, -0.204_868],
+    [0.114_503, 0.786_281, 0.099_216],
+    [-0.003_882, -0.048_116, 1.051_998],
+];
+const DEUTERANOPIA: [[f64; 3]; 3] = [
+    [0.367_322, 0.860_646, -0.227_968],
+    [0.280_085, 0.672_501, 0.047_413],
+    [-0.011_820, 0.042_940, 0.968_881],
+];
+const TRITANOPIA: [[f64; 3]; 3] = [
+    [1.255_528, -0.076_749, -0.178_779],
+    [-0.078_411, 0.930_809, 0.147_602],
+    [0.004_733, 0.691_367, 0.303_900],
+];
+
+fn multiply(matrix: &[[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+/// Color depth of a terminal, for [`ColorSequence::depth`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Depth {
+    /// 24-bit color
+    Truecolor,
+    /// Nearest entry of the 256 color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// Nearest of the basic and bright colors
+    #[value(name = "16")]
+    Ansi16,
+}
+
+/// Escape sequence setting a foreground or background color, displayed on demand.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorSequence {
+    color: Rgb,
+    layer: u8,
+    depth: Depth,
+}
+
+impl ColorSequence {
+    /// Approximate the color at a lower color depth (24-bit by default).
+    pub fn depth(mut self, depth: Depth) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl fmt::Display for ColorSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layer = self.layer;
+        match self.depth {
+            Depth::Truecolor => {
+                let Rgb(r, g, b) = self.color;
+                write!(f, "\x1b[{layer};2;{r};{g};{b}{CODE_END}")
+            }
+            Depth::Ansi256 => {
+                let code = self.color.nearest_ansi_code();
+                write!(f, "\x1b[{layer};5;{code}{CODE_END}")
+            }
+            Depth::Ansi16 => {
+                // 30-37 and 40-47 for the basic colors, 90-97 and 100-107 for the bright ones
+                let code = self.color.nearest_basic_code();
+                let base = if code < 8 { layer - 8 } else { layer + 52 };
+                write!(f, "\x1b[{}{CODE_END}", base + code % 8)
+            }
+        }
+    }
+}
+
+/// Red, green, and blue (0 to 1) of a hue (0 to 1) with chroma c and lightness offset m.
+///
+/// Hues outside of 0 to 1 wrap around, so 1 and -1 are red like 0.
+pub fn hue_chroma(h: f64, c: f64, m: f64) -> (f64, f64, f64) {
+    // rem_euclid may round a tiny negative hue up to 1
+    let h = (h.rem_euclid(1.0) * 360.0) % 360.0;
+
+    let x = c * (1.0 - f64::abs((h / 60.0) % 2.0 - 1.0));
+    let (r_, g_, b_) = if h < 60.0 {
+        (c, x, 0.0)
+    } else if h < 120.0 {
+        (x, c, 0.0)
+    } else if h < 180.0 {
+        (0.0, c, x)
+    } else if h < 240.0 {
+        (0.0, x, c)
+    } else if h < 300.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+    (r_ + m, g_ + m, b_ + m)
+}
+
+/// Hue (0 to 1), saturation, and value of red, green, and blue (0 to 1).
+///
+/// Greys have a hue and saturation of 0.
+pub fn hsv([r, g, b]: [f64; 3]) -> (f64, f64, f64) {
+    let max = r.max(g).max(b);
+    let c = max - r.min(g).min(b);
+    let h = if c == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / c).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / c + 2.0
+    } else {
+        (r - g) / c + 4.0
+    };
+    let s = if max == 0.0 { 0.0 } else { c / max };
+    (h / 6.0, s, max)
+}
+
+/// Hue (0 to 1), saturation, and lightness of red, green, and blue (0 to 1).
+///
+/// Greys (including black and white) have a hue and saturation of 0.
+pub fn hsl(channels: [f64; 3]) -> (f64, f64, f64) {
+    let (h, s, v) = hsv(channels);
+    let l = v * (1.0 - s / 2.0);
+    let s = if l == 0.0 || l == 1.0 {
+        0.0
+    } else {
+        (v - l) / l.min(1.0 - l)
+    };
+    (h, s, l)
+}
+
+/// Red, green, and blue (0 to 1) of a blackbody at a color temperature in kelvin.
+///
+/// Uses Tanner Helland's fit of the blackbody spectrum, which holds from 1000 K to 40000 K.
+pub fn blackbody(kelvin: f64) -> [f64; 3] {
+    let t = kelvin / 100.0;
+    let red = match t <= 66.0 {
+        true => 255.0,
+        false => 329.698_727_446 * (t - 60.0).powf(-0.133_204_759_2),
+    };
+    let green = match t <= 66.0 {
+        true => 99.470_802_586_1 * t.ln() - 161.119_568_166_1,
+        false => 288.122_169_528_3 * (t - 60.0).powf(-0.075_514_849_2),
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_731_223_1 * (t - 10.0).ln() - 305.044_792_730_7
+    };
+    [red, green, blue].map(|x| x.clamp(0.0, 255.0) / 255.0)
+}
+
+/// CIEDE2000 color difference of two L*a*b* colors (Sharma, Wu, and Dalal 2005), with unit
+/// weights.
+pub fn ciede2000([l1, a1, b1]: [f64; 3], [l2, a2, b2]: [f64; 3]) -> f64 {
+    const POW25_7: f64 = 6_103_515_625.0;
+    let c_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - (c_mean.powi(7) / (c_mean.powi(7) + POW25_7)).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |a: f64, b: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+    let chromatic = c1 * c2 != 0.0;
+
+    let dl = l2 - l1;
+    let dc = c2 - c1;
+    let dh = match h2 - h1 {
+        _ if !chromatic => 0.0,
+        d if d > 180.0 => d - 360.0,
+        d if d < -180.0 => d + 360.0,
+        d => d,
+    };
+    let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if !chromatic {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(h_mean - 30.0) + 0.24 * cos(2.0 * h_mean) + 0.32 * cos(3.0 * h_mean + 6.0)
+            - 0.20 * cos(4.0 * h_mean - 63.0);
+    let rotation = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let rc = 2.0 * (c_mean.powi(7) / (c_mean.powi(7) + POW25_7)).sqrt();
+    let sl = 1.0 + 0.015 * (l_mean - 50.0).powi(2) / (20.0 + (l_mean - 50.0).powi(2)).sqrt();
+    let sc = 1.0 + 0.045 * c_mean;
+    let sh = 1.0 + 0.015 * c_mean * t;
+    let rt = -(2.0 * rotation).to_radians().sin() * rc;
+    let (dl, dc, dh) = (dl / sl, dc / sc, dh / sh);
+    (dl * dl + dc * dc + dh * dh + rt * dc * dh).sqrt()
+}
+
+/// Linear sRGB channels of an OKLab color.
+pub fn oklab_to_linear_srgb(l: f64, a: f64, b: f64) -> [f64; 3] {
+    let l_ = (l + 0.396_337_777_4 * a + 0.215_803_757_3 * b).powi(3);
+    let m_ = (l - 0.105_561_345_8 * a - 0.063_854_172_8 * b).powi(3);
+    let s_ = (l - 0.089_484_177_5 * a - 1.291_485_548_0 * b).powi(3);
+    [
+        4.076_741_662_1 * l_ - 3.307_711_591_3 * m_ + 0.230_969_929_2 * s_,
+        -1.268_438_004_6 * l_ + 2.609_757_401_1 * m_ - 0.341_319_396_5 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_614_7 * m_ + 1.707_614_701_0 * s_,
+    ]
+}
+
+/// OKLab lightness, a, and b of linear sRGB channels.
+pub fn linear_srgb_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+    let m_ = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+    let s_ = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+    [
+        0.210_454_255_3 * l_ + 0.793_617_785_0 * m_ - 0.004_072_046_8 * s_,
+        1.977_998_495_1 * l_ - 2.428_592_205_0 * m_ + 0.450_593_709_9 * s_,
+        0.025_904_037_1 * l_ + 0.782_771_766_2 * m_ - 0.808_675_766_0 * s_,
+    ]
+}
+
+/// Decode an sRGB channel to linear light.
+pub fn srgb_linear(x: f64) -> f64 {
+    if x <= 0.040_45 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear channel with the sRGB transfer function.
+pub fn srgb_gamma(x: f64) -> f64 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Formats as `#rrggbb`.
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Formats as `RRGGBB`, without the `#`.
+impl fmt::UpperHex for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+/// Parses a color as [`parse_channels`] does.
+impl FromStr for Rgb {
+    type Err = ColorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_channels(s).map(Self::from_channels)
+    }
+}
+
+/// Error from parsing a color, with the byte offset of the failing part in the parsed text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorParseError {
+    /// A character of a hex color that is not a hex digit.
+    BadHex {
+        color: String,
+        offset: usize,
+        digit: char,
+    },
+    /// A hex color with neither 3 (after a `#`) nor 6 digits.
+    HexLength { color: String, offset: usize },
+    /// `rgb(`, `hsv(`, or `hsl(` without the closing `)`.
+    Unclosed {
+        function: &'static str,
+        offset: usize,
+    },
+    /// A function of other than 3 components.
+    ComponentCount { color: String, offset: usize },
+    /// A component that is not a number.
+    Component {
+        name: &'static str,
+        value: String,
+        max: f64,
+        offset: usize,
+    },
+    /// A component below 0 or above its maximum.
+    OutOfRange {
+        name: &'static str,
+        value: String,
+        max: f64,
+        offset: usize,
+    },
+}
+
+impl ColorParseError {
+    /// Byte offset and length of the failing part.
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            Self::BadHex { offset, digit, .. } => (*offset, digit.len_utf8()),
+            Self::HexLength { color, offset } | Self::ComponentCount { color, offset } => {
+                (*offset, color.len())
+            }
+            Self::Unclosed { offset, .. } => (*offset, 0),
+            Self::Component { value, offset, .. } | Self::OutOfRange { value, offset, .. } => {
+                (*offset, value.len())
+            }
+        }
+    }
+
+    fn shifted(mut self, by: usize) -> Self {
+        match &mut self {
+            Self::BadHex { offset, .. }
+            | Self::HexLength { offset, .. }
+            | Self::Unclosed { offset, .. }
+            | Self::ComponentCount { offset, .. }
+            | Self::Component { offset, .. }
+            | Self::OutOfRange { offset, .. } => *offset += by,
+        }
+        self
+    }
+}
+
+impl fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadHex { color, digit, .. } => {
+                write!(f, "invalid hex digit '{digit}' in color '{color}'")
+            }
+            Self::HexLength { color, .. } => {
+                write!(f, "invalid hex color '{color}' (expected #RRGGBB or #RGB)")
+            }
+            Self::Unclosed { function, .. } => write!(f, "expected ')' to close '{function}('"),
+            Self::ComponentCount { color, .. } => write!(f, "expected 3 components in '{color}'"),
+            Self::Component {
+                name, value, max, ..
+            }
+            | Self::OutOfRange {
+                name, value, max, ..
+            } => write!(
+                f,
+                "{name} must be a number from 0 to {max}, found '{value}'"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ColorParseError {}
+
+impl From<ColorParseError> for ParseError {
+    fn from(error: ColorParseError) -> Self {
+        let (offset, len) = error.span();
+        ParseError::new(offset, len, error.to_string())
+    }
+}
+
+/// Name, maximum, and accepted unit suffixes of each component of `rgb()`.
+const RGB_COMPONENTS: [(&str, f64, &[&str]); 3] = [
+    ("red", 255.0, &[]),
+    ("green", 255.0, &[]),
+    ("blue", 255.0, &[]),
+];
+
+/// Name, maximum, and accepted unit suffixes of each component of `hsv()`.
+const HSV_COMPONENTS: [(&str, f64, &[&str]); 3] = [
+    ("hue", 360.0, &["°", "deg"]),
+    ("saturation", 100.0, &["%"]),
+    ("value", 100.0, &["%"]),
+];
+
+/// Name, maximum, and accepted unit suffixes of each component of `hsl()`.
+const HSL_COMPONENTS: [(&str, f64, &[&str]); 3] = [
+    ("hue", 360.0, &["°", "deg"]),
+    ("saturation", 100.0, &["%"]),
+    ("lightness", 100.0, &["%"]),
+];
+
+/// Red, green, and blue (0 to 1) of `#RRGGBB`, `#RGB`, `RRGGBB`, `rgb(R, G, B)` from 0 to 255,
+/// or `hsv(H, S, V)` or `hsl(H, S, L)` in degrees and percents (with or without the `°` and
+/// `%` suffixes).
+///
+/// Case and whitespace around the color and its components are ignored.
+pub fn parse_channels(s: &str) -> Result<[f64; 3], ColorParseError> {
+    let start = s.len() - s.trim_start().len();
+    let color = s.trim();
+    let channels = || {
+        if let Some([r, g, b]) = function_args(color, "rgb", RGB_COMPONENTS)? {
+            Ok([r / 255.0, g / 255.0, b / 255.0])
+        } else if let Some([h, sat, v]) = function_args(color, "hsv", HSV_COMPONENTS)? {
+            let c = v / 100.0 * sat / 100.0;
+            let (r, g, b) = hue_chroma((h / 360.0) % 1.0, c, v / 100.0 - c);
+            Ok([r, g, b])
+        } else if let Some([h, sat, l]) = function_args(color, "hsl", HSL_COMPONENTS)? {
+            let l = l / 100.0;
+            let c = (1.0 - f64::abs(2.0 * l - 1.0)) * sat / 100.0;
+            let (r, g, b) = hue_chroma((h / 360.0) % 1.0, c, l - c / 2.0);
+            Ok([r, g, b])
+        } else {
+            Ok(Rgb::from_hex(color)?.channels())
+        }
+    };
+    channels().map_err(|error: ColorParseError| error.shifted(start))
+}
+
+/// Components of `name(a, b, c)`, each from 0 to its maximum, or None for another function.
+fn function_args(
+    s: &str,
+    name: &'static str,
+    components: [(&'static str, f64, &[&str]); 3],
+) -> Result<Option<[f64; 3]>, ColorParseError> {
+    let Some(inner) = s
+        .get(..name.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(name))
+        .and_then(|_| s[name.len()..].trim_start().strip_prefix('('))
+    else {
+        return Ok(None);
+    };
+    let inner = inner.strip_suffix(')').ok_or(ColorParseError::Unclosed {
+        function: name,
+        offset: s.len(),
+    })?;
+    let parts: Vec<&str> = inner.split(',').collect();
+    if parts.len() != 3 {
+        return Err(ColorParseError::ComponentCount {
+            color: s.to_owned(),
+            offset: 0,
+        });
+    }
+    let mut values = [0.0; 3];
+    for ((value, part), (component, max, suffixes)) in values.iter_mut().zip(parts).zip(components)
+    {
+        let part = part.trim();
+        let offset = part.as_ptr() as usize - s.as_ptr() as usize;
+        let number = suffixes
+            .iter()
+            .find_map(|suffix| part.strip_suffix(suffix))
+            .unwrap_or(part)
+            .trim_end();
+        let number: f64 = number.parse().map_err(|_| ColorParseError::Component {
+            name: component,
+            value: part.to_owned(),
+            max,
+            offset,
+        })?;
+        if !(0.0..=max).contains(&number) {
+            return Err(ColorParseError::OutOfRange {
+                name: component,
+                value: part.to_owned(),
+                max,
+                offset,
+            });
+        }
+        *value = number;
+    }
+    Ok(Some(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test pairs of L*a*b* colors and their CIEDE2000 difference (Sharma, Wu, and Dalal 2005,
+    /// table 1).
+    const SHARMA: [([f64; 3], [f64; 3], f64); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2972, 0.0], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 1.8634, 0.5757], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2592, 0.3350], 1.0000),
+        (
+            [60.2574, -34.0099, 36.2677],
+            [60.4626, -34.1751, 39.4387],
+            1.2644,
+        ),
+        (
+            [63.0109, -31.0961, -5.8663],
+            [62.8187, -29.7946, -4.0864],
+            1.2630,
+        ),
+        (
+            [61.2901, 3.7196, -5.3901],
+            [61.4292, 2.2480, -4.9620],
+            1.8731,
+        ),
+        (
+            [35.0831, -44.1164, 3.7933],
+            [35.0232, -40.0716, 1.5901],
+            1.8645,
+        ),
+        (
+            [22.7233, 20.0904, -46.6940],
+            [23.0331, 14.9730, -42.5619],
+            2.0373,
+        ),
+        (
+            [36.4612, 47.8580, 18.3852],
+            [36.2715, 50.5065, 21.2231],
+            1.4146,
+        ),
+        (
+            [90.8027, -2.0831, 1.4410],
+            [91.1528, -1.6435, 0.0447],
+            1.4441,
+        ),
+        (
+            [90.9257, -0.5406, -0.9208],
+            [88.6381, -0.8985, -0.7239],
+            1.5381,
+        ),
+        (
+            [6.7747, -0.2908, -2.4247],
+            [5.8714, -0.0985, -2.2286],
+            0.6377,
+        ),
+        (
+            [2.0776, 0.0795, -1.1350],
+            [0.9033, -0.0636, -0.5514],
+            0.9082,
+        ),
+    ];
+
+    #[test]
+    fn ciede2000_matches_the_reference_pairs() {
+        for (i, (lab1, lab2, expected)) in SHARMA.into_iter().enumerate() {
+            let difference = ciede2000(lab1, lab2);
+            assert!(
+                (difference - expected).abs() < 5e-5,
+                "pair {}: {difference:.4}, expected {expected:.4}",
+                i + 1
+            );
+            assert_eq!(difference, ciede2000(lab2, lab1), "pair {}", i + 1);
+        }
+    }
+
+    #[test]
+    fn delta_e_of_black_and_white() {
+        let (black, white) = (Rgb(0, 0, 0), Rgb(255, 255, 255));
+        assert!((black.delta_e76(white) - 100.0).abs() < 1e-3);
+        assert!((black.delta_e2000(white) - 100.0).abs() < 1e-3);
+        assert_eq!(white.delta_e2000(white), 0.0);
+        assert!((black.rgb_distance(white) - 255.0 * 3f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contrast_of_black_and_white() {
+        let (black, white) = (Rgb(0, 0, 0), Rgb(255, 255, 255));
+        assert_eq!(black.relative_luminance(), 0.0);
+        assert_eq!(white.relative_luminance(), 1.0);
+        assert_eq!(black.contrast_ratio(white), 21.0);
+        assert_eq!(white.contrast_ratio(black), 21.0);
+        assert_eq!(white.contrast_ratio(white), 1.0);
+    }
+
+    #[test]
+    fn parses_every_form() {
+        let cases = [
+            ("#ff8800", Rgb(255, 136, 0)),
+            ("#FF8800", Rgb(255, 136, 0)),
+            ("ff8800", Rgb(255, 136, 0)),
+            ("#f80", Rgb(255, 136, 0)),
+            ("  #ff8800\n", Rgb(255, 136, 0)),
+            ("rgb(255, 136, 0)", Rgb(255, 136, 0)),
+            ("RGB( 255 ,136,0 )", Rgb(255, 136, 0)),
+            ("rgb (255,136,0)", Rgb(255, 136, 0)),
+            ("hsv(32, 100%, 100%)", Rgb(255, 136, 0)),
+            ("hsv(32deg, 100, 100)", Rgb(255, 136, 0)),
+            ("HSV(32°,100%,100%)", Rgb(255, 136, 0)),
+            ("hsv(360, 100%, 100%)", Rgb(255, 0, 0)),
+            ("hsl(32, 100%, 50%)", Rgb(255, 136, 0)),
+            ("hsl(0, 0%, 100%)", Rgb(255, 255, 255)),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(text.parse::<Rgb>(), Ok(expected), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn parse_errors_name_the_failing_part() {
+        use ColorParseError::*;
+        let hex_length = |color: &str| HexLength {
+            color: color.to_owned(),
+            offset: 0,
+        };
+        let out_of_range = |name, value: &str, max, offset| OutOfRange {
+            name,
+            value: value.to_owned(),
+            max,
+            offset,
+        };
+        let component = |name, value: &str, max, offset| Component {
+            name,
+            value: value.to_owned(),
+            max,
+            offset,
+        };
+        let cases = [
+            (
+                "",
+                hex_length(""),
+                "invalid hex color '' (expected #RRGGBB or #RGB)",
+            ),
+            (
+                "#12345",
+                hex_length("#12345"),
+                "invalid hex color '#12345' (expected #RRGGBB or #RGB)",
+            ),
+            (
+                "f80",
+                hex_length("f80"),
+                "invalid hex color 'f80' (expected #RRGGBB or #RGB)",
+            ),
+            (
+                "12g456",
+                BadHex {
+                    color: "12g456".to_owned(),
+                    offset: 2,
+                    digit: 'g',
+                },
+                "invalid hex digit 'g' in color '12g456'",
+            ),
+            (
+                "rgb(1,2)",
+                ComponentCount {
+                    color: "rgb(1,2)".to_owned(),
+                    offset: 0,
+                },
+                "expected 3 components in 'rgb(1,2)'",
+            ),
+            (
+                "rgb(1,2,3",
+                Unclosed {
+                    function: "rgb",
+                    offset: 9,
+                },
+                "expected ')' to close 'rgb('",
+            ),
+            (
+                "rgb(256,0,0)",
+                out_of_range("red", "256", 255.0, 4),
+                "red must be a number from 0 to 255, found '256'",
+            ),
+            (
+                "rgb(0,-1,0)",
+                out_of_range("green", "-1", 255.0, 6),
+                "green must be a number from 0 to 255, found '-1'",
+            ),
+            (
+                "hsv(400,1,1)",
+                out_of_range("hue", "400", 360.0, 4),
+                "hue must be a number from 0 to 360, found '400'",
+            ),
+            (
+                "hsv(10,50%,x)",
+                component("value", "x", 100.0, 11),
+                "value must be a number from 0 to 100, found 'x'",
+            ),
+            (
+                "hsl(0, 100, 101)",
+                out_of_range("lightness", "101", 100.0, 12),
+                "lightness must be a number from 0 to 100, found '101'",
+            ),
+            (
+                " rgb(1,2,x)",
+                component("blue", "x", 255.0, 9),
+                "blue must be a number from 0 to 255, found 'x'",
+            ),
+        ];
+        for (text, expected, message) in cases {
+            let error = text.parse::<Rgb>().unwrap_err();
+            assert_eq!(error, expected, "{text:?}");
+            assert_eq!(error.to_string(), message, "{text:?}");
+        }
+        let error = ParseError::from("rgb(0, 300, 0)".parse::<Rgb>().unwrap_err());
+        assert_eq!((error.offset, error.len), (7, 3));
+    }
+
+    #[test]
+    fn hsv_and_hsl_of_primaries_secondaries_black_and_white() {
+        let cases = [
+            (Rgb(255, 0, 0), (0.0, 1.0, 1.0), (0.0, 1.0, 0.5)),
+            (
+                Rgb(255, 255, 0),
+                (1.0 / 6.0, 1.0, 1.0),
+                (1.0 / 6.0, 1.0, 0.5),
+            ),
+            (Rgb(0, 255, 0), (2.0 / 6.0, 1.0, 1.0), (2.0 / 6.0, 1.0, 0.5)),
+            (
+                Rgb(0, 255, 255),
+                (3.0 / 6.0, 1.0, 1.0),
+                (3.0 / 6.0, 1.0, 0.5),
+            ),
+            (Rgb(0, 0, 255), (4.0 / 6.0, 1.0, 1.0), (4.0 / 6.0, 1.0, 0.5)),
+            (
+                Rgb(255, 0, 255),
+                (5.0 / 6.0, 1.0, 1.0),
+                (5.0 / 6.0, 1.0, 0.5),
+            ),
+            (Rgb(0, 0, 0), (0.0, 0.0, 0.0), (0.0, 0.0, 0.0)),
+            (Rgb(255, 255, 255), (0.0, 0.0, 1.0), (0.0, 0.0, 1.0)),
+        ];
+        for (color, hsv, hsl) in cases {
+            assert_eq!(color.to_hsv(), hsv, "{color}");
+            assert_eq!(color.to_hsl(), hsl, "{color}");
+            assert_eq!(Rgb::from_hsv(hsv.0, hsv.1, hsv.2), color);
+            assert_eq!(Rgb::from_hsl(hsl.0, hsl.1, hsl.2), color);
+        }
+    }
+
+    #[test]
+    fn hsv_and_hsl_round_trip_across_the_cube() {
+        let levels = (0..=255).step_by(15).chain([1, 128, 254]);
+        for r in levels.clone() {
+            for g in levels.clone() {
+                for b in levels.clone() {
+                    let color = Rgb(r, g, b);
+                    let (h, s, v) = color.to_hsv();
+                    assert!((0.0..1.0).contains(&h), "{color}: hue {h}");
+                    assert_eq!(Rgb::from_hsv(h, s, v), color);
+                    let (h, s, l) = color.to_hsl();
+                    assert!([h, s, l].iter().all(|x| (0.0..=1.0).contains(x)), "{color}");
+                    assert_eq!(Rgb::from_hsl(h, s, l), color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hues_wrap_around() {
+        assert_eq!(Rgb::from_hsv(1.0, 1.0, 1.0), Rgb(255, 0, 0));
+        assert_eq!(Rgb::from_hsv(-0.25, 1.0, 1.0), Rgb(128, 0, 255));
+        assert_eq!(Rgb::from_hsv(1.25, 1.0, 1.0), Rgb(128, 255, 0));
+        assert_eq!(Rgb::from_hsv(-1e-17, 1.0, 1.0), Rgb(255, 0, 0));
+        assert_eq!(Rgb::from_hsl(1.0, 1.0, 0.5), Rgb(255, 0, 0));
+        assert_eq!(Rgb::from_hsl(-0.25, 1.0, 0.5), Rgb(128, 0, 255));
+    }
+
+    #[test]
+    fn from_ansi_code_covers_the_palette() {
+        assert_eq!(Rgb::from_ansi_code(0), Rgb(0, 0, 0));
+        assert_eq!(Rgb::from_ansi_code(9), Rgb(255, 0, 0));
+        assert_eq!(Rgb::from_ansi_code(15), Rgb(255, 255, 255));
+        assert_eq!(Rgb::from_ansi_code(16), Rgb(0, 0, 0));
+        assert_eq!(Rgb::from_ansi_code(21), Rgb(0, 0, 255));
+        assert_eq!(Rgb::from_ansi_code(110), Rgb(135, 175, 215));
+        assert_eq!(Rgb::from_ansi_code(196), Rgb(255, 0, 0));
+        assert_eq!(Rgb::from_ansi_code(231), Rgb(255, 255, 255));
+        assert_eq!(Rgb::from_ansi_code(232), Rgb(8, 8, 8));
+        assert_eq!(Rgb::from_ansi_code(255), Rgb(238, 238, 238));
+    }
+
+    #[test]
+    fn nearest_codes_of_palette_colors_are_their_codes() {
+        for code in 0..=15 {
+            assert_eq!(Rgb::from_ansi_code(code).nearest_basic_code(), code);
+        }
+        for code in 16..=255 {
+            assert_eq!(Rgb::from_ansi_code(code).nearest_ansi_code(), code);
+        }
+        for code in 232..=255 {
+            assert_eq!(Rgb::from_ansi_code(code).nearest_grey_code(), code);
+        }
+    }
+
+    #[test]
+    fn nearest_codes_round_to_the_closest_level() {
+        assert_eq!(Rgb(250, 5, 10).nearest_ansi_code(), 196);
+        assert_eq!(Rgb(130, 170, 220).nearest_ansi_code(), 110);
+        assert_eq!(Rgb(127, 128, 129).nearest_grey_code(), 244);
+        assert_eq!(Rgb(200, 10, 10).nearest_basic_code(), 1);
+        assert_eq!(Rgb(90, 90, 250).nearest_basic_code(), 12);
+    }
+
+    #[test]
+    fn nearest_ansi_code_mapping() {
+        let cases = [
+            (Rgb(0, 0, 0), 16),
+            (Rgb(255, 255, 255), 231),
+            (Rgb(255, 0, 0), 196),
+            (Rgb(0, 255, 0), 46),
+            (Rgb(0, 0, 255), 21),
+            (Rgb(95, 135, 175), 67),
+            (Rgb(120, 0, 0), 88),
+            (Rgb(200, 100, 50), 167),
+            (Rgb(135, 135, 135), 102),
+            // Greys between the cube levels, and colors barely tinted, are on the greyscale ramp
+            (Rgb(128, 128, 128), 244),
+            (Rgb(100, 100, 100), 241),
+            (Rgb(130, 128, 126), 244),
+            (Rgb(60, 64, 62), 237),
+        ];
+        for (color, code) in cases {
+            assert_eq!(color.nearest_ansi_code(), code, "{color}");
+        }
+    }
+
+    #[test]
+    fn luminosity_and_relative_luminance() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+        let primaries = [
+            (Rgb(255, 0, 0), 0.2126),
+            (Rgb(0, 255, 0), 0.7152),
+            (Rgb(0, 0, 255), 0.0722),
+        ];
+        for (color, weight) in primaries {
+            assert!(close(color.luminosity(), weight), "{color}");
+            assert!(close(color.relative_luminance(), weight), "{color}");
+        }
+        let grey = Rgb(128, 128, 128);
+        assert!(close(grey.luminosity(), 128.0 / 255.0));
+        assert!(close(grey.relative_luminance(), 0.2159));
+        let half_red = Rgb(128, 0, 0);
+        assert!(close(half_red.luminosity(), 0.2126 * 128.0 / 255.0));
+        assert!(close(half_red.relative_luminance(), 0.2126 * 0.2159));
+    }
+
+    #[test]
+    fn from_channels_clamps_and_rounds() {
+        assert_eq!(Rgb::from_channels([-0.5, 0.5, 1.5]), Rgb(0, 128, 255));
+        assert_eq!(Rgb::from_channels([0.1, 0.2, 0.3]), Rgb(26, 51, 77));
+    }
+
+    #[test]
+    fn from_oklch_of_known_colors() {
+        assert_eq!(Rgb::from_oklch(1.0, 0.0, 0.0), Rgb(255, 255, 255));
+        assert_eq!(Rgb::from_oklch(0.0, 0.0, 0.0), Rgb(0, 0, 0));
+        assert_eq!(
+            Rgb::from_oklch(0.627_955, 0.257_683, 29.2339 / 360.0),
+            Rgb(255, 0, 0)
+        );
+        assert_eq!(
+            Rgb::from_oklch(0.866_440, 0.294_827, 142.4953 / 360.0),
+            Rgb(0, 255, 0)
+        );
+    }
+
+    #[test]
+    fn from_oklch_reduces_chroma_into_the_gamut() {
+        let color = Rgb::from_oklch(0.627_955, 1.0, 29.2339 / 360.0);
+        let [_, a, b] = linear_srgb_to_oklab(color.channels().map(srgb_linear));
+        let hue = b.atan2(a).to_degrees();
+        assert!((hue - 29.2339).abs() < 1.0, "{color}: hue {hue}");
+        assert!(color.0 > 200 && color.1 < 60 && color.2 < 60, "{color}");
+    }
+
+    #[test]
+    fn lab_of_known_colors() {
+        let close = |[l1, a1, b1]: [f64; 3], [l2, a2, b2]: [f64; 3]| {
+            (l1 - l2).abs() < 0.01 && (a1 - a2).abs() < 0.01 && (b1 - b2).abs() < 0.01
+        };
+        let cases = [
+            (Rgb(255, 255, 255), [100.0, 0.0, 0.0]),
+            (Rgb(0, 0, 0), [0.0, 0.0, 0.0]),
+            (Rgb(255, 0, 0), [53.2408, 80.0925, 67.2032]),
+            (Rgb(0, 0, 255), [32.2970, 79.1875, -107.8602]),
+        ];
+        for (color, expected) in cases {
+            assert!(close(color.lab(), expected), "{color}: {:?}", color.lab());
+        }
+    }
+
+    #[test]
+    fn simulate_keeps_greys_and_removes_color() {
+        let deficiencies = [
+            Deficiency::Protanopia,
+            Deficiency::Deuteranopia,
+            Deficiency::Tritanopia,
+            Deficiency::Achromatopsia,
+        ];
+        for deficiency in deficiencies {
+            for v in [0, 64, 128, 255] {
+                let Rgb(r, g, b) = Rgb(v, v, v).simulate(deficiency);
+                assert!(
+                    [r, g, b].iter().all(|c| c.abs_diff(v) <= 1),
+                    "{deficiency:?} {v}"
+                );
+            }
+        }
+        let Rgb(r, g, b) = Rgb(255, 0, 0).simulate(Deficiency::Achromatopsia);
+        assert!(r == g && g == b);
+        // Red and green look alike without red or green cones
+        let red_green = |deficiency| {
+            let (red, green) = (Rgb(200, 40, 40), Rgb(40, 160, 40));
+            red.simulate(deficiency)
+                .delta_e2000(green.simulate(deficiency))
+        };
+        let normal = Rgb(200, 40, 40).delta_e2000(Rgb(40, 160, 40));
+        assert!(red_green(Deficiency::Protanopia) < normal / 2.0);
+        assert!(red_green(Deficiency::Deuteranopia) < normal / 2.0);
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(Rgb(1, 2, 3).fg(), "\x1b[38;2;1;2;3m");
+        assert_eq!(Rgb(1, 2, 3).bg(), "\x1b[48;2;1;2;3m");
+        assert_eq!(Rgb(1, 2, 3).bg_seq().to_string(), Rgb(1, 2, 3).bg());
+        let orange = Rgb(255, 128, 0);
+        assert_eq!(
+            orange.bg_seq().depth(Depth::Ansi256).to_string(),
+            "\x1b[48;5;208m"
+        );
+        assert_eq!(orange.fg_seq().depth(Depth::Ansi16).to_string(), "\x1b[33m");
+        assert_eq!(
+            Rgb(250, 10, 10).fg_seq().depth(Depth::Ansi16).to_string(),
+            "\x1b[91m"
+        );
+        assert_eq!(
+            Rgb(0, 0, 128).bg_seq().depth(Depth::Ansi16).to_string(),
+            "\x1b[44m"
+        );
+        assert_eq!(format!("{orange:X}"), "FF8000");
+    }
+}
@@ -0,0 +1,232 @@
+use crate::ansi::{self, Kind, Sgr, SgrColor, Token};
+
+const RESET: &str = "\x1b[m";
+const LABEL_DIM: &str = "\x1b[2m";
+
+/// Annotated breakdown of text containing escape sequences, one token per line.
+///
+/// Colors are shown with a swatch unless plain.
+pub fn explain_input(input: &[u8], plain: bool) -> String {
+    let mut result = String::new();
+    for token in ansi::tokens(input) {
+        let (label, description) = describe(&token, plain);
+        let raw = printable(token.raw);
+        if plain {
+            result.push_str(&format!("{label:<8}{raw}"));
+        } else {
+            result.push_str(&format!("{LABEL_DIM}{label:<8}{RESET}{raw}"));
+        }
+        if !description.is_empty() {
+            result.push_str("  ");
+            result.push_str(&description);
+        }
+        result.push('\n');
+    }
+    result
+}
+
+fn describe(token: &Token, plain: bool) -> (&'static str, String) {
+    if let Some(params) = token.sgr_params() {
+        let description: Vec<String> = ansi::decode_sgr(params)
+            .iter()
+            .map(|sgr| describe_sgr(sgr, plain))
+            .collect();
+        return ("sgr", description.join(", "));
+    }
+    match token.kind {
+        Kind::Text => ("text", String::new()),
+        Kind::Csi {
+            params,
+            intermediates,
+            final_byte,
+        } => ("csi", describe_csi(params, intermediates, final_byte)),
+        Kind::Osc { data } => ("osc", describe_osc(data)),
+        Kind::ControlString { introducer, .. } => {
+            let name = match introducer {
+                b'P' => "device control string",
+                b'X' => "start of string",
+                b'^' => "privacy message",
+                _ => "application program command",
+            };
+            ("string", name.to_owned())
+        }
+        Kind::Esc {
+            intermediates,
+            final_byte,
+        } => ("esc", describe_esc(intermediates, final_byte)),
+        Kind::Unknown => ("unknown", hex_bytes(token.raw)),
+        Kind::Incomplete => ("partial", hex_bytes(token.raw)),
+    }
+}
+
+fn describe_sgr(sgr: &Sgr, plain: bool) -> String {
+    match sgr {
+        Sgr::Reset => "reset".to_owned(),
+        Sgr::Attribute(_, name) | Sgr::UnderlineStyle(_, name) => (*name).to_owned(),
+        Sgr::Foreground(color) => format!("foreground {}", describe_color(color, plain)),
+        Sgr::Background(color) => format!("background {}", describe_color(color, plain)),
+        Sgr::UnderlineColor(color) => format!("underline color {}", describe_color(color, plain)),
+        Sgr::Unknown(params) => format!("unknown ({params})"),
+    }
+}
+
+fn describe_color(color: &SgrColor, plain: bool) -> String {
+    let (name, swatch) = match *color {
+        SgrColor::Default => return "default".to_owned(),
+        SgrColor::Indexed(index) => {
+            let name = match ansi::palette_name(index) {
+                Some(name) => format!("{name} ({index})"),
+                None => format!("{index}"),
+            };
+            (name, format!("\x1b[48;5;{index}m"))
+        }
+        SgrColor::Rgb(r, g, b) => (
+            format!("#{r:02x}{g:02x}{b:02x}"),
+            format!("\x1b[48;2;{r};{g};{b}m"),
+        ),
+    };
+    if plain {
+        name
+    } else {
+        format!("{name} {swatch}  {RESET}")
+    }
+}
+
+fn describe_csi(params: &[u8], intermediates: &[u8], final_byte: u8) -> String {
+    let private = params.first() == Some(&b'?');
+    let name = match (final_byte, private, intermediates) {
+        (b'A', false, []) => "cursor up",
+        (b'B', false, []) => "cursor down",
+        (b'C', false, []) => "cursor forward",
+        (b'D', false, []) => "cursor back",
+        (b'E', false, []) => "cursor next line",
+        (b'F', false, []) => "cursor previous line",
+        (b'G', false, []) => "cursor column",
+        (b'H' | b'f', false, []) => "cursor position",
+        (b'J', false, []) => "erase in display",
+        (b'K', false, []) => "erase in line",
+        (b'S', false, []) => "scroll up",
+        (b'T', false, []) => "scroll down",
+        (b's', false, []) => "save cursor",
+        (b'u', false, []) => "restore cursor",
+        (b'n', false, []) => "device status report",
+        (b'c', false, []) => "device attributes",
+        (b'q', false, b" ") => "cursor style",
+        (b'h', true, []) => "set private mode",
+        (b'l', true, []) => "reset private mode",
+        (b'h', false, []) => "set mode",
+        (b'l', false, []) => "reset mode",
+        _ => "unrecognized control sequence",
+    };
+    if params.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{name} ({})", String::from_utf8_lossy(params))
+    }
+}
+
+fn describe_osc(data: &[u8]) -> String {
+    let data = String::from_utf8_lossy(data);
+    let (command, rest) = data.split_once(';').unwrap_or((&data, ""));
+    match command {
+        "0" => format!("set icon name and window title \"{rest}\""),
+        "1" => format!("set icon name \"{rest}\""),
+        "2" => format!("set window title \"{rest}\""),
+        "4" => format!("palette color ({rest})"),
+        "7" => format!("working directory {rest}"),
+        "8" => {
+            let (params, uri) = rest.split_once(';').unwrap_or((rest, ""));
+            match (uri.is_empty(), params.is_empty()) {
+                (true, _) => "hyperlink end".to_owned(),
+                (false, true) => format!("hyperlink {uri}"),
+                (false, false) => format!("hyperlink {uri} ({params})"),
+            }
+        }
+        "10" => format!("foreground color ({rest})"),
+        "11" => format!("background color ({rest})"),
+        "12" => format!("cursor color ({rest})"),
+        "52" => "clipboard".to_owned(),
+        "104" => "reset palette".to_owned(),
+        "133" => format!("shell integration mark ({rest})"),
+        "1337" => "iTerm2 command".to_owned(),
+        command => format!("unrecognized command {command}"),
+    }
+}
+
+fn describe_esc(intermediates: &[u8], final_byte: u8) -> String {
+    let name = match (intermediates, final_byte) {
+        ([], b'7') => "save cursor",
+        ([], b'8') => "restore cursor",
+        ([], b'c') => "reset terminal",
+        ([], b'D') => "index",
+        ([], b'E') => "next line",
+        ([], b'M') => "reverse index",
+        ([], b'=') => "application keypad",
+        ([], b'>') => "normal keypad",
+        ([], b'\\') => "string terminator",
+        ([b'(' | b')' | b'*' | b'+'], _) => "designate character set",
+        _ => "unrecognized escape sequence",
+    };
+    name.to_owned()
+}
+
+/// Raw bytes with escape and control characters made visible.
+fn printable(raw: &[u8]) -> String {
+    let mut result = String::new();
+    for ch in String::from_utf8_lossy(raw).chars() {
+        match ch {
+            '\x1b' => result.push_str("\\e"),
+            '\x07' => result.push_str("\\a"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\\' => result.push_str("\\\\"),
+            ch if ch.is_control() => result.push_str(&format!("\\x{:02x}", u32::from(ch))),
+            ch => result.push(ch),
+        }
+    }
+    result
+}
+
+fn hex_bytes(raw: &[u8]) -> String {
+    let bytes: Vec<String> = raw.iter().map(|b| format!("{b:02x}")).collect();
+    format!("bytes {}", bytes.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_token_on_its_own_line() {
+        let input = b"a\x1b[1;38;5;196mb\x1b[38:2::1:2:3m\x1b]8;;http://x\x1b\\l\x1b]8;;\x07\x1b[2J\x1b(B\x1b[m\x1b[";
+        assert_eq!(
+            explain_input(input, true),
+            concat!(
+                "text    a\n",
+                "sgr     \\e[1;38;5;196m  bold, foreground 196\n",
+                "text    b\n",
+                "sgr     \\e[38:2::1:2:3m  foreground #010203\n",
+                "osc     \\e]8;;http://x\\e\\\\  hyperlink http://x\n",
+                "text    l\n",
+                "osc     \\e]8;;\\a  hyperlink end\n",
+                "csi     \\e[2J  erase in display (2)\n",
+                "esc     \\e(B  designate character set\n",
+                "sgr     \\e[m  reset\n",
+                "partial \\e[  bytes 1b 5b\n",
+            )
+        );
+    }
+
+    #[test]
+    fn colors_have_swatches_unless_plain() {
+        assert_eq!(
+            explain_input(b"\x1b[31m", false),
+            "\x1b[2msgr     \x1b[m\\e[31m  foreground red (1) \x1b[48;5;1m  \x1b[m\n"
+        );
+        assert_eq!(
+            explain_input(b"\x1b[31m", true),
+            "sgr     \\e[31m  foreground red (1)\n"
+        );
+    }
+}
@@ -1,53 +1,701 @@
-use clap::Parser;
+use crate::{
+    ansi,
+    color::Rgb,
+    explain,
+    lscolors::LsColors,
+    markup::Markup,
+    meter,
+    output::RecordWriter,
+    parse::{self, ParseError},
+    styled::Styled,
+    styles::UserStyles,
+    terminal, width,
+    worddiff::{self, ChangeKind},
+};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{ArgAction, ArgGroup, Parser, ValueEnum};
+use regex::Regex;
+use std::{
+    ffi::OsString,
+    fmt::{self, Write},
+    io::{self, BufRead, IsTerminal, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
 
 const CODE_START: &str = "\x1b[";
 const CODE_END: &str = "m";
-const RESET: &str = "\x1b[m";
+/// Escape sequence resetting all colors and formatting options.
+pub const RESET: &str = "\x1b[m";
+/// Escape sequence resetting only the underline color.
+pub const RESET_UNDERLINE_COLOR: &str = "\x1b[59m";
+const WORD_DIFF_INSERT: &str = "42";
+const WORD_DIFF_REMOVE: &str = "31;9";
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_METER_GRADIENT: [Rgb; 3] = [Rgb(0, 255, 0), Rgb(255, 255, 0), Rgb(255, 0, 0)];
+// Room for the longest escape sequences around the text
+const RESERVED_CAPACITY: usize = 32;
 const AFTER_LONG_HELP: &str = "\x1b[1;4mColors (use uppercase for brighter color):\x1b[0m
 blac(k), (w)hite, (r)ed, (g)reen, (b)lue, (y)ellow, (c)yan, (m)agenta
+or a 256-color palette index (0-255), or hex #RRGGBB, RRGGBB, or #RGB,
+or rgb(R, G, B) from 0 to 255, or hsv(H, S, V) or hsl(H, S, L) in degrees and percents,
+or grey:NN for a grey of NN percent lightness (0-100),
+or default (d) for the terminal's default color
 
 \x1b[1;4mFormatting options\x1b[0m:
-(b)old, (d)im, (u)nderline, (i)talic, (s)trikethrough
+(b)old, (d)im, (u)nderline, (i)talic, in(v)erted, (s)trikethrough,
+(bl)ink, (h)idden, (o)verline, double-underline (uu)
 
 \x1b[1;4mStyles\x1b[0m:
-ok, notice, error, warn, info, debug";
+ok, notice, error, warn, info, debug
 
-#[derive(Debug, Parser)]
+\x1b[1;4mMarkup\x1b[0m:
+Tags are the names above or their short aliases, fg:COLOR, bg:COLOR, or style:STYLE,
+and {b} is bold (use {blue} or {fg:b} for blue):
+printcolor --markup '{b}Done:{/} {g}3 passed{/}, {fg:R}1 failed{/}'
+
+\x1b[1;4mColumns\x1b[0m:
+Format a field of every line of stdin, keeping the spacing of the line:
+df -h | printcolor --column 5 -f red
+ps aux | printcolor --column -1:bold,cyan
+
+\x1b[1;4mDiffs\x1b[0m:
+Color the added, removed, and header lines of a diff:
+git diff --no-color | printcolor --diff --diff-add bright-green
+
+\x1b[1;4mLogs\x1b[0m:
+Color lines by their level (error, warn, info, debug, trace), or only the level:
+tail -f app.log | printcolor --log --log-scope token --map fatal=red,bold
+
+\x1b[1;4mSegments\x1b[0m:
+Separate groups of arguments with --then to format each group as a segment of one line:
+printcolor -f green OK --then ': ' --then -o bold \"$file\"
+
+User styles are sections of ~/.config/termcolor/styles.toml:
+[styles.deploy]
+foreground = \"bright-green\"
+background = \"black\"
+options = [\"bold\"]";
+
+#[derive(Debug, Clone, Parser)]
 #[clap(about = "Format text for ANSI terminal.")]
 #[clap(author = "https://ariel.ninja")]
 #[clap(hide_possible_values = true)]
 #[clap(version)]
 #[clap(after_long_help = AFTER_LONG_HELP)]
+#[clap(group(ArgGroup::new("padded").args(["width", "banner"]).multiple(true)))]
+#[clap(group(ArgGroup::new("codes").conflicts_with_all([
+    "text", "explicit_text", "template", "markup", "word_diff", "explain_input", "meter", "strip",
+    "length", "run",
+])))]
 pub struct Args {
-    /// Text to format
-    #[arg()]
+    /// Text to format (everything after the first word is taken literally) [default: stdin]
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
     text: Vec<String>,
-    /// Premade style
-    #[arg(short = 's', long, conflicts_with_all = ["foreground", "background", "options"])]
-    style: Option<Style>,
+    /// Text to format, even if it starts with a dash (repeatable)
+    #[arg(long = "text", value_name = "TEXT", allow_hyphen_values = true)]
+    explicit_text: Vec<String>,
+    /// Premade style, or one defined in ~/.config/termcolor/styles.toml (colors and options
+    /// given explicitly are layered on top)
+    #[arg(short = 's', long, value_name = "STYLE")]
+    style: Option<String>,
     /// Foreground color
     #[arg(short = 'f', long, value_name = "COLOR")]
-    foreground: Option<Color>,
+    foreground: Option<ColorSpec>,
     /// Background color
     #[arg(short = 'b', long, value_name = "COLOR")]
-    background: Option<Color>,
+    background: Option<ColorSpec>,
+    /// Underline color, which implies underline
+    #[arg(long, value_name = "COLOR")]
+    underline_color: Option<ColorSpec>,
+    /// Underline style (SGR 4:n), which implies underline
+    #[arg(long, value_name = "STYLE")]
+    underline_style: Option<UnderlineStyle>,
     /// Formatting options (comma-separated)
     #[arg(short = 'o', long, value_delimiter = ',')]
     options: Vec<FormattingOption>,
+    /// Apply the LS_COLORS entry for a key (e.g. di, ex, *.tar)
+    #[arg(long, value_name = "KEY")]
+    ls_color: Option<String>,
+    /// Apply the LS_COLORS glob entry that best matches a path
+    #[arg(long, value_name = "PATH")]
+    ls_color_for: Option<String>,
+    /// Highlight changed words between two texts (old and new, or two NUL-separated records on stdin)
+    #[arg(long)]
+    word_diff: bool,
+    /// Show removed words inline (with --word-diff)
+    #[arg(long, requires = "word_diff")]
+    show_removed: bool,
+    /// Render a meter bar for a value (after the text)
+    #[arg(long, value_name = "VALUE")]
+    meter: Option<f64>,
+    /// Maximum value of the meter
+    #[arg(long, value_name = "MAX", default_value_t = 100.0)]
+    meter_max: f64,
+    /// Width of the meter (defaults to filling the terminal)
+    #[arg(long, value_name = "WIDTH")]
+    meter_width: Option<usize>,
+    /// Meter gradient colors (comma-separated hex) [default: 00ff00,ffff00,ff0000]
+    #[arg(long, value_name = "COLORS", value_delimiter = ',', value_parser = Rgb::from_hex)]
+    meter_gradient: Vec<Rgb>,
+    /// Render a template file with inline {tag}…{/} markup and ${VAR} interpolation
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["text", "explicit_text", "word_diff"])]
+    template: Option<PathBuf>,
+    /// Validate the template without printing
+    #[arg(long, requires = "template")]
+    check_template: bool,
+    /// Parse {tag}…{/} markup in the text, with tags as in templates
+    #[arg(long, conflicts_with_all = ["template", "word_diff"])]
+    markup: bool,
+    /// Remove escape sequences from stdin: all of them, or only colors and formatting (sgr)
+    #[arg(
+        long,
+        value_name = "WHICH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all",
+        conflicts_with_all = ["text", "explicit_text", "template", "markup", "word_diff", "explain_input", "meter"]
+    )]
+    strip: Option<Strip>,
+    /// Print the width in columns of each line of the text (or stdin), or the widest (max)
+    #[arg(
+        long,
+        value_name = "WHICH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "lines",
+        conflicts_with_all = ["template", "markup", "word_diff", "explain_input", "meter", "strip"]
+    )]
+    length: Option<Length>,
+    /// Run the text as a command, then print a summary styled by its exit status
+    #[arg(
+        long,
+        conflicts_with_all = ["template", "markup", "word_diff", "meter", "strip", "length"]
+    )]
+    run: bool,
+    /// Style of the summary of a command that succeeds (as for --highlight)
+    #[arg(long, value_name = "STYLE", default_value = "ok", requires = "run")]
+    ok_style: String,
+    /// Style of the summary of a command that fails (as for --highlight)
+    #[arg(long, value_name = "STYLE", default_value = "error", requires = "run")]
+    fail_style: String,
+    /// Hide the output of the command, showing only the summary
+    #[arg(short = 'q', long, requires = "run")]
+    quiet: bool,
+    /// Explain the escape sequences in the text (or stdin)
+    #[arg(long, conflicts_with_all = ["template", "word_diff", "meter", "markup"])]
+    explain_input: bool,
+    /// Pad the text to a width in columns, inside the formatting
+    #[arg(long, value_name = "WIDTH")]
+    width: Option<usize>,
+    /// Pad every line to the width of the terminal, centered by default
+    #[arg(long)]
+    banner: bool,
+    /// Alignment of the padded text [default: left, or center with --banner]
+    #[arg(long, value_name = "ALIGN", requires = "padded")]
+    align: Option<Align>,
+    /// Character to pad with
+    #[arg(long, value_name = "CHAR", default_value_t = ' ', requires = "padded")]
+    fill: char,
+    /// Cut text longer than the width
+    #[arg(long, requires = "padded")]
+    truncate: bool,
+    /// Foreground colors to cycle through for successive words or arguments (comma-separated)
+    #[arg(
+        long,
+        value_name = "COLORS",
+        value_delimiter = ',',
+        conflicts_with_all = ["foreground", "template", "markup", "word_diff", "explain_input"]
+    )]
+    alternate: Vec<ColorSpec>,
+    /// Alternate colors by word, or by argument
+    #[arg(
+        long,
+        value_name = "UNIT",
+        default_value = "word",
+        requires = "alternate"
+    )]
+    alternate_by: AlternateBy,
+    /// Color each character with a successive hue
+    #[arg(long, conflicts_with_all = ["foreground", "alternate", "template", "markup", "word_diff", "explain_input"])]
+    rainbow: bool,
+    /// Characters per full cycle of the rainbow
+    #[arg(long, value_name = "N", default_value_t = 30, requires = "rainbow", value_parser = clap::value_parser!(u32).range(1..))]
+    rainbow_period: u32,
+    /// Hue of the first character of the rainbow, in degrees
+    #[arg(long, value_name = "DEG", default_value_t = 0.0, requires = "rainbow")]
+    rainbow_offset: f64,
+    /// Color the characters along a gradient (two or more hex colors separated by ':')
+    #[arg(
+        long,
+        value_name = "COLORS",
+        value_delimiter = ':',
+        value_parser = Rgb::from_hex,
+        conflicts_with_all = ["foreground", "alternate", "rainbow", "template", "markup", "word_diff", "explain_input"]
+    )]
+    gradient: Vec<Rgb>,
+    /// Span the gradient across each line or the whole text (stdin is always per line)
+    #[arg(
+        long,
+        value_name = "SCOPE",
+        default_value = "text",
+        requires = "gradient"
+    )]
+    gradient_scope: GradientScope,
+    /// Highlight matches of a regex with a style, or with comma-separated colors and options
+    /// (PATTERN=STYLE, repeatable; the first pattern matching a span wins)
+    #[arg(
+        long,
+        value_name = "PATTERN=STYLE",
+        conflicts_with_all = ["rainbow", "gradient", "template", "markup", "word_diff", "explain_input"]
+    )]
+    highlight: Vec<String>,
+    /// Color the lines of a diff on stdin (unified, context, or normal), leaving lines that
+    /// already have escape sequences as they are
+    #[arg(
+        long,
+        conflicts_with_all = ["column", "rainbow", "gradient", "alternate", "highlight", "template", "markup", "word_diff", "explain_input"]
+    )]
+    diff: bool,
+    /// Style of added lines of --diff (as for --highlight)
+    #[arg(long, value_name = "STYLE", default_value = "green", requires = "diff")]
+    diff_add: String,
+    /// Style of removed lines of --diff (as for --highlight)
+    #[arg(long, value_name = "STYLE", default_value = "red", requires = "diff")]
+    diff_remove: String,
+    /// Style of changed lines of context diffs (as for --highlight)
+    #[arg(
+        long,
+        value_name = "STYLE",
+        default_value = "yellow",
+        requires = "diff"
+    )]
+    diff_change: String,
+    /// Style of hunk headers of --diff (as for --highlight)
+    #[arg(long, value_name = "STYLE", default_value = "cyan", requires = "diff")]
+    diff_hunk: String,
+    /// Style of file headers of --diff (as for --highlight)
+    #[arg(long, value_name = "STYLE", default_value = "bold", requires = "diff")]
+    diff_header: String,
+    /// Color the lines of a log on stdin by their level (error, warn, info, debug, or trace)
+    #[arg(
+        long,
+        conflicts_with_all = ["diff", "column", "rainbow", "gradient", "alternate", "highlight", "template", "markup", "word_diff", "explain_input"]
+    )]
+    log: bool,
+    /// Color the whole line of --log, or only the level
+    #[arg(long, value_name = "SCOPE", default_value = "line", requires = "log")]
+    log_scope: LogScope,
+    /// Style of the lines of a --log level (LEVEL=STYLE as for --highlight, repeatable), for
+    /// other levels or to override the premade styles
+    #[arg(long, value_name = "LEVEL=STYLE", requires = "log")]
+    map: Vec<String>,
+    /// Format only a field of every line of stdin: N with the colors and options, or N:STYLE
+    /// (as for --highlight); N counts from 1, or from the end if negative (repeatable)
+    #[arg(
+        long,
+        value_name = "N[:STYLE]",
+        allow_hyphen_values = true,
+        conflicts_with_all = ["rainbow", "gradient", "alternate", "highlight", "template", "markup", "word_diff", "explain_input"]
+    )]
+    column: Vec<String>,
+    /// Separator of the fields of --column: whitespace (any run of it), tab, or a character
+    #[arg(
+        long,
+        value_name = "DELIMITER",
+        default_value = "whitespace",
+        value_parser = delimiter,
+        requires = "column"
+    )]
+    delimiter: Delimiter,
+    /// Text before every line
+    #[arg(long, value_name = "TEXT", allow_hyphen_values = true)]
+    prefix: Option<String>,
+    /// Text after every line
+    #[arg(long, value_name = "TEXT", allow_hyphen_values = true)]
+    suffix: Option<String>,
+    /// Style of the prefix (as for --highlight)
+    #[arg(long, value_name = "STYLE")]
+    prefix_style: Option<String>,
+    /// Style of the suffix (as for --highlight)
+    #[arg(long, value_name = "STYLE")]
+    suffix_style: Option<String>,
+    /// Start the prefix with the local time (HH:MM:SS) of every line
+    #[arg(long)]
+    timestamp: bool,
+    /// Make the text an OSC 8 hyperlink to a URL
+    #[arg(long, value_name = "URL", value_parser = osc_param)]
+    link: Option<String>,
+    /// Hyperlink id, so terminals highlight all parts of the link together
+    #[arg(long, value_name = "ID", requires = "link", value_parser = link_id)]
+    link_id: Option<String>,
+    /// Interpret backslash escapes in the text (\n, \t, \r, \\, \e, \0NNN, \xHH)
+    #[arg(short = 'e', long)]
+    escapes: bool,
     /// Reset formatting before text
     #[arg(short = 'R', long)]
     reset: bool,
     /// Do not reset formatting after text
     #[arg(short = 'r', long)]
     no_reset: bool,
-    /// Do not print newline
+    /// Print only the escape sequence for the colors and options, without text or reset
+    #[arg(long, group = "codes")]
+    code_only: bool,
+    /// Print only the escape sequence that resets formatting
+    #[arg(long, group = "codes")]
+    reset_code: bool,
+    /// Print shell definitions of the escape sequences of every style (premade and user)
+    #[arg(long, value_name = "SHELL", group = "codes")]
+    export_styles: Option<Shell>,
+    /// Prefix of the exported variable names
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "STYLE_",
+        requires = "export_styles"
+    )]
+    export_prefix: String,
+    /// Reset the underline color after text (for use with --no-reset)
+    #[arg(long)]
+    reset_underline_color: bool,
+    /// Read NUL-separated records from stdin instead of lines
+    #[arg(short = '0', long)]
+    null: bool,
+    /// Separate the records read with --null by NUL (or by newlines with false)
+    #[arg(
+        long,
+        value_name = "BOOL",
+        default_value_t = true,
+        action = ArgAction::Set,
+        requires = "null"
+    )]
+    null_output: bool,
+    /// Do not print newline (nor the reset, given no text, to print only the codes)
     #[arg(short = 'n', long)]
     no_newline: bool,
+    /// Print newline as CRLF (default on Windows)
+    #[arg(long, conflicts_with = "lf")]
+    crlf: bool,
+    /// Print newline as LF (default elsewhere)
+    #[arg(long)]
+    lf: bool,
+    /// When to use escape sequences (auto: only to a terminal or when escaped for a prompt,
+    /// and unless NO_COLOR is set)
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    when: When,
+    /// Print escape sequences as they are (raw), as `\e` text (literal), or as text wrapped
+    /// for bash or zsh prompts
+    #[arg(long, value_name = "STYLE", default_value = "raw")]
+    escape: Escape,
+    /// Print bright named colors as bold normal foregrounds and normal backgrounds, for
+    /// terminals without the bright codes (auto: unless the terminal advertises 16 colors or
+    /// more)
+    #[arg(long, value_name = "MAPPING")]
+    compat: Option<Compat>,
+    /// Omit all escape sequences
+    #[arg(skip)]
+    plain: bool,
+}
+
+impl Args {
+    /// Omit all escape sequences, printing only the text.
+    pub fn disable_colors(&mut self) {
+        self.plain = true;
+    }
+
+    /// Set when to use escape sequences, as `--when` does.
+    pub fn set_when(&mut self, when: When) {
+        self.when = when;
+    }
+
+    /// Whether `--when` (or NO_COLOR) disables escape sequences.
+    fn colors_disabled(&self) -> bool {
+        match self.when {
+            When::Always => false,
+            When::Never => true,
+            When::Auto => {
+                // Escaped output and codes on their own are captured rather than shown
+                (self.escape == Escape::Raw
+                    && !self.code_only
+                    && !self.reset_code
+                    && !io::stdout().is_terminal())
+                    || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+            }
+        }
+    }
+
+    /// Whether to run the text as a command (see [`run`]).
+    pub fn runs(&self) -> bool {
+        self.run
+    }
+
+    /// Whether to strip escape sequences from stdin (see [`strip_stream`]).
+    pub fn strips(&self) -> bool {
+        self.strip.is_some()
+    }
+
+    /// Whether lines are annotated with a prefix or suffix, so should be flushed as they arrive.
+    pub fn annotates_lines(&self) -> bool {
+        self.prefix.is_some() || self.suffix.is_some() || self.timestamp
+    }
+
+    /// Whether lines of stdin should be flushed as they arrive: when annotated, and for logs.
+    pub fn flushes_lines(&self) -> bool {
+        self.annotates_lines() || self.log
+    }
+
+    /// Whether bright named colors are printed as bold normal colors.
+    fn bold_bright(&self) -> bool {
+        match self.compat {
+            None => false,
+            Some(Compat::BoldBright) => true,
+            Some(Compat::Auto) => !terminal::TerminalCapabilities::detect().bright,
+        }
+    }
+
+    /// Colors and formatting options, with bright named colors mapped to normal ones for
+    /// --compat bold-bright (bold in the foreground, as backgrounds have no bold).
+    fn compat_props(&self) -> (Option<ColorSpec>, Option<ColorSpec>, Vec<FormattingOption>) {
+        let mut options = self.options.clone();
+        if !self.bold_bright() {
+            return (self.foreground, self.background, options);
+        }
+        let normal = |color: Option<ColorSpec>| match color {
+            Some(ColorSpec::Named(color)) => color.normal().map(ColorSpec::Named),
+            _ => None,
+        };
+        let foreground = match normal(self.foreground) {
+            Some(color) => {
+                if !options.contains(&FormattingOption::Bold) {
+                    options.push(FormattingOption::Bold);
+                }
+                Some(color)
+            }
+            None => self.foreground,
+        };
+        let background = normal(self.background).or(self.background);
+        (foreground, background, options)
+    }
+
+    /// The flag of a mode that only formats the lines of stdin, if any.
+    fn line_mode(&self) -> Option<&'static str> {
+        if !self.column.is_empty() {
+            Some("--column")
+        } else if self.diff {
+            Some("--diff")
+        } else if self.log {
+            Some("--log")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the text is read from stdin: when the only text is `-`, or when no text is given
+    /// and stdin is not a terminal.
+    ///
+    /// Modes with their own input (templates, word diffs, markup, explaining, lengths, and meters)
+    /// and modes without input (codes only and exports) never are.
+    pub fn reads_stdin(&self) -> bool {
+        if self.template.is_some()
+            || self.word_diff
+            || self.markup
+            || self.explain_input
+            || self.length.is_some()
+            || self.code_only
+            || self.reset_code
+            || self.export_styles.is_some()
+            || self.meter.is_some()
+        {
+            return false;
+        }
+        match (&self.text[..], &self.explicit_text[..]) {
+            ([dash], []) => dash == "-",
+            ([], []) => !io::stdin().is_terminal(),
+            _ => false,
+        }
+    }
+
+    fn link_start(&self) -> Option<String> {
+        let url = self.link.as_deref()?;
+        Some(terminal::hyperlink_start(url, self.link_id.as_deref()))
+    }
+
+    /// Pad (or with `--truncate`, cut) each line to the width in columns, not counting escape
+    /// sequences.
+    fn fit_width(&self, text: String) -> String {
+        let width = match (self.width, self.banner) {
+            (Some(width), _) => width,
+            (None, true) => terminal::width().unwrap_or(DEFAULT_WIDTH),
+            (None, false) => return text,
+        };
+        let mut result = String::with_capacity(text.len());
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                result.push('\n');
+            }
+            let (line, ending) = match line.strip_suffix('\r') {
+                Some(line) => (line, "\r"),
+                None => (line, ""),
+            };
+            let mut fitted = String::with_capacity(line.len());
+            let mut columns = 0;
+            for token in ansi::tokens(line.as_bytes()) {
+                // Tokens split the line at ESC bytes, so their text is valid UTF-8
+                let text = std::str::from_utf8(token.raw).unwrap_or_default();
+                if token.is_escape() {
+                    fitted.push_str(text);
+                    continue;
+                }
+                for c in text.chars() {
+                    let char_width = width::char_width(c);
+                    if self.truncate && columns + char_width > width {
+                        break;
+                    }
+                    fitted.push(c);
+                    columns += char_width;
+                }
+            }
+            let padding = width.saturating_sub(columns);
+            let align = match (self.align, self.banner) {
+                (Some(align), _) => align,
+                (None, true) => Align::Center,
+                (None, false) => Align::Left,
+            };
+            let left = match align {
+                Align::Left => 0,
+                Align::Center => padding / 2,
+                Align::Right => padding,
+            };
+            result.extend(std::iter::repeat_n(self.fill, left));
+            result.push_str(&fitted);
+            result.extend(std::iter::repeat_n(self.fill, padding - left));
+            result.push_str(ending);
+        }
+        result
+    }
+
+    /// Join the text arguments, coloring them (or their words) with alternating colors.
+    fn alternate_text(&self, step: &mut usize) -> String {
+        if self.alternate.is_empty() || self.plain {
+            return self.text.join(" ");
+        }
+        match self.alternate_by {
+            AlternateBy::Word => self.alternate_words(&self.text.join(" "), step),
+            AlternateBy::Arg => self
+                .text
+                .iter()
+                .map(|text| format!("{}{text}", self.alternate_sgr(step)))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Color each word with the next alternating color, counting from step.
+    fn alternate_words(&self, text: &str, step: &mut usize) -> String {
+        if self.alternate.is_empty() || self.plain {
+            return text.to_owned();
+        }
+        let mut result = String::with_capacity(text.len() * 2);
+        let mut in_word = false;
+        for c in text.chars() {
+            if !in_word && !c.is_whitespace() {
+                result.push_str(&self.alternate_sgr(step));
+            }
+            in_word = !c.is_whitespace();
+            result.push(c);
+        }
+        result
+    }
+
+    fn alternate_sgr(&self, step: &mut usize) -> String {
+        let color = self.alternate[*step % self.alternate.len()];
+        *step += 1;
+        sgr(&prop_codes(Some(color), None, &[]))
+    }
+
+    /// Color each character that is not whitespace with the next hue of the rainbow, counting
+    /// from step.
+    fn rainbow(&self, text: String, step: &mut u32) -> String {
+        if !self.rainbow || self.plain {
+            return text;
+        }
+        color_chars(&text, |c| {
+            if c.is_whitespace() {
+                return None;
+            }
+            let turns = f64::from(*step) / f64::from(self.rainbow_period);
+            *step += 1;
+            let hue = (self.rainbow_offset / 360.0 + turns).rem_euclid(1.0);
+            Some(Rgb::from_hsv(hue, 1.0, 1.0))
+        })
+    }
+
+    /// Color the characters along the gradient, across each line or the whole text.
+    fn gradient(&self, text: String) -> Result<String> {
+        if self.gradient.len() == 1 {
+            bail!("a gradient needs at least two colors");
+        }
+        if self.gradient.is_empty() || self.plain {
+            return Ok(text);
+        }
+        // Across the characters of the text, not counting escape sequences and line endings
+        let gradient = |text: &str| {
+            let counted = |c: &char| !matches!(c, '\n' | '\r');
+            let length = ansi::tokens(text.as_bytes())
+                .filter(|token| !token.is_escape())
+                .map(|token| {
+                    String::from_utf8_lossy(token.raw)
+                        .chars()
+                        .filter(counted)
+                        .count()
+                })
+                .sum::<usize>();
+            let last = length.saturating_sub(1).max(1) as f64;
+            let mut index = 0;
+            color_chars(text, |c| {
+                if !counted(&c) {
+                    return None;
+                }
+                let t = index as f64 / last;
+                index += 1;
+                (!c.is_whitespace()).then(|| gradient_at(&self.gradient, t))
+            })
+        };
+        Ok(match self.gradient_scope {
+            GradientScope::Text => gradient(&text),
+            GradientScope::Line => text
+                .split('\n')
+                .map(gradient)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+    }
+
+    /// Separator of the records read from stdin.
+    fn record_separator(&self) -> &'static str {
+        match self.null && self.null_output {
+            true => "\0",
+            false => self.newline(),
+        }
+    }
+
+    fn newline(&self) -> &'static str {
+        if self.crlf {
+            "\r\n"
+        } else if self.lf {
+            "\n"
+        } else {
+            terminal::newline()
+        }
+    }
 }
 
 /// Formatting options
 #[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
-enum FormattingOption {
+#[non_exhaustive]
+pub enum FormattingOption {
     #[clap(alias = "b")]
     Bold,
     #[clap(alias = "d")]
@@ -55,14 +703,25 @@ enum FormattingOption {
     #[clap(alias = "u")]
     Underline,
     #[clap(alias = "i")]
+    Italic,
+    #[clap(alias = "v", alias = "inv")]
     Inverted,
     #[clap(alias = "s")]
     Strikethrough,
+    #[clap(alias = "bl")]
+    Blink,
+    #[clap(alias = "h")]
+    Hidden,
+    #[clap(alias = "o")]
+    Overline,
+    #[clap(alias = "uu")]
+    DoubleUnderline,
 }
 
 /// Color options
 #[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
-enum Color {
+#[non_exhaustive]
+pub enum Color {
     #[clap(alias = "k")]
     Black,
     #[clap(alias = "w")]
@@ -97,115 +756,2721 @@ enum Color {
     BrightMagenta,
 }
 
-/// Color options
+impl Color {
+    /// The normal color of a bright color.
+    pub fn normal(self) -> Option<Self> {
+        match self {
+            Color::BrightBlack => Some(Color::Black),
+            Color::BrightWhite => Some(Color::White),
+            Color::BrightRed => Some(Color::Red),
+            Color::BrightGreen => Some(Color::Green),
+            Color::BrightBlue => Some(Color::Blue),
+            Color::BrightYellow => Some(Color::Yellow),
+            Color::BrightCyan => Some(Color::Cyan),
+            Color::BrightMagenta => Some(Color::Magenta),
+            _ => None,
+        }
+    }
+}
+
+/// Foreground or background color: a named color, a 256-color palette index, 24-bit color, a
+/// grey, or the terminal's default.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ColorSpec {
+    Named(Color),
+    /// 0-7 basic, 8-15 bright, 16-231 color cube, 232-255 greyscale
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+    /// Lightness in percent (0-100), printed as the nearest grey of the 256-color palette
+    Grey(u8),
+    /// The terminal's default color, resetting only this layer
+    Default,
+}
+
+impl ColorSpec {
+    /// Index of the nearest grey of the 256-color palette: the greyscale ramp, or black and
+    /// white of the color cube.
+    fn grey_index(percent: u8) -> u8 {
+        let level = (f64::from(percent.min(100)) * 2.55).round() as i32;
+        // The ramp is 8, 18, ..., 238
+        let ramp = ((level - 8) as f64 / 10.0).round().clamp(0.0, 23.0) as i32;
+        let candidates = [(16, 0), (232 + ramp, 8 + 10 * ramp), (231, 255)];
+        let (index, _) = candidates
+            .into_iter()
+            .min_by_key(|(_, candidate)| (candidate - level).abs())
+            .expect("candidates are not empty");
+        index as u8
+    }
+}
+
+impl fmt::Display for ColorSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorSpec::Named(color) => color.fmt(f),
+            ColorSpec::Indexed(index) => index.fmt(f),
+            ColorSpec::Rgb(r, g, b) => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+            ColorSpec::Grey(percent) => write!(f, "grey:{percent}"),
+            ColorSpec::Default => f.write_str("default"),
+        }
+    }
+}
+
+/// Parses a color name or alias, a palette index from 0 to 255, `#RRGGBB`, `RRGGBB`, or `#RGB`,
+/// `rgb(R, G, B)`, `hsv(H, S, V)`, or `hsl(H, S, L)`, `grey:NN` (also `gray:NN`) with NN from 0 to 100, or
+/// `default` (also `d`).
+impl FromStr for ColorSpec {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "default" || s == "d" {
+            return Ok(ColorSpec::Default);
+        }
+        if let Some(percent) = s.strip_prefix("grey:").or_else(|| s.strip_prefix("gray:")) {
+            return match percent.parse() {
+                Ok(percent) if percent <= 100 => Ok(ColorSpec::Grey(percent)),
+                _ => Err(ParseError::token(
+                    5,
+                    percent,
+                    "grey must be a percentage (0-100), found",
+                )),
+            };
+        }
+        let function = |name: &str| {
+            s.get(..name.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(name))
+        };
+        if function("rgb") || function("hsv") || function("hsl") {
+            let Rgb(r, g, b) = s.parse()?;
+            return Ok(ColorSpec::Rgb(r, g, b));
+        }
+        if s.starts_with('#') || (s.len() == 6 && s.bytes().all(|b| b.is_ascii_hexdigit())) {
+            let Rgb(r, g, b) = Rgb::from_hex(s)?;
+            return Ok(ColorSpec::Rgb(r, g, b));
+        }
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return s
+                .parse()
+                .map(ColorSpec::Indexed)
+                .map_err(|_| ParseError::token(0, s, "palette index out of range (0-255)"));
+        }
+        s.parse()
+            .map(ColorSpec::Named)
+            .map_err(|error: ParseError| {
+                let mut names = value_names::<Color>();
+                names.push("default".to_owned());
+                error.suggest(s, names.iter().map(String::as_str))
+            })
+    }
+}
+
+impl From<Color> for ColorSpec {
+    fn from(color: Color) -> Self {
+        ColorSpec::Named(color)
+    }
+}
+
+impl From<u8> for ColorSpec {
+    fn from(index: u8) -> Self {
+        ColorSpec::Indexed(index)
+    }
+}
+
+impl From<Rgb> for ColorSpec {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        ColorSpec::Rgb(r, g, b)
+    }
+}
+
+/// Alignment of text padded to a width
 #[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
-enum Style {
-    Ok,
-    Notice,
-    Error,
-    Warn,
-    Info,
-    Debug,
+pub enum Align {
+    Left,
+    Center,
+    Right,
 }
 
-pub fn format(mut args: Args) -> String {
-    // Premade Style
-    args = apply_style(args);
+/// When to use escape sequences
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum When {
+    Auto,
+    Always,
+    Never,
+}
 
-    // Text formatting
-    let mut prop_codes = Vec::new();
-    if let Some(fg) = args.foreground {
-        prop_codes.push((30 + get_color_code_digit(fg)).to_string());
+/// Shell syntax of exported definitions
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Definition of a variable set to an escape sequence.
+    fn define(self, name: &str, sequence: &str) -> String {
+        match self {
+            // ANSI-C quoting
+            Shell::Bash | Shell::Zsh => {
+                let quoted = sequence
+                    .replace('\\', "\\\\")
+                    .replace('\'', "\\'")
+                    .replace('\x1b', "\\e");
+                format!("{name}=$'{quoted}'")
+            }
+            // Escapes are only interpreted outside of quotes
+            Shell::Fish => {
+                let quoted: Vec<String> = sequence
+                    .split('\x1b')
+                    .map(|part| match part.is_empty() {
+                        true => String::new(),
+                        false => format!("'{}'", part.replace('\\', "\\\\").replace('\'', "\\'")),
+                    })
+                    .collect();
+                format!("set -gx {name} {}", quoted.join("\\e"))
+            }
+        }
     }
-    if let Some(bg) = args.background {
-        prop_codes.push((40 + get_color_code_digit(bg)).to_string());
+}
+
+/// How to print escape sequences
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Escape {
+    /// As they are
+    Raw,
+    /// As text, with `\e` for ESC
+    Literal,
+    /// As text wrapped in `\[` and `\]`, for bash prompts
+    Bash,
+    /// As text wrapped in `%{` and `%}`, for zsh prompts
+    Zsh,
+}
+
+impl Escape {
+    /// Rewrite the escape sequences in the output.
+    fn apply(self, output: String) -> String {
+        let (open, close) = match self {
+            Escape::Raw => return output,
+            Escape::Literal => ("", ""),
+            Escape::Bash => ("\\[", "\\]"),
+            Escape::Zsh => ("%{", "%}"),
+        };
+        let mut result = String::with_capacity(output.len() * 2);
+        for token in ansi::tokens(output.as_bytes()) {
+            let raw = String::from_utf8_lossy(token.raw);
+            if token.is_escape() {
+                result.push_str(open);
+                let literal = raw
+                    .replace('\\', "\\\\")
+                    .replace('\x1b', "\\e")
+                    .replace('\x07', "\\a");
+                result.push_str(&literal);
+                result.push_str(close);
+            } else {
+                result.push_str(&raw);
+            }
+        }
+        result
     }
-    for option in &args.options {
-        prop_codes.push(get_format_code(option).to_string());
+}
+
+/// Escape sequences to remove
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Strip {
+    /// All escape sequences
+    All,
+    /// Only colors and formatting
+    Sgr,
+}
+
+/// Widths to print
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Length {
+    /// Width of each line
+    Lines,
+    /// Width of the widest line
+    Max,
+}
+
+/// Kind of a line of a diff
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DiffLine {
+    Header,
+    Hunk,
+    Add,
+    Remove,
+    Change,
+}
+
+impl DiffLine {
+    /// Recognize a line of a unified, context, or normal diff.
+    ///
+    /// `in_hunk` is whether the previous lines are in a hunk of a unified diff, where lines
+    /// starting with `--- ` or `+++ ` are removed or added rather than file headers.
+    fn classify(line: &str, in_hunk: &mut bool) -> Option<Self> {
+        if *in_hunk {
+            match line.chars().next() {
+                Some('+') => return Some(DiffLine::Add),
+                Some('-') => return Some(DiffLine::Remove),
+                None | Some(' ' | '\\') => return None,
+                Some(_) => *in_hunk = false,
+            }
+        }
+        if line.starts_with("@@") {
+            *in_hunk = true;
+            return Some(DiffLine::Hunk);
+        }
+        let is_range = |line: &str| {
+            let mut parts = line.split(['a', 'c', 'd']);
+            let is_lines = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .split(',')
+                        .all(|n| n.bytes().all(|b| b.is_ascii_digit()))
+            };
+            matches!((parts.next(), parts.next(), parts.next()), (Some(from), Some(to), None) if is_lines(from) && is_lines(to))
+        };
+        if line == "---"
+            || line.starts_with("***************")
+            || ((line.starts_with("*** ") && line.ends_with(" ****"))
+                || (line.starts_with("--- ") && line.ends_with(" ----")))
+            || is_range(line)
+        {
+            Some(DiffLine::Hunk)
+        } else if ["diff ", "index ", "+++ ", "--- ", "*** "]
+            .iter()
+            .any(|header| line.starts_with(header))
+        {
+            Some(DiffLine::Header)
+        } else if line.starts_with(['+', '>']) {
+            Some(DiffLine::Add)
+        } else if line.starts_with(['-', '<']) {
+            Some(DiffLine::Remove)
+        } else if line.starts_with("! ") {
+            Some(DiffLine::Change)
+        } else {
+            None
+        }
     }
+}
 
-    // Formatted text
-    let text = args.text.join(" ");
-    let mut result = if !prop_codes.is_empty() {
-        let prop_codes = prop_codes.join(";");
-        format!("{CODE_START}{prop_codes}{CODE_END}{text}")
-    } else {
-        text
-    };
+/// Escape sequences of the kinds of diff lines.
+struct DiffStyles {
+    header: String,
+    hunk: String,
+    add: String,
+    remove: String,
+    change: String,
+    /// Reset after a colored line, as the record is only reset without --no-reset
+    reset: &'static str,
+}
 
-    // Pre-text formatting
-    if args.reset {
-        result = format!("{RESET}{result}")
+impl DiffStyles {
+    fn new(args: &Args) -> Result<Self> {
+        Ok(Self {
+            header: highlight_sgr(&args.diff_header)?,
+            hunk: highlight_sgr(&args.diff_hunk)?,
+            add: highlight_sgr(&args.diff_add)?,
+            remove: highlight_sgr(&args.diff_remove)?,
+            change: highlight_sgr(&args.diff_change)?,
+            reset: if args.no_reset { RESET } else { "" },
+        })
     }
-    // Post-text formatting
-    if !args.no_reset {
-        result.push_str(RESET);
-    };
-    if !args.no_newline {
-        result.push('\n');
-    };
 
-    result
+    /// Start a line with the formatting of its kind, unless it already has escape sequences.
+    fn color(&self, line: String, in_hunk: &mut bool) -> String {
+        // Classify even lines left as they are, to follow the hunks
+        let kind = DiffLine::classify(&line, in_hunk);
+        if line.contains('\x1b') {
+            return line;
+        }
+        let sgr = match kind {
+            Some(DiffLine::Header) => &self.header,
+            Some(DiffLine::Hunk) => &self.hunk,
+            Some(DiffLine::Add) => &self.add,
+            Some(DiffLine::Remove) => &self.remove,
+            Some(DiffLine::Change) => &self.change,
+            None => return line,
+        };
+        format!("{sgr}{line}{}", self.reset)
+    }
+}
+
+/// Compatibility mapping of colors
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Compat {
+    /// Bold bright if the terminal does not advertise 16 colors or more
+    Auto,
+    /// Bright foregrounds as bold normal colors, and bright backgrounds as normal colors
+    BoldBright,
+}
+
+/// Part of a log line to color
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum LogScope {
+    /// The whole line
+    Line,
+    /// Only the level
+    Token,
 }
 
-fn apply_style(mut args: Args) -> Args {
-    if let Some(style) = args.style {
-        args.background = None;
-        args.foreground = None;
-        args.options = Vec::new();
-        match style {
-            Style::Ok => {
-                args.foreground = Some(Color::Green);
+/// Log levels and the premade styles of their lines.
+const LOG_LEVELS: [(&str, Style); 7] = [
+    ("error", Style::Error),
+    ("err", Style::Error),
+    ("warn", Style::Warn),
+    ("warning", Style::Warn),
+    ("info", Style::Info),
+    ("debug", Style::Debug),
+    ("trace", Style::Debug),
+];
+
+/// Escape sequences of the log levels.
+struct LogStyles {
+    levels: Vec<(String, String)>,
+    scope: LogScope,
+}
+
+impl LogStyles {
+    /// The premade levels, overridden and extended by the `LEVEL=STYLE` maps.
+    fn new(args: &Args) -> Result<Self> {
+        let mut levels: Vec<(String, String)> = LOG_LEVELS
+            .iter()
+            .map(|&(level, style)| {
+                let (fg, bg, options) = style_props(style);
+                (level.to_owned(), sgr(&prop_codes(fg, bg, &options)))
+            })
+            .collect();
+        for map in &args.map {
+            let Some((level, style)) = map.split_once('=') else {
+                bail!("invalid map '{map}' (expected LEVEL=STYLE)");
+            };
+            if level.is_empty() || !level.chars().all(char::is_alphanumeric) {
+                bail!("invalid log level '{level}' (expected letters and digits)");
+            }
+            let sgr = highlight_sgr(style)?;
+            levels.retain(|(known, _)| !known.eq_ignore_ascii_case(level));
+            levels.push((level.to_owned(), sgr));
+        }
+        Ok(Self {
+            levels,
+            scope: args.log_scope,
+        })
+    }
+
+    /// Wrap the line, or its level, in the formatting of the first word that is a level.
+    fn color(&self, line: String) -> String {
+        let Some((range, sgr)) = words(&line).find_map(|range| {
+            self.levels
+                .iter()
+                .find(|(level, _)| level.eq_ignore_ascii_case(&line[range.clone()]))
+                .map(|(_, sgr)| (range, sgr))
+        }) else {
+            return line;
+        };
+        match self.scope {
+            LogScope::Line => format!("{sgr}{line}{RESET}"),
+            LogScope::Token => {
+                // Include the brackets of a bracketed level
+                let bracketed =
+                    line[..range.start].ends_with('[') && line[range.end..].starts_with(']');
+                let range = match bracketed {
+                    true => range.start - 1..range.end + 1,
+                    false => range,
+                };
+                format!(
+                    "{}{sgr}{}{RESET}{}",
+                    &line[..range.start],
+                    &line[range.clone()],
+                    &line[range.end..]
+                )
             }
-            Style::Notice => {
-                args.foreground = Some(Color::Magenta);
+        }
+    }
+}
+
+/// Byte ranges of the runs of letters and digits of a line.
+fn words(line: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut chars = line.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.by_ref().find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = line.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end = i;
+                break;
             }
-            Style::Error => {
-                args.foreground = Some(Color::Red);
+            chars.next();
+        }
+        Some(start..end)
+    })
+}
+
+/// Separator of the fields of a line
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Delimiter {
+    /// Runs of whitespace, ignoring leading and trailing whitespace
+    Whitespace,
+    Char(char),
+}
+
+impl Delimiter {
+    /// Byte ranges of the fields of a line.
+    fn fields(self, line: &str) -> Vec<Range<usize>> {
+        let mut fields = Vec::new();
+        match self {
+            Delimiter::Whitespace => {
+                let mut start = None;
+                for (i, c) in line.char_indices() {
+                    match (start, c.is_whitespace()) {
+                        (None, false) => start = Some(i),
+                        (Some(field_start), true) => {
+                            fields.push(field_start..i);
+                            start = None;
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some(field_start) = start {
+                    fields.push(field_start..line.len());
+                }
             }
-            Style::Warn => {
-                args.foreground = Some(Color::Yellow);
+            Delimiter::Char(delimiter) => {
+                let mut start = 0;
+                for (i, _) in line.match_indices(delimiter) {
+                    fields.push(start..i);
+                    start = i + delimiter.len_utf8();
+                }
+                fields.push(start..line.len());
             }
-            Style::Info => {
-                args.foreground = Some(Color::Cyan);
+        }
+        fields
+    }
+}
+
+/// Style of extended underlines
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum UnderlineStyle {
+    Straight,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// SGR parameter, with the style as a sub-parameter so it stays a single parameter.
+    fn code(self) -> &'static str {
+        match self {
+            UnderlineStyle::Straight => "4:1",
+            UnderlineStyle::Double => "4:2",
+            UnderlineStyle::Curly => "4:3",
+            UnderlineStyle::Dotted => "4:4",
+            UnderlineStyle::Dashed => "4:5",
+        }
+    }
+}
+
+/// Unit of alternating colors
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum AlternateBy {
+    /// Every word, separated by whitespace
+    Word,
+    /// Every argument of the text
+    Arg,
+}
+
+/// Span of a gradient over multi-line text
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum GradientScope {
+    /// Restart the gradient on every line
+    Line,
+    /// Continue the gradient across lines
+    Text,
+}
+
+/// Premade styles
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum Style {
+    Ok,
+    Notice,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+macro_rules! value_enum_str {
+    ($($name:ident => $what:literal),*) => {$(
+        /// Formats as the name used on the command line.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.to_possible_value() {
+                    Some(value) => f.write_str(value.get_name()),
+                    None => Ok(()),
+                }
             }
-            Style::Debug => {
-                args.background = Some(Color::Cyan);
-                args.foreground = Some(Color::Black);
-                args.options.push(FormattingOption::Dim);
+        }
+
+        /// Parses a name or alias as used on the command line.
+        impl FromStr for $name {
+            type Err = ParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_value(s, 0, concat!("unknown ", $what))
             }
         }
+    )*};
+}
+
+value_enum_str!(Color => "color", FormattingOption => "formatting option", Style => "style");
+
+/// Separator of the argument groups of segments.
+pub const SEGMENT_SEPARATOR: &str = "--then";
+
+/// Split the command line at [`SEGMENT_SEPARATOR`] into the command lines of each segment,
+/// which all start with the binary name.
+pub fn segment_args(argv: impl IntoIterator<Item = OsString>) -> Vec<Vec<OsString>> {
+    let mut argv = argv.into_iter();
+    let binary = argv.next().unwrap_or_default();
+    let mut segments = vec![vec![binary.clone()]];
+    for arg in argv {
+        if arg == SEGMENT_SEPARATOR {
+            segments.push(vec![binary.clone()]);
+        } else {
+            segments
+                .last_mut()
+                .expect("segments are not empty")
+                .push(arg);
+        }
     }
-    args
+    segments
 }
 
-fn get_format_code(option: &FormattingOption) -> u8 {
-    match option {
-        FormattingOption::Bold => 1,
-        FormattingOption::Dim => 2,
-        FormattingOption::Underline => 4,
-        FormattingOption::Inverted => 7,
-        FormattingOption::Strikethrough => 9,
+/// Format segments one after another, with the line ending of the last segment only.
+pub fn format_segments(segments: Vec<Args>) -> Result<String> {
+    let last = segments.len().saturating_sub(1);
+    // The first segment decides whether the line is colored, unless others say explicitly
+    let plain = segments
+        .first()
+        .is_some_and(|args| args.plain || args.colors_disabled());
+    let mut result = String::new();
+    for (i, mut args) in segments.into_iter().enumerate() {
+        if args.runs() || args.strips() {
+            bail!("--run and --strip cannot be used with {SEGMENT_SEPARATOR}");
+        }
+        if args.when == When::Auto {
+            args.plain |= plain;
+            args.when = When::Always;
+        }
+        if i < last {
+            args.no_newline = true;
+        }
+        result.push_str(&format(args)?);
     }
+    Ok(result)
 }
 
-fn get_color_code_digit(color: Color) -> u8 {
-    match color {
-        Color::Black => 0,
-        Color::Red => 1,
-        Color::Green => 2,
-        Color::Yellow => 3,
-        Color::Blue => 4,
-        Color::Magenta => 5,
-        Color::Cyan => 6,
-        Color::White => 7,
-        Color::BrightBlack => 60,
-        Color::BrightRed => 61,
-        Color::BrightGreen => 62,
-        Color::BrightYellow => 63,
-        Color::BrightBlue => 64,
-        Color::BrightMagenta => 65,
-        Color::BrightCyan => 66,
-        Color::BrightWhite => 67,
+/// Colors and formatting options of [`paint`], for formatting text without command line
+/// arguments, built up by chaining.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct StyleBuilder {
+    pub foreground: Option<ColorSpec>,
+    pub background: Option<ColorSpec>,
+    pub options: Vec<FormattingOption>,
+    /// Leave the formatting on after the text
+    pub no_reset: bool,
+}
+
+impl StyleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn foreground(mut self, color: impl Into<ColorSpec>) -> Self {
+        self.foreground = Some(color.into());
+        self
+    }
+
+    pub fn background(mut self, color: impl Into<ColorSpec>) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+
+    pub fn option(mut self, option: FormattingOption) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// Add the colors and formatting options of a premade style, as `--style` does: colors
+    /// already set override the style's.
+    pub fn style(mut self, style: Style) -> Self {
+        let (foreground, background, options) = style_props(style);
+        self.foreground = self.foreground.or(foreground);
+        self.background = self.background.or(background);
+        self.options.splice(0..0, options);
+        self
+    }
+
+    pub fn no_reset(mut self, no_reset: bool) -> Self {
+        self.no_reset = no_reset;
+        self
+    }
+
+    /// The escape sequence setting the colors and formatting options (empty if there are none).
+    pub fn sgr(&self) -> String {
+        sgr(&prop_codes(self.foreground, self.background, &self.options))
+    }
+
+    /// Format text with these colors and formatting options, see [`paint`].
+    pub fn paint(&self, text: &str) -> String {
+        paint(text, self)
+    }
+}
+
+/// Format text as printcolor does, without a line ending: every line is formatted and reset
+/// before its line ending, and the last line is reset unless `no_reset` is set.
+pub fn paint(text: &str, options: &StyleBuilder) -> String {
+    Styled::with_options(text, options.clone()).render()
+}
+
+/// Print the text formatted by the arguments to stdout as printcolor does, running, stripping,
+/// or streaming stdin as they ask, and return the exit code (of the command with `--run`).
+pub fn print(mut args: Args) -> Result<i32> {
+    use io::Write as _;
+    if !terminal::enable_vt_processing() {
+        args.disable_colors();
+    }
+    if args.runs() {
+        let (summary, code) = run(args)?;
+        io::stdout().lock().write_all(summary.as_bytes())?;
+        return Ok(code);
+    }
+    if args.strips() {
+        strip_stream(args, io::stdin().lock(), RecordWriter::stdout(true))?;
+        return Ok(0);
+    }
+    if args.reads_stdin() {
+        let output = RecordWriter::stdout(args.flushes_lines());
+        format_stream(args, io::stdin().lock(), output)?;
+        return Ok(0);
+    }
+    let output = format(args)?;
+    // Single write for the whole output
+    io::stdout().lock().write_all(output.as_bytes())?;
+    Ok(0)
+}
+
+pub fn format(mut args: Args) -> Result<String> {
+    args.plain |= args.colors_disabled();
+    // Premade Style
+    args = apply_style(args)?;
+    let explicit_text = std::mem::take(&mut args.explicit_text);
+    args.text.extend(explicit_text);
+    if args.escapes {
+        args.text = args.text.iter().map(|text| unescape(text)).collect();
+    }
+
+    if let Some(shell) = args.export_styles {
+        return export_styles(&args, shell);
+    }
+
+    if args.code_only || args.reset_code {
+        if args.plain {
+            return Ok(String::new());
+        }
+        let code = match args.reset_code {
+            true => RESET.to_owned(),
+            false => sgr(&text_codes(&args)?),
+        };
+        return Ok(args.escape.apply(code));
+    }
+
+    if args.explain_input {
+        let input = if args.text.is_empty() {
+            let mut input = Vec::new();
+            std::io::stdin().read_to_end(&mut input)?;
+            input
+        } else {
+            args.text.join(" ").into_bytes()
+        };
+        return Ok(explain::explain_input(&input, args.plain));
+    }
+
+    if let Some(mode) = args.line_mode() {
+        bail!("{mode} formats the lines of stdin, not text arguments");
+    }
+
+    if let Some(length) = args.length {
+        let input = if args.text.is_empty() {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            input
+        } else {
+            args.text.join(" ")
+        };
+        let widths = input.lines().map(width::width);
+        let widths: Vec<String> = match length {
+            Length::Lines => widths.map(|width| width.to_string()).collect(),
+            Length::Max => vec![widths.max().unwrap_or_default().to_string()],
+        };
+        let mut result = widths.join(args.newline());
+        if result.is_empty() {
+            result.push('0');
+        }
+        if !args.no_newline {
+            result.push_str(args.newline());
+        }
+        return Ok(result);
+    }
+
+    // Text formatting
+    let prop_codes = text_codes(&args)?;
+
+    // Formatted text
+    let text = if let Some(path) = &args.template {
+        let text = render_template(path, &sgr(&prop_codes), args.plain)?;
+        if args.check_template {
+            return Ok(String::new());
+        }
+        text
+    } else if args.markup {
+        render_markup(&args.text.join(" "), &sgr(&prop_codes), args.plain)?
+    } else if args.word_diff {
+        let (old, new) = word_diff_input(&args.text)?;
+        if args.plain {
+            new
+        } else {
+            render_word_diff(&old, &new, &prop_codes, args.show_removed)
+        }
+    } else {
+        let text = args.fit_width(args.alternate_text(&mut 0));
+        let text = args.gradient(args.rainbow(text, &mut 0))?;
+        match args.plain {
+            true => text,
+            false => highlight(text, &highlights(&args)?, &sgr(&prop_codes)),
+        }
+    };
+    let meter = match args.meter {
+        Some(value) => {
+            let width = args.meter_width.unwrap_or_else(|| {
+                let text_width = width::width(&text) + usize::from(!text.is_empty());
+                terminal::width()
+                    .unwrap_or(DEFAULT_WIDTH)
+                    .saturating_sub(text_width)
+                    .max(1)
+            });
+            let meter = meter::render(
+                value,
+                args.meter_max,
+                width,
+                if args.meter_gradient.is_empty() {
+                    &DEFAULT_METER_GRADIENT
+                } else {
+                    &args.meter_gradient
+                },
+                args.plain,
+            )?;
+            Some(meter)
+        }
+        None => None,
+    };
+    let annotations = Annotations::new(&args)?;
+    // Plain text lines are wrapped one by one, modes that emit their own sequences are not
+    let per_line = args.template.is_none() && !args.markup && !args.word_diff;
+    let (lines, last_line) = match text.rsplit_once('\n').filter(|_| per_line) {
+        Some((lines, last)) => (Some(lines), last),
+        None => (None, text.as_str()),
+    };
+    if args.plain {
+        let mut result = String::with_capacity(text.len() + RESERVED_CAPACITY);
+        for line in lines.into_iter().flat_map(|lines| lines.split('\n')) {
+            push_line(&mut result, line, "", &annotations);
+        }
+        annotations.push_prefix(&mut result);
+        result.push_str(last_line);
+        if let Some(meter) = meter {
+            if !text.is_empty() {
+                result.push(' ');
+            }
+            result.push_str(&meter);
+        }
+        annotations.push_suffix(&mut result);
+        if !args.no_newline {
+            result.push_str(args.newline());
+        }
+        return Ok(result);
+    }
+    let mut result = String::with_capacity(text.len() + prop_codes.len() + RESERVED_CAPACITY);
+    // Pre-text formatting
+    if args.reset {
+        result.push_str(RESET);
+    }
+    let link_start = args.link_start();
+    if let Some(link_start) = &link_start {
+        result.push_str(link_start);
+    }
+    for line in lines.into_iter().flat_map(|lines| lines.split('\n')) {
+        push_line(&mut result, line, &prop_codes, &annotations);
+    }
+    annotations.push_prefix(&mut result);
+    push_sgr(&mut result, &prop_codes);
+    result.push_str(last_line);
+    // Only the codes, to prefix text printed after them (such as in a prompt)
+    let prefix_only = args.no_newline && text.is_empty() && meter.is_none();
+    if let Some(meter) = meter {
+        if !prop_codes.is_empty() {
+            result.push_str(RESET);
+        }
+        if !text.is_empty() {
+            result.push(' ');
+        }
+        result.push_str(&meter);
+    }
+
+    // Post-text formatting
+    if !args.no_reset && !prefix_only {
+        result.push_str(RESET);
+    };
+    if args.reset_underline_color {
+        result.push_str(RESET_UNDERLINE_COLOR);
+    }
+    if link_start.is_some() {
+        result.push_str(terminal::HYPERLINK_END);
+    }
+    annotations.push_suffix(&mut result);
+    if !args.no_newline {
+        result.push_str(args.newline());
+    };
+
+    Ok(args.escape.apply(result))
+}
+
+/// Run the command given as the text, with its output passed through (unless quiet), then
+/// return the summary line and the exit code to exit with.
+pub fn run(mut args: Args) -> Result<(String, i32)> {
+    args.plain |= args.colors_disabled();
+    let explicit_text = std::mem::take(&mut args.explicit_text);
+    args.text.extend(explicit_text);
+    let Some((program, arguments)) = args.text.split_first() else {
+        bail!("no command to run");
+    };
+    let mut command = std::process::Command::new(program);
+    command.args(arguments);
+    if args.quiet {
+        command
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run '{program}'"))?;
+    let command_line = args.text.join(" ");
+    let (style, summary, code) = match (status.code(), exit_signal(&status)) {
+        (Some(0), _) => (&args.ok_style, format!("✓ {command_line}"), 0),
+        (Some(code), _) => (
+            &args.fail_style,
+            format!("✗ {command_line} (exit {code})"),
+            code,
+        ),
+        // Shells report death by a signal as 128 plus the signal number
+        (None, Some(signal)) => (
+            &args.fail_style,
+            format!("✗ {command_line} (signal {signal})"),
+            128 + signal,
+        ),
+        (None, None) => (&args.fail_style, format!("✗ {command_line}"), 1),
+    };
+    let mut result = String::with_capacity(summary.len() + RESERVED_CAPACITY);
+    if args.plain {
+        result.push_str(&summary);
+    } else {
+        result.push_str(&highlight_sgr(style)?);
+        result.push_str(&summary);
+        result.push_str(RESET);
+    }
+    if !args.no_newline {
+        result.push_str(args.newline());
+    }
+    Ok((result, code))
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    std::os::unix::process::ExitStatusExt::signal(status)
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &std::process::ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Remove escape sequences from the input, writing out each chunk as soon as it is read.
+///
+/// Everything else, including invalid UTF-8, passes through untouched.
+pub fn strip_stream(
+    args: Args,
+    mut input: impl Read,
+    mut output: RecordWriter<impl io::Write>,
+) -> Result<()> {
+    let mut stripper = ansi::Stripper::new(args.strip == Some(Strip::Sgr));
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        output.record_bytes(&stripper.strip(&buffer[..read]))?;
+    }
+    output.finish()?;
+    Ok(())
+}
+
+/// Format each line (or NUL-separated record) read from the input, writing it out as soon as
+/// it is read.
+///
+/// Invalid UTF-8 is replaced. Every record ends with its separator, except the last one with
+/// `--no-newline`.
+pub fn format_stream(
+    args: Args,
+    mut input: impl BufRead,
+    mut output: RecordWriter<impl io::Write>,
+) -> Result<()> {
+    let mut args = apply_style(args)?;
+    args.plain |= args.colors_disabled();
+    let prop_codes = text_codes(&args)?;
+    let link_start = args.link_start();
+    let highlights = highlights(&args)?;
+    let annotations = Annotations::new(&args)?;
+    let base = sgr(&prop_codes);
+    let columns = columns(&args, &base)?;
+    let diff_styles = match args.diff {
+        true => Some(DiffStyles::new(&args)?),
+        false => None,
+    };
+    let log_styles = match args.log {
+        true => Some(LogStyles::new(&args)?),
+        false => None,
+    };
+    // Modes of the lines of stdin format parts of the lines themselves
+    let line_codes = match args.line_mode() {
+        None => prop_codes.as_str(),
+        Some(_) => "",
+    };
+    let mut step = 0;
+    let mut alternate_step = 0;
+    let mut in_diff_hunk = false;
+    let separator = if args.null { b'\0' } else { b'\n' };
+    let output_separator = args.record_separator();
+    let mut line = Vec::new();
+    let mut first = true;
+    loop {
+        line.clear();
+        if input.read_until(separator, &mut line)? == 0 {
+            break;
+        }
+        if line.last() == Some(&separator) {
+            line.pop();
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = match args.null {
+            true => &text,
+            false => text.strip_suffix('\r').unwrap_or(&text),
+        };
+        let text = args.alternate_words(text, &mut alternate_step);
+        let text = args.rainbow(args.fit_width(text), &mut step);
+        let text = args.gradient(text)?;
+        let text = if args.plain {
+            text
+        } else if let Some(diff_styles) = &diff_styles {
+            diff_styles.color(text, &mut in_diff_hunk)
+        } else if let Some(log_styles) = &log_styles {
+            log_styles.color(text)
+        } else if !columns.is_empty() {
+            color_columns(&text, &columns, args.delimiter)
+        } else {
+            highlight(text, &highlights, &base)
+        };
+        let mut record = String::with_capacity(text.len() + prop_codes.len() + RESERVED_CAPACITY);
+        // The separator of the previous record, held back in case it was the last
+        if !first {
+            record.push_str(output_separator);
+        }
+        first = false;
+        if args.plain {
+            annotations.push_prefix(&mut record);
+            record.push_str(&text);
+        } else {
+            if args.reset {
+                record.push_str(RESET);
+            }
+            annotations.push_prefix(&mut record);
+            if let Some(link_start) = &link_start {
+                record.push_str(link_start);
+            }
+            push_sgr(&mut record, line_codes);
+            record.push_str(&text);
+            if !args.no_reset {
+                record.push_str(RESET);
+            }
+            if args.reset_underline_color {
+                record.push_str(RESET_UNDERLINE_COLOR);
+            }
+            if link_start.is_some() {
+                record.push_str(terminal::HYPERLINK_END);
+            }
+        }
+        annotations.push_suffix(&mut record);
+        output.record(&record)?;
+    }
+    if !first && !args.no_newline {
+        output.record(output_separator)?;
+    }
+    output.finish()?;
+    Ok(())
+}
+
+/// SGR parameters of the colors, formatting options, and LS_COLORS entry of the arguments.
+fn text_codes(args: &Args) -> Result<String> {
+    let is_underline = |option: &FormattingOption| {
+        matches!(
+            option,
+            FormattingOption::Underline | FormattingOption::DoubleUnderline
+        )
+    };
+    let (foreground, background, options) = args.compat_props();
+    let mut prop_codes = match args.underline_style {
+        // The style replaces plain underlines
+        Some(style) => {
+            let options: Vec<FormattingOption> = options
+                .iter()
+                .copied()
+                .filter(|option| !is_underline(option))
+                .collect();
+            let mut prop_codes = prop_codes(foreground, background, &options);
+            push_code(&mut prop_codes, style.code());
+            prop_codes
+        }
+        None => prop_codes(foreground, background, &options),
+    };
+    if let Some(color) = args.underline_color {
+        let underlined = args.underline_style.is_some() || options.iter().any(is_underline);
+        if !underlined {
+            push_code(
+                &mut prop_codes,
+                get_format_code(&FormattingOption::Underline),
+            );
+        }
+        // SGR 58 has no form for the basic colors, so they use their palette index
+        match color {
+            ColorSpec::Named(color) => {
+                let digit = get_color_code_digit(color);
+                let index = if digit >= 60 { digit - 60 + 8 } else { digit };
+                push_code(&mut prop_codes, format_args!("58;5;{index}"));
+            }
+            ColorSpec::Indexed(index) => push_code(&mut prop_codes, format_args!("58;5;{index}")),
+            ColorSpec::Grey(percent) => {
+                let index = ColorSpec::grey_index(percent);
+                push_code(&mut prop_codes, format_args!("58;5;{index}"))
+            }
+            ColorSpec::Rgb(r, g, b) => push_code(&mut prop_codes, format_args!("58;2;{r};{g};{b}")),
+            ColorSpec::Default => push_code(&mut prop_codes, 59),
+        }
+    }
+    if args.ls_color.is_some() || args.ls_color_for.is_some() {
+        let ls_colors = LsColors::from_env()?;
+        let ls_codes = ls_colors.resolve(args.ls_color.as_deref(), args.ls_color_for.as_deref())?;
+        push_code(&mut prop_codes, ls_codes);
+    }
+    Ok(prop_codes)
+}
+
+/// Append an SGR parameter to a `;`-separated parameter list.
+fn push_code(prop_codes: &mut String, code: impl std::fmt::Display) {
+    if !prop_codes.is_empty() {
+        prop_codes.push(';');
+    }
+    write!(prop_codes, "{code}").expect("writing to a String cannot fail");
+}
+
+/// Append the escape sequence for an SGR parameter list (nothing if empty).
+pub(crate) fn push_sgr(buffer: &mut String, prop_codes: &str) {
+    if !prop_codes.is_empty() {
+        buffer.push_str(CODE_START);
+        buffer.push_str(prop_codes);
+        buffer.push_str(CODE_END);
+    }
+}
+
+/// Push a line that is not the last, formatted and reset before its line ending.
+pub(crate) fn push_line(
+    buffer: &mut String,
+    line: &str,
+    prop_codes: &str,
+    annotations: &Annotations,
+) {
+    let (line, ending) = match line.strip_suffix('\r') {
+        Some(line) => (line, "\r\n"),
+        None => (line, "\n"),
+    };
+    annotations.push_prefix(buffer);
+    if !line.is_empty() && !prop_codes.is_empty() {
+        push_sgr(buffer, prop_codes);
+        buffer.push_str(line);
+        buffer.push_str(RESET);
+    } else {
+        buffer.push_str(line);
+    }
+    annotations.push_suffix(buffer);
+    buffer.push_str(ending);
+}
+
+fn sgr(prop_codes: &str) -> String {
+    let mut buffer = String::new();
+    push_sgr(&mut buffer, prop_codes);
+    buffer
+}
+
+fn word_diff_input(text: &[String]) -> Result<(String, String)> {
+    match text {
+        [old, new] => Ok((old.clone(), new.clone())),
+        [] => {
+            let input = std::io::read_to_string(std::io::stdin())?;
+            let mut records = input.trim_end_matches(['\0', '\n']).split('\0');
+            match (records.next(), records.next(), records.next()) {
+                (Some(old), Some(new), None) => Ok((old.to_owned(), new.to_owned())),
+                _ => bail!("--word-diff expects two NUL-separated records on stdin"),
+            }
+        }
+        _ => bail!("--word-diff expects two texts (old and new)"),
+    }
+}
+
+fn render_word_diff(old: &str, new: &str, prop_codes: &str, show_removed: bool) -> String {
+    let base = sgr(prop_codes);
+    let highlight = |codes: &str| {
+        let mut prop_codes = prop_codes.to_owned();
+        push_code(&mut prop_codes, codes);
+        sgr(&prop_codes)
+    };
+    let insert = highlight(WORD_DIFF_INSERT);
+    let remove = highlight(WORD_DIFF_REMOVE);
+    let mut result = String::with_capacity(new.len());
+    for change in worddiff::word_diff(old, new) {
+        let highlight = match change.kind {
+            ChangeKind::Equal => None,
+            ChangeKind::Insert => Some(&insert),
+            ChangeKind::Remove if show_removed => Some(&remove),
+            ChangeKind::Remove => continue,
+        };
+        result.push_str(change.whitespace);
+        match highlight {
+            Some(highlight) => {
+                result.push_str(&format!("{highlight}{}{RESET}{base}", change.word));
+            }
+            None => result.push_str(change.word),
+        }
+    }
+    result
+}
+
+/// Definitions of the escape sequences of every style and of the reset, as `-s` prints them.
+fn export_styles(args: &Args, shell: Shell) -> Result<String> {
+    let mut names: Vec<String> = Style::value_variants()
+        .iter()
+        .map(Style::to_string)
+        .collect();
+    for name in user_styles()?.names() {
+        if !names.iter().any(|existing| existing == name) {
+            names.push(name.to_owned());
+        }
+    }
+    let variable = |name: &str| {
+        let name: String = name
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect();
+        format!("{}{name}", args.export_prefix)
+    };
+    let mut result = String::new();
+    for name in names {
+        let mut style_args = args.clone();
+        style_args.style = Some(name.clone());
+        (style_args.foreground, style_args.background) = (None, None);
+        style_args.options.clear();
+        let style_args = apply_style(style_args)?;
+        let sequence = sgr(&text_codes(&style_args)?);
+        result.push_str(&shell.define(&variable(&name), &sequence));
+        result.push_str(args.newline());
+    }
+    result.push_str(&shell.define(&variable("reset"), RESET));
+    result.push_str(args.newline());
+    Ok(result)
+}
+
+/// Layer explicit colors and options over the style, with its options first.
+fn apply_style(mut args: Args) -> Result<Args> {
+    if let Some(name) = &args.style {
+        let (foreground, background, mut options) = resolve_style(name)?;
+        args.foreground = args.foreground.or(foreground);
+        args.background = args.background.or(background);
+        for option in std::mem::take(&mut args.options) {
+            if !options.contains(&option) {
+                options.push(option);
+            }
+        }
+        args.options = options;
+    }
+    Ok(args)
+}
+
+/// The user styles, loaded from the config file on first use only.
+fn user_styles() -> Result<&'static UserStyles> {
+    static USER_STYLES: OnceLock<Result<UserStyles, String>> = OnceLock::new();
+    USER_STYLES
+        .get_or_init(|| UserStyles::load().map_err(|error| error.to_string()))
+        .as_ref()
+        .map_err(|error| anyhow!("{error}"))
+}
+
+/// Colors and formatting options of a premade style, or else of a user style.
+///
+/// Premade styles need no config file, so it is only read for other names.
+fn resolve_style(
+    name: &str,
+) -> Result<(Option<ColorSpec>, Option<ColorSpec>, Vec<FormattingOption>)> {
+    if let Ok(style) = <Style as ValueEnum>::from_str(name, false) {
+        return Ok(style_props(style));
+    }
+    let user_styles = user_styles()?;
+    if let Some(style) = user_styles.get(name) {
+        return Ok((style.foreground, style.background, style.options.clone()));
+    }
+    let mut names = value_names::<Style>();
+    names.extend(user_styles.names().map(str::to_owned));
+    let suggestion = parse::closest(name, names.iter().map(String::as_str))
+        .map(|closest| format!(", did you mean '{closest}'?"))
+        .unwrap_or_default();
+    bail!("unknown style '{name}'{suggestion}")
+}
+
+/// Foreground, background, and formatting options of a premade style.
+fn style_props(style: Style) -> (Option<ColorSpec>, Option<ColorSpec>, Vec<FormattingOption>) {
+    let (fg, bg, options) = match style {
+        Style::Ok => (Some(Color::Green), None, Vec::new()),
+        Style::Notice => (Some(Color::Magenta), None, Vec::new()),
+        Style::Error => (Some(Color::Red), None, Vec::new()),
+        Style::Warn => (Some(Color::Yellow), None, Vec::new()),
+        Style::Info => (Some(Color::Cyan), None, Vec::new()),
+        Style::Debug => (
+            Some(Color::Black),
+            Some(Color::Cyan),
+            vec![FormattingOption::Dim],
+        ),
+    };
+    (fg.map(ColorSpec::from), bg.map(ColorSpec::from), options)
+}
+
+/// `;`-separated SGR parameters for colors and formatting options.
+pub(crate) fn prop_codes(
+    foreground: Option<ColorSpec>,
+    background: Option<ColorSpec>,
+    options: &[FormattingOption],
+) -> String {
+    let mut prop_codes = String::with_capacity(RESERVED_CAPACITY);
+    for (color, base, layer) in [(foreground, 30, 38), (background, 40, 48)] {
+        match color {
+            Some(ColorSpec::Named(color)) => {
+                push_code(&mut prop_codes, base + get_color_code_digit(color))
+            }
+            Some(ColorSpec::Indexed(index)) => {
+                push_code(&mut prop_codes, format_args!("{layer};5;{index}"))
+            }
+            Some(ColorSpec::Grey(percent)) => {
+                let index = ColorSpec::grey_index(percent);
+                push_code(&mut prop_codes, format_args!("{layer};5;{index}"))
+            }
+            Some(ColorSpec::Rgb(r, g, b)) => {
+                push_code(&mut prop_codes, format_args!("{layer};2;{r};{g};{b}"))
+            }
+            Some(ColorSpec::Default) => push_code(&mut prop_codes, base + 9),
+            None => {}
+        }
+    }
+    for option in options {
+        push_code(&mut prop_codes, get_format_code(option));
+    }
+    prop_codes
+}
+
+/// Resolve a markup tag: a color or formatting option name, `fg:COLOR`, `bg:COLOR`, or `style:STYLE`.
+///
+/// Short aliases of formatting options take precedence over those of colors.
+fn markup_tag(tag: &str) -> Result<String, ParseError> {
+    let prop_codes = match tag.split_once(':') {
+        Some(("fg", name)) => prop_codes(Some(color_spec(name, 3)?), None, &[]),
+        Some(("bg", name)) => prop_codes(None, Some(color_spec(name, 3)?), &[]),
+        Some(("style", name)) => {
+            let (fg, bg, options) = style_props(parse_value(name, 6, "unknown style")?);
+            prop_codes(fg, bg, &options)
+        }
+        Some((kind, _)) => {
+            let error = ParseError::token(0, kind, "unknown tag kind");
+            return Err(error.suggest(kind, ["fg", "bg", "style"]));
+        }
+        // Options first, so that {b} is bold rather than blue
+        None => match (
+            <FormattingOption as ValueEnum>::from_str(tag, false),
+            <Color as ValueEnum>::from_str(tag, false),
+        ) {
+            (Ok(option), _) => prop_codes(None, None, &[option]),
+            (_, Ok(fg)) => prop_codes(Some(fg.into()), None, &[]),
+            _ => {
+                let mut names = value_names::<Color>();
+                names.extend(value_names::<FormattingOption>());
+                let error = ParseError::token(0, tag, "unknown tag");
+                return Err(error.suggest(tag, names.iter().map(String::as_str)));
+            }
+        },
+    };
+    Ok(sgr(&prop_codes))
+}
+
+fn color_spec(name: &str, offset: usize) -> Result<ColorSpec, ParseError> {
+    name.parse()
+        .map_err(|error: ParseError| error.shifted(offset))
+}
+
+fn parse_value<T: ValueEnum>(name: &str, offset: usize, message: &str) -> Result<T, ParseError> {
+    T::from_str(name, false).map_err(|_| {
+        let names = value_names::<T>();
+        ParseError::token(offset, name, message).suggest(name, names.iter().map(String::as_str))
+    })
+}
+
+/// Names and aliases of all variants of a value enum.
+fn value_names<T: ValueEnum>() -> Vec<String> {
+    T::value_variants()
+        .iter()
+        .filter_map(ValueEnum::to_possible_value)
+        .flat_map(|value| {
+            value
+                .get_name_and_aliases()
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn render_template(path: &Path, base: &str, plain: bool) -> Result<String> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read template {}", path.display()))?;
+    let markup = Markup {
+        resolve: markup_tag,
+        variables: Some(|name: &str| std::env::var(name).ok()),
+        base: base.to_owned(),
+        plain,
+    };
+    let source = source.strip_suffix('\n').unwrap_or(&source);
+    markup.render(source).map_err(|error| {
+        let (line, column) = error.line_column(source);
+        anyhow!(
+            "{}:{line}:{column}: {}",
+            path.display(),
+            error.report(source)
+        )
+    })
+}
+
+fn render_markup(text: &str, base: &str, plain: bool) -> Result<String> {
+    let markup = Markup {
+        resolve: markup_tag,
+        variables: None::<fn(&str) -> Option<String>>,
+        base: base.to_owned(),
+        plain,
+    };
+    markup
+        .render(text)
+        .map_err(|error| anyhow!("invalid markup: {}", error.report(text)))
+}
+
+/// Styled prefix and suffix of lines.
+#[derive(Default)]
+pub(crate) struct Annotations {
+    prefix: Option<String>,
+    suffix: Option<String>,
+    timestamp: bool,
+    prefix_sgr: String,
+    suffix_sgr: String,
+    plain: bool,
+}
+
+impl Annotations {
+    fn new(args: &Args) -> Result<Self> {
+        let style = |style: &Option<String>| match (style, args.plain) {
+            (Some(style), false) => highlight_sgr(style),
+            _ => Ok(String::new()),
+        };
+        Ok(Self {
+            prefix: args.prefix.clone(),
+            suffix: args.suffix.clone(),
+            timestamp: args.timestamp,
+            prefix_sgr: style(&args.prefix_style)?,
+            suffix_sgr: style(&args.suffix_style)?,
+            plain: args.plain,
+        })
+    }
+
+    fn push_prefix(&self, buffer: &mut String) {
+        let text = match (self.timestamp, &self.prefix) {
+            (false, None) => return,
+            (false, Some(prefix)) => prefix.clone(),
+            (true, None) => format!("{} ", timestamp()),
+            (true, Some(prefix)) => format!("{} {prefix}", timestamp()),
+        };
+        self.push(buffer, &self.prefix_sgr, &text);
+    }
+
+    fn push_suffix(&self, buffer: &mut String) {
+        if let Some(suffix) = &self.suffix {
+            self.push(buffer, &self.suffix_sgr, suffix);
+        }
+    }
+
+    fn push(&self, buffer: &mut String, sgr: &str, text: &str) {
+        if self.plain || sgr.is_empty() {
+            buffer.push_str(text);
+        } else {
+            buffer.push_str(sgr);
+            buffer.push_str(text);
+            buffer.push_str(RESET);
+        }
+    }
+}
+
+/// Local time of day as `HH:MM:SS`.
+fn timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let seconds = (now + utc_offset(now)).rem_euclid(24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Offset of the local time zone from UTC in seconds.
+#[cfg(unix)]
+fn utc_offset(now: i64) -> i64 {
+    let time = now as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm it is given, and a zeroed tm is valid.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            return 0;
+        }
+        tm.tm_gmtoff
+    }
+}
+
+/// Offset of the local time zone from UTC in seconds (UTC where it is not available).
+#[cfg(not(unix))]
+fn utc_offset(_now: i64) -> i64 {
+    0
+}
+
+/// A pattern to highlight and the escape sequence to highlight it with.
+struct Highlight {
+    pattern: Regex,
+    sgr: String,
+}
+
+/// Parse the `PATTERN=STYLE` highlights.
+fn highlights(args: &Args) -> Result<Vec<Highlight>> {
+    args.highlight
+        .iter()
+        .map(|highlight| {
+            let Some((pattern, style)) = highlight.rsplit_once('=') else {
+                bail!("invalid highlight '{highlight}' (expected PATTERN=STYLE)");
+            };
+            let pattern = Regex::new(pattern)
+                .with_context(|| format!("invalid highlight pattern '{pattern}'"))?;
+            Ok(Highlight {
+                pattern,
+                sgr: highlight_sgr(style)?,
+            })
+        })
+        .collect()
+}
+
+/// A field to format, counting from 1 (or from the end if negative), and the escape sequence
+/// to format it with.
+struct Column {
+    index: isize,
+    sgr: String,
+}
+
+impl Column {
+    /// Position of the field among a number of fields, if there are enough of them.
+    fn position(&self, fields: usize) -> Option<usize> {
+        match self.index {
+            index if index > 0 => Some(index.unsigned_abs() - 1).filter(|&i| i < fields),
+            index => fields.checked_sub(index.unsigned_abs()),
+        }
+    }
+}
+
+/// Parse the `N[:STYLE]` columns, formatting the ones without a style as base.
+fn columns(args: &Args, base: &str) -> Result<Vec<Column>> {
+    args.column
+        .iter()
+        .map(|column| {
+            let (index, style) = match column.split_once(':') {
+                Some((index, style)) => (index, Some(style)),
+                None => (column.as_str(), None),
+            };
+            let Some(index) = index.parse().ok().filter(|&index: &isize| index != 0) else {
+                bail!("invalid column '{column}' (expected N or N:STYLE, where N is not 0)");
+            };
+            let sgr = match style {
+                Some(style) => highlight_sgr(style)?,
+                None => base.to_owned(),
+            };
+            Ok(Column { index, sgr })
+        })
+        .collect()
+}
+
+/// Wrap the fields of the columns in their formatting, leaving the rest of the line as it is.
+/// The first column matching a field wins.
+fn color_columns(line: &str, columns: &[Column], delimiter: Delimiter) -> String {
+    let fields = delimiter.fields(line);
+    let mut result = String::with_capacity(line.len() + RESERVED_CAPACITY);
+    let mut end = 0;
+    for (position, field) in fields.iter().enumerate() {
+        let Some(column) = columns
+            .iter()
+            .find(|column| column.position(fields.len()) == Some(position))
+        else {
+            continue;
+        };
+        if column.sgr.is_empty() {
+            continue;
+        }
+        result.push_str(&line[end..field.start]);
+        result.push_str(&column.sgr);
+        result.push_str(&line[field.clone()]);
+        result.push_str(RESET);
+        end = field.end;
+    }
+    result.push_str(&line[end..]);
+    result
+}
+
+/// Escape sequence for a style name, or else for comma-separated markup tags.
+fn highlight_sgr(style: &str) -> Result<String> {
+    if let Ok((fg, bg, options)) = resolve_style(style) {
+        return Ok(sgr(&prop_codes(fg, bg, &options)));
+    }
+    let mut result = String::new();
+    let mut offset = 0;
+    for tag in style.split(',') {
+        let sgr = markup_tag(tag).map_err(|error| {
+            anyhow!(
+                "invalid highlight style: {}",
+                error.shifted(offset).report(style)
+            )
+        })?;
+        result.push_str(&sgr);
+        offset += tag.len() + 1;
+    }
+    Ok(result)
+}
+
+/// Wrap the matches in their highlight, then reset and return to the base formatting.
+fn highlight(text: String, highlights: &[Highlight], base: &str) -> String {
+    if highlights.is_empty() {
+        return text;
+    }
+    let mut spans: Vec<(usize, usize, &str)> = Vec::new();
+    for highlight in highlights {
+        for found in highlight.pattern.find_iter(&text) {
+            let overlaps = spans
+                .iter()
+                .any(|&(start, end, _)| found.start() < end && start < found.end());
+            if !found.is_empty() && !overlaps {
+                spans.push((found.start(), found.end(), &highlight.sgr));
+            }
+        }
+    }
+    spans.sort_unstable_by_key(|&(start, _, _)| start);
+    let mut result = String::with_capacity(text.len() + spans.len() * RESERVED_CAPACITY);
+    let mut position = 0;
+    for (start, end, sgr) in spans {
+        result.push_str(&text[position..start]);
+        result.push_str(sgr);
+        result.push_str(&text[start..end]);
+        result.push_str(RESET);
+        result.push_str(base);
+        position = end;
+    }
+    result.push_str(&text[position..]);
+    result
+}
+
+/// Set the foreground color of each character with the color returned for it, passing
+/// escape sequences through.
+fn color_chars(text: &str, mut color: impl FnMut(char) -> Option<Rgb>) -> String {
+    let mut result = String::with_capacity(text.len() * 20);
+    for token in ansi::tokens(text.as_bytes()) {
+        // Tokens split the text at ESC bytes, so they are valid UTF-8
+        let raw = std::str::from_utf8(token.raw).unwrap_or_default();
+        if token.is_escape() {
+            result.push_str(raw);
+            continue;
+        }
+        for c in raw.chars() {
+            if let Some(rgb) = color(c) {
+                write!(result, "{}", rgb.fg_seq()).expect("writing to a String cannot fail");
+            }
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Color at t (0 to 1) along evenly spaced gradient stops, interpolated in OKLab.
+fn gradient_at(stops: &[Rgb], t: f64) -> Rgb {
+    if stops.len() == 1 {
+        return stops[0];
+    }
+    let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let index = (position.floor() as usize).min(stops.len() - 2);
+    stops[index].lerp_oklab(stops[index + 1], position - index as f64)
+}
+
+/// A field delimiter: whitespace, tab, or a single character.
+fn delimiter(value: &str) -> Result<Delimiter, String> {
+    match value {
+        "whitespace" => return Ok(Delimiter::Whitespace),
+        "tab" => return Ok(Delimiter::Char('\t')),
+        _ => {}
+    }
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Delimiter::Char(c)),
+        _ => Err("expected whitespace, tab, or a single character".to_owned()),
+    }
+}
+
+/// A parameter of an OSC sequence, which cannot contain control characters.
+fn osc_param(value: &str) -> Result<String, String> {
+    match value.chars().find(|c| c.is_control()) {
+        Some(c) => Err(format!("control character {c:?} is not allowed")),
+        None => Ok(value.to_owned()),
+    }
+}
+
+/// A hyperlink id, which cannot contain the `:` and `;` parameter separators.
+fn link_id(value: &str) -> Result<String, String> {
+    if value.contains([':', ';']) {
+        return Err("':' and ';' are not allowed".to_owned());
+    }
+    osc_param(value)
+}
+
+/// Decode backslash escapes as `echo -e` does, leaving unknown escapes as they are.
+fn unescape(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            result.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        // Up to max digits in the radix after the escape, and their value
+        let digits = |start: usize, max: usize, radix: u32| {
+            let count = bytes[start..]
+                .iter()
+                .take(max)
+                .take_while(|b| char::from(**b).is_digit(radix))
+                .count();
+            let value = bytes[start..start + count].iter().fold(0u32, |value, b| {
+                value * radix + char::from(*b).to_digit(radix).unwrap_or_default()
+            });
+            (count, value as u8)
+        };
+        let (decoded, len) = match bytes[i + 1] {
+            b'n' => (b'\n', 2),
+            b't' => (b'\t', 2),
+            b'r' => (b'\r', 2),
+            b'\\' => (b'\\', 2),
+            b'e' => (0x1b, 2),
+            b'0' => {
+                let (count, value) = digits(i + 2, 3, 8);
+                (value, 2 + count)
+            }
+            b'x' => match digits(i + 2, 2, 16) {
+                (0, _) => (b'\\', 1),
+                (count, value) => (value, 2 + count),
+            },
+            _ => (b'\\', 1),
+        };
+        result.push(decoded);
+        i += len;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+fn get_format_code(option: &FormattingOption) -> u8 {
+    match option {
+        FormattingOption::Bold => 1,
+        FormattingOption::Dim => 2,
+        FormattingOption::Underline => 4,
+        FormattingOption::Italic => 3,
+        FormattingOption::Inverted => 7,
+        FormattingOption::Strikethrough => 9,
+        FormattingOption::Blink => 5,
+        FormattingOption::Hidden => 8,
+        FormattingOption::Overline => 53,
+        FormattingOption::DoubleUnderline => 21,
+    }
+}
+
+fn get_color_code_digit(color: Color) -> u8 {
+    match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::BrightBlack => 60,
+        Color::BrightRed => 61,
+        Color::BrightGreen => 62,
+        Color::BrightYellow => 63,
+        Color::BrightBlue => 64,
+        Color::BrightMagenta => 65,
+        Color::BrightCyan => 66,
+        Color::BrightWhite => 67,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::fuzz::{check_error, Rng, CASES};
+    use clap::CommandFactory;
+
+    fn args(argv: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("printcolor").chain(argv.iter().copied()))
+            .unwrap_or_else(|error| panic!("{argv:?}: {error}"))
+    }
+
+    /// Output of the arguments, with LF newlines.
+    fn streamed(argv: &[&str], input: &str) -> String {
+        let mut buffer = Vec::new();
+        let output = RecordWriter::new(&mut buffer, false);
+        let argv = [&["--lf", "--when=always"], argv].concat();
+        format_stream(args(&argv), input.as_bytes(), output)
+            .unwrap_or_else(|error| panic!("{argv:?}: {error}"));
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn output(argv: &[&str]) -> String {
+        let argv = [&["--lf", "--when=always"], argv].concat();
+        format(args(&argv)).unwrap_or_else(|error| panic!("{argv:?}: {error}"))
+    }
+
+    #[test]
+    fn word_diff_highlights_inserted_words() {
+        assert_eq!(
+            output(&[
+                "-f",
+                "red",
+                "--word-diff",
+                "the quick fox",
+                "the slow  fox jumps"
+            ]),
+            "\x1b[31mthe \x1b[31;42mslow\x1b[m\x1b[31m  fox \x1b[31;42mjumps\x1b[m\x1b[31m\x1b[m\n"
+        );
+        assert_eq!(
+            output(&[
+                "--word-diff",
+                "--show-removed",
+                "the quick fox",
+                "the slow fox"
+            ]),
+            "the \x1b[31;9mquick\x1b[m \x1b[42mslow\x1b[m fox\x1b[m\n"
+        );
+    }
+
+    #[test]
+    fn word_diff_needs_two_texts() {
+        let error = format(args(&["--word-diff", "a", "b", "c"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--word-diff expects two texts (old and new)"
+        );
+    }
+
+    #[test]
+    fn meter_follows_the_text() {
+        assert_eq!(
+            output(&["--meter", "5", "--meter-max", "10", "--meter-width", "2", "-f", "red", "disk"]),
+            "\x1b[31mdisk\x1b[m \x1b[48;2;0;255;0m\x1b[38;2;0;0;0m5\x1b[48;2;48;48;48m\x1b[38;2;255;255;255m \x1b[m\n"
+        );
+    }
+
+    #[test]
+    fn meter_fills_the_columns_the_text_leaves() {
+        let line = output(&["--meter", "5", "-f", "red", "日本"]);
+        let columns = terminal::width().unwrap_or(DEFAULT_WIDTH);
+        assert_eq!(width::width(line.trim_end()), columns);
+    }
+
+    /// Template file with the given contents, unique to the test.
+    fn template(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("printcolor-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn template_renders_markup_over_the_base_style() {
+        let path = template("render", "{bold}Hello{/} world\n");
+        let path = path.to_str().unwrap();
+        assert_eq!(
+            output(&["-f", "green", "--template", path]),
+            "\x1b[32m\x1b[1mHello\x1b[m\x1b[32m world\x1b[m\n"
+        );
+        assert_eq!(output(&["--template", path, "--check-template"]), "");
+    }
+
+    #[test]
+    fn template_errors_have_line_numbers() {
+        let path = template("error", "fine\n  {nope}\n");
+        let error = format(args(&["--template", path.to_str().unwrap()])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{}:2:4: unknown tag 'nope'\n2 |   {{nope}}\n  |    ^^^^",
+                path.display()
+            )
+        );
+        let missing = format(args(&["--template", "/nonexistent/template"])).unwrap_err();
+        assert_eq!(
+            missing.to_string(),
+            "failed to read template /nonexistent/template"
+        );
+    }
+
+    #[test]
+    fn length_of_each_line_or_the_widest() {
+        assert_eq!(output(&["--length", "ab\n漢字\x1b[1mx"]), "2\n5\n");
+        assert_eq!(output(&["--length=max", "abc\n\na"]), "3\n");
+        assert_eq!(output(&["--length", "-n", "abc"]), "3");
+        let crlf = format(args(&["--crlf", "--length", "a\nbc"])).unwrap();
+        assert_eq!(crlf, "1\r\n2\r\n");
+    }
+
+    #[test]
+    fn line_endings_follow_crlf_and_lf() {
+        let ending = |flags: &[&str]| {
+            let argv = [&["--when=always", "-f", "red"], flags, &["hi"]].concat();
+            format(args(&argv)).unwrap()
+        };
+        assert_eq!(ending(&["--crlf"]), "\x1b[31mhi\x1b[m\r\n");
+        assert_eq!(ending(&["--lf"]), "\x1b[31mhi\x1b[m\n");
+        assert_eq!(
+            ending(&[]),
+            format!("\x1b[31mhi\x1b[m{}", terminal::newline())
+        );
+        assert!(Args::try_parse_from(["printcolor", "--crlf", "--lf", "hi"]).is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn lf_overrides_the_windows_newline() {
+        assert_eq!(format(args(&["hi"])).unwrap(), "hi\r\n");
+        assert_eq!(format(args(&["--lf", "hi"])).unwrap(), "hi\n");
+    }
+
+    #[test]
+    fn colors_fall_back_to_plain_text_without_escape_processing() {
+        let cases: [&[&str]; 4] = [
+            &["-f", "red", "-o", "bold", "hi"],
+            &["--rainbow", "hi"],
+            &["--highlight", "h=warn", "hi"],
+            &["--meter", "1", "--meter-width", "2", "hi"],
+        ];
+        for argv in cases {
+            // As print does when enable_vt_processing fails
+            let mut args = args(&[&["--lf", "--when=always"], argv].concat());
+            args.disable_colors();
+            let output = format(args).unwrap();
+            assert!(!output.contains('\x1b'), "{argv:?}: {output:?}");
+            assert!(output.starts_with("hi"), "{argv:?}: {output:?}");
+        }
+    }
+
+    #[test]
+    fn highlights_return_to_the_base_style() {
+        let args = [
+            "-f",
+            "blue",
+            "--highlight",
+            "err\\w*=red",
+            "--highlight",
+            "e=bold",
+        ];
+        assert_eq!(
+            output(&[&args[..], &["an error here"]].concat()),
+            "\x1b[34man \x1b[31merror\x1b[m\x1b[34m h\x1b[1me\x1b[m\x1b[34mr\x1b[1me\x1b[m\x1b[34m\x1b[m\n"
+        );
+        assert_eq!(
+            output(&["--highlight", "x=bold,underline", "axb"]),
+            "a\x1b[1m\x1b[4mx\x1b[mb\x1b[m\n"
+        );
+    }
+
+    #[test]
+    fn highlight_errors() {
+        let error = |highlight| {
+            let error =
+                format(args(&["--when=always", "--highlight", highlight, "a"])).unwrap_err();
+            error.to_string()
+        };
+        assert_eq!(error("x"), "invalid highlight 'x' (expected PATTERN=STYLE)");
+        assert_eq!(error("(=red"), "invalid highlight pattern '('");
+        assert_eq!(
+            error("x=nope,bold"),
+            "invalid highlight style: unknown tag 'nope'\n1 | nope,bold\n  | ^^^^"
+        );
+    }
+
+    #[test]
+    fn every_line_is_annotated() {
+        let args = ["-f", "red", "--prefix", "> ", "--suffix", " <"];
+        assert_eq!(
+            output(&[&args[..], &["--prefix-style", "green", "a\nb"]].concat()),
+            "\x1b[32m> \x1b[m\x1b[31ma\x1b[m <\n\x1b[32m> \x1b[m\x1b[31mb\x1b[m <\n"
+        );
+        assert_eq!(
+            streamed(&args, "a\nb\n"),
+            "> \x1b[31ma\x1b[m <\n> \x1b[31mb\x1b[m <\n"
+        );
+        let timestamped = output(&["--timestamp", "--prefix", "| ", "x"]);
+        let pattern = Regex::new(r"^\d\d:\d\d:\d\d \| x\x1b\[m\n$").unwrap();
+        assert!(pattern.is_match(&timestamped), "{timestamped:?}");
+    }
+
+    #[test]
+    fn when_never_and_always() {
+        let plain = format(args(&["--lf", "--when", "never", "-f", "red", "x"])).unwrap();
+        assert_eq!(plain, "x\n");
+        assert_eq!(output(&["-f", "red", "x"]), "\x1b[31mx\x1b[m\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_summary_by_exit_status() {
+        let summary = |argv: &[&str]| {
+            let argv = [&["--lf", "--when=always", "--run", "-q", "--"], argv].concat();
+            run(args(&argv)).unwrap()
+        };
+        assert_eq!(summary(&["true"]), ("\x1b[32m✓ true\x1b[m\n".to_owned(), 0));
+        assert_eq!(
+            summary(&["sh", "-c", "exit 3"]),
+            ("\x1b[31m✗ sh -c exit 3 (exit 3)\x1b[m\n".to_owned(), 3)
+        );
+        let (killed, code) = summary(&["sh", "-c", "kill -9 $$"]);
+        assert!(killed.ends_with("(signal 9)\x1b[m\n"), "{killed:?}");
+        assert_eq!(code, 137);
+        let missing = run(args(&["--run", "/nonexistent/command"])).unwrap_err();
+        assert_eq!(missing.to_string(), "failed to run '/nonexistent/command'");
+    }
+
+    #[test]
+    fn banner_centers_in_the_terminal_width() {
+        let padding = terminal::width().unwrap_or(DEFAULT_WIDTH) - 2;
+        let (left, right) = (" ".repeat(padding / 2), " ".repeat(padding - padding / 2));
+        assert_eq!(
+            output(&["-b", "blue", "--banner", "hi"]),
+            format!("\x1b[44m{left}hi{right}\x1b[m\n")
+        );
+        assert_eq!(
+            output(&["--banner", "--width", "6", "--align", "right", "--fill", "-", "漢"]),
+            "----漢\x1b[m\n"
+        );
+        assert_eq!(
+            output(&["--width", "3", "--truncate", "漢字x"]),
+            "漢 \x1b[m\n"
+        );
+        assert!(Args::try_parse_from(["printcolor", "--align", "left", "x"]).is_err());
+    }
+
+    #[test]
+    fn underline_color_implies_underline() {
+        let underline = |argv: &[&str]| output(&[&["--underline-color"], argv, &["x"]].concat());
+        assert_eq!(underline(&["red"]), "\x1b[4;58;5;1mx\x1b[m\n");
+        assert_eq!(underline(&["bright-blue"]), "\x1b[4;58;5;12mx\x1b[m\n");
+        assert_eq!(
+            underline(&["200", "-o", "double-underline"]),
+            "\x1b[21;58;5;200mx\x1b[m\n"
+        );
+        assert_eq!(underline(&["#ff8000"]), "\x1b[4;58;2;255;128;0mx\x1b[m\n");
+        assert_eq!(
+            underline(&["red", "--no-reset", "--reset-underline-color"]),
+            "\x1b[4;58;5;1mx\x1b[59m\n"
+        );
+    }
+
+    #[test]
+    fn alternate_colors_by_word_or_argument() {
+        let text = ["one two", "three"];
+        assert_eq!(
+            output(&[&["--alternate", "red,blue"], &text[..]].concat()),
+            "\x1b[31mone \x1b[34mtwo \x1b[31mthree\x1b[m\n"
+        );
+        assert_eq!(
+            output(
+                &[
+                    &["--alternate", "red,blue", "--alternate-by", "arg"],
+                    &text[..]
+                ]
+                .concat()
+            ),
+            "\x1b[31mone two \x1b[34mthree\x1b[m\n"
+        );
+        // Streamed lines continue the cycle
+        assert_eq!(
+            streamed(&["--alternate", "red,blue"], "a b\nc\n"),
+            "\x1b[31ma \x1b[34mb\x1b[m\n\x1b[31mc\x1b[m\n"
+        );
+    }
+
+    #[test]
+    fn codes_on_their_own() {
+        assert_eq!(
+            output(&["-f", "red", "-o", "bold", "--code-only"]),
+            "\x1b[31;1m"
+        );
+        assert_eq!(output(&["--reset-code"]), "\x1b[m");
+        assert_eq!(output(&["--reset-code", "--escape", "bash"]), "\\[\\e[m\\]");
+        let never = format(args(&["--when=never", "-f", "red", "--code-only"])).unwrap();
+        assert_eq!(never, "");
+        assert!(Args::try_parse_from(["printcolor", "--code-only", "x"]).is_err());
+    }
+
+    #[test]
+    fn shells_quote_escape_sequences() {
+        let sequence = "\x1b[31m'\\";
+        assert_eq!(Shell::Bash.define("A", sequence), "A=$'\\e[31m\\'\\\\'");
+        assert_eq!(Shell::Zsh.define("A", "\x1b[m"), "A=$'\\e[m'");
+        assert_eq!(
+            Shell::Fish.define("A", sequence),
+            "set -gx A \\e'[31m\\'\\\\'"
+        );
+        assert_eq!(
+            Shell::Fish.define("A", "\x1b[1m\x1b[2m"),
+            "set -gx A \\e'[1m'\\e'[2m'"
+        );
+    }
+
+    #[test]
+    fn export_every_premade_style_and_the_reset() {
+        let exports = output(&["--export-styles", "bash", "--export-prefix", "S_"]);
+        let lines: Vec<&str> = exports.lines().collect();
+        assert_eq!(
+            lines[..3],
+            [
+                "S_OK=$'\\e[32m'",
+                "S_NOTICE=$'\\e[35m'",
+                "S_ERROR=$'\\e[31m'"
+            ]
+        );
+        assert_eq!(lines.last(), Some(&"S_RESET=$'\\e[m'"));
+        for style in Style::value_variants() {
+            let name = style.to_string().to_ascii_uppercase().replace('-', "_");
+            assert!(exports.contains(&format!("S_{name}=")), "{name}: {exports}");
+        }
+    }
+
+    #[test]
+    fn null_separated_records() {
+        assert_eq!(
+            streamed(&["-0", "-f", "red"], "a\nb\0c\0"),
+            "\x1b[31ma\nb\x1b[m\0\x1b[31mc\x1b[m\0"
+        );
+        assert_eq!(
+            streamed(&["-0", "--null-output", "false"], "a\0b"),
+            "a\x1b[m\nb\x1b[m\n"
+        );
+        assert!(Args::try_parse_from(["printcolor", "--null-output", "false"]).is_err());
+    }
+
+    #[test]
+    fn greys_are_the_nearest_palette_grey() {
+        let indexes: Vec<u8> = [0, 3, 4, 50, 95, 96, 100]
+            .into_iter()
+            .map(ColorSpec::grey_index)
+            .collect();
+        assert_eq!(indexes, [16, 232, 232, 244, 255, 255, 231]);
+        assert_eq!("gray:50".parse::<ColorSpec>(), Ok(ColorSpec::Grey(50)));
+        assert_eq!(ColorSpec::Grey(7).to_string(), "grey:7");
+        let error = "grey:101".parse::<ColorSpec>().unwrap_err();
+        assert_eq!(
+            error.message,
+            "grey must be a percentage (0-100), found '101'"
+        );
+        assert_eq!(
+            output(&["-f", "grey:50", "-b", "grey:0", "x"]),
+            "\x1b[38;5;244;48;5;16mx\x1b[m\n"
+        );
+    }
+
+    #[test]
+    fn default_resets_only_its_layer() {
+        assert_eq!(
+            output(&[
+                "-f",
+                "default",
+                "-b",
+                "d",
+                "--underline-color",
+                "default",
+                "x"
+            ]),
+            "\x1b[39;49;4;59mx\x1b[m\n"
+        );
+        assert_eq!(ColorSpec::Default.to_string(), "default");
+        let error = "defualt".parse::<ColorSpec>().unwrap_err();
+        assert_eq!(error.suggestion.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn rgb_and_hsv_functions_are_colors() {
+        assert_eq!(
+            "rgb(1, 2, 3)".parse::<ColorSpec>(),
+            Ok(ColorSpec::Rgb(1, 2, 3))
+        );
+        assert_eq!(
+            "HSV(0, 100, 100)".parse::<ColorSpec>(),
+            Ok(ColorSpec::Rgb(255, 0, 0))
+        );
+        assert_eq!(
+            output(&["-f", "rgb(1,2,3)", "x"]),
+            "\x1b[38;2;1;2;3mx\x1b[m\n"
+        );
+    }
+
+    #[test]
+    fn underline_style_replaces_plain_underlines() {
+        assert_eq!(
+            output(&[
+                "--underline-style",
+                "curly",
+                "-o",
+                "underline",
+                "-o",
+                "bold",
+                "x"
+            ]),
+            "\x1b[1;4:3mx\x1b[m\n"
+        );
+        assert_eq!(
+            output(&[
+                "--underline-style",
+                "dashed",
+                "--underline-color",
+                "red",
+                "x"
+            ]),
+            "\x1b[4:5;58;5;1mx\x1b[m\n"
+        );
+    }
+
+    fn segments(argv: &[&str]) -> Result<String> {
+        let argv = std::iter::once("printcolor").chain(argv.iter().copied());
+        let segments = segment_args(argv.map(OsString::from))
+            .into_iter()
+            .map(|argv| Args::try_parse_from(argv).unwrap())
+            .collect();
+        format_segments(segments)
+    }
+
+    #[test]
+    fn segments_share_one_line() {
+        let argv = ["printcolor", "-f", "red", "a", "--then", "b"].map(OsString::from);
+        assert_eq!(
+            segment_args(argv),
+            [
+                vec!["printcolor", "-f", "red", "a"],
+                vec!["printcolor", "b"]
+            ]
+            .map(|argv| argv.into_iter().map(OsString::from).collect::<Vec<_>>())
+        );
+        assert_eq!(
+            segments(&[
+                "--lf",
+                "--when=always",
+                "-f",
+                "green",
+                "OK",
+                "--then",
+                ": ",
+                "--then",
+                "-o",
+                "bold",
+                "--lf",
+                "x"
+            ])
+            .unwrap(),
+            "\x1b[32mOK\x1b[m: \x1b[m\x1b[1mx\x1b[m\n"
+        );
+        // The first segment decides on colors for the others
+        assert_eq!(
+            segments(&[
+                "--when=never",
+                "-f",
+                "red",
+                "a",
+                "--then",
+                "-f",
+                "blue",
+                "--lf",
+                "b"
+            ])
+            .unwrap(),
+            "ab\n"
+        );
+        let error = segments(&["a", "--then", "--run", "true"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--run and --strip cannot be used with --then"
+        );
+    }
+
+    #[test]
+    fn fields_by_whitespace_or_a_character() {
+        let line = "  a  b c ";
+        let fields: Vec<&str> = (Delimiter::Whitespace.fields(line).into_iter())
+            .map(|field| &line[field])
+            .collect();
+        assert_eq!(fields, ["a", "b", "c"]);
+        let line = "a,,b";
+        let fields: Vec<&str> = (Delimiter::Char(',').fields(line).into_iter())
+            .map(|field| &line[field])
+            .collect();
+        assert_eq!(fields, ["a", "", "b"]);
+        assert_eq!(delimiter("tab"), Ok(Delimiter::Char('\t')));
+        assert!(delimiter("ab").is_err());
+    }
+
+    #[test]
+    fn columns_keep_the_spacing_of_the_line() {
+        let argv = ["--column", "2", "-f", "red", "--column", "-1:bold"];
+        assert_eq!(
+            streamed(&argv, "  a  b c\nx\n"),
+            "  a  \x1b[31mb\x1b[m \x1b[1mc\x1b[m\x1b[m\n\x1b[1mx\x1b[m\x1b[m\n"
+        );
+        assert_eq!(
+            streamed(&["--column", "2:blue", "--delimiter", ","], "a,b,c\n"),
+            "a,\x1b[34mb\x1b[m,c\x1b[m\n"
+        );
+        let error = format(args(&["--column", "2", "x"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--column formats the lines of stdin, not text arguments"
+        );
+    }
+
+    #[test]
+    fn column_zero_is_invalid() {
+        let mut buffer = Vec::new();
+        let output = RecordWriter::new(&mut buffer, false);
+        let argv = ["--when=always", "--column", "0"];
+        let error = format_stream(args(&argv), &b"a\n"[..], output).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid column '0' (expected N or N:STYLE, where N is not 0)"
+        );
+    }
+
+    #[test]
+    fn diff_headers_only_precede_the_hunks() {
+        let diff = concat!(
+            "diff --git a/f b/f\n",
+            "--- a/f\n",
+            "+++ b/f\n",
+            "@@ -1,2 +1,2 @@\n",
+            " same\n",
+            "--- removed\n",
+            "+++ added\n",
+            "diff --git a/g b/g\n",
+            "--- a/g\n",
+        );
+        assert_eq!(
+            streamed(&["--diff"], diff),
+            concat!(
+                "\x1b[1mdiff --git a/f b/f\x1b[m\n",
+                "\x1b[1m--- a/f\x1b[m\n",
+                "\x1b[1m+++ b/f\x1b[m\n",
+                "\x1b[36m@@ -1,2 +1,2 @@\x1b[m\n",
+                " same\x1b[m\n",
+                "\x1b[31m--- removed\x1b[m\n",
+                "\x1b[32m+++ added\x1b[m\n",
+                "\x1b[1mdiff --git a/g b/g\x1b[m\n",
+                "\x1b[1m--- a/g\x1b[m\n",
+            )
+        );
+    }
+
+    #[test]
+    fn diff_lines_of_context_and_normal_diffs() {
+        let context = "*** a\n--- b\n***************\n*** 1 ****\n! x\n--- 1 ----\n! y\n";
+        assert_eq!(
+            streamed(&["--diff", "--diff-change", "yellow"], context),
+            concat!(
+                "\x1b[1m*** a\x1b[m\n",
+                "\x1b[1m--- b\x1b[m\n",
+                "\x1b[36m***************\x1b[m\n",
+                "\x1b[36m*** 1 ****\x1b[m\n",
+                "\x1b[33m! x\x1b[m\n",
+                "\x1b[36m--- 1 ----\x1b[m\n",
+                "\x1b[33m! y\x1b[m\n",
+            )
+        );
+        assert_eq!(
+            streamed(&["--diff", "--no-reset"], "1c1\n< a\n---\n> \x1b[1mb\n"),
+            "\x1b[36m1c1\x1b[m\n\x1b[31m< a\x1b[m\n\x1b[36m---\x1b[m\n> \x1b[1mb\n"
+        );
+    }
+
+    #[test]
+    fn log_lines_by_level() {
+        let argv = ["--log", "--log-scope", "token", "--map", "fatal=red,bold"];
+        assert_eq!(
+            streamed(&argv, "ERROR boom\n[warn] x\nfatal: y\nplain\n"),
+            concat!(
+                "\x1b[31mERROR\x1b[m boom\x1b[m\n",
+                "\x1b[33m[warn]\x1b[m x\x1b[m\n",
+                "\x1b[31m\x1b[1mfatal\x1b[m: y\x1b[m\n",
+                "plain\x1b[m\n",
+            )
+        );
+        assert_eq!(
+            streamed(&["--log"], "INFO ok\n"),
+            "\x1b[36mINFO ok\x1b[m\x1b[m\n"
+        );
+        let words: Vec<Range<usize>> = words("[a1] b-c").collect();
+        assert_eq!(words, [1..3, 5..6, 7..8]);
+    }
+
+    #[test]
+    fn log_maps_are_level_and_style() {
+        let error = |map| {
+            let argv = ["--when=always", "--log", "--map", map];
+            let output = RecordWriter::new(Vec::new(), false);
+            let error = format_stream(args(&argv), &b"x\n"[..], output).unwrap_err();
+            error.to_string()
+        };
+        assert_eq!(error("fatal"), "invalid map 'fatal' (expected LEVEL=STYLE)");
+        assert_eq!(
+            error("a b=red"),
+            "invalid log level 'a b' (expected letters and digits)"
+        );
+    }
+
+    #[test]
+    fn command_is_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn text_can_look_like_every_short_flag() {
+        let command = Args::command();
+        let mut shorts: Vec<char> = command
+            .get_arguments()
+            .filter_map(|a| a.get_short())
+            .collect();
+        shorts.extend(['h', 'V']);
+        shorts.sort_unstable();
+        // A new short flag must be covered here as well
+        assert_eq!(
+            shorts.iter().collect::<String>(),
+            "0RVbefhnoqrs",
+            "short flags changed"
+        );
+        for short in shorts {
+            let flag = format!("-{short}");
+            let trailing = args(&["-f", "red", "word", &flag]);
+            assert_eq!(trailing.text, ["word", flag.as_str()], "word {flag}");
+            let explicit = args(&["--text", &flag]);
+            assert_eq!(explicit.explicit_text, [flag.as_str()], "--text {flag}");
+            let mixed = args(&["--text", &flag, "-n"]);
+            assert!(mixed.no_newline, "--text {flag} -n");
+        }
+    }
+
+    #[test]
+    fn markup_short_aliases() {
+        assert_eq!(markup_tag("b").unwrap(), "\x1b[1m");
+        assert_eq!(markup_tag("blue").unwrap(), "\x1b[34m");
+        assert_eq!(markup_tag("fg:b").unwrap(), "\x1b[34m");
+        assert_eq!(markup_tag("B").unwrap(), "\x1b[94m");
+        assert_eq!(markup_tag("i").unwrap(), "\x1b[3m");
+        assert_eq!(markup_tag("r").unwrap(), "\x1b[31m");
+        assert!(markup_tag("x").is_err());
+    }
+
+    #[test]
+    fn italic_and_inverted_bytes() {
+        for option in ["italic", "i"] {
+            let output = format(args(&["--when", "always", "--lf", "-o", option, "x"])).unwrap();
+            assert_eq!(output.as_bytes(), b"\x1b[3mx\x1b[m\n", "-o {option}");
+        }
+        for option in ["inverted", "v", "inv"] {
+            let output = format(args(&["--when", "always", "--lf", "-o", option, "x"])).unwrap();
+            assert_eq!(output.as_bytes(), b"\x1b[7mx\x1b[m\n", "-o {option}");
+        }
+    }
+
+    #[test]
+    fn each_line_is_styled_and_reset() {
+        let output = format(args(&[
+            "--when",
+            "always",
+            "--lf",
+            "-f",
+            "red",
+            "one\n\ntwo",
+        ]))
+        .unwrap();
+        assert_eq!(
+            output.as_bytes(),
+            b"\x1b[31mone\x1b[m\n\n\x1b[31mtwo\x1b[m\n"
+        );
+        let argv = ["--when", "always", "--lf", "-b", "blue", "-r", "one\ntwo"];
+        let output = format(args(&argv)).unwrap();
+        assert_eq!(output.as_bytes(), b"\x1b[44mone\x1b[m\n\x1b[44mtwo\n");
+    }
+
+    #[test]
+    fn when_always_never_and_auto() {
+        let cases: [(&[&str], &[u8]); 4] = [
+            (&[], b"\x1b[31mx\x1b[m\n"),
+            (&["-R"], b"\x1b[m\x1b[31mx\x1b[m\n"),
+            (&["-r"], b"\x1b[31mx\n"),
+            (&["-R", "-r"], b"\x1b[m\x1b[31mx\n"),
+        ];
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let colored = !no_color && io::stdout().is_terminal();
+        for (flags, styled) in cases {
+            let output = |when: &str| {
+                let mut argv = vec!["--when", when, "--lf", "-f", "red"];
+                argv.extend(flags);
+                argv.push("x");
+                format(args(&argv)).unwrap()
+            };
+            assert_eq!(output("always").as_bytes(), styled, "{flags:?}");
+            // Resets are dropped along with the colors
+            assert_eq!(output("never"), "x\n", "{flags:?}");
+            let auto = match colored {
+                true => styled,
+                false => b"x\n",
+            };
+            assert_eq!(output("auto").as_bytes(), auto, "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn prompt_prefix_of_empty_text() {
+        let output = |escape: &str, text: &str| {
+            format(args(&["--escape", escape, "-f", "green", "-n", text])).unwrap()
+        };
+        assert_eq!(output("bash", ""), r"\[\e[32m\]");
+        assert_eq!(output("zsh", ""), r"%{\e[32m%}");
+        assert_eq!(output("literal", ""), r"\e[32m");
+        assert_eq!(output("bash", "$"), r"\[\e[32m\]$\[\e[m\]");
+        assert_eq!(output("zsh", "$"), r"%{\e[32m%}$%{\e[m%}");
+        let with_newline = format(args(&["--escape", "bash", "--lf", "-f", "green", ""])).unwrap();
+        assert_eq!(with_newline, concat!(r"\[\e[32m\]\[\e[m\]", "\n"));
+    }
+
+    #[test]
+    fn flags_after_the_first_word_are_text() {
+        let args = args(&["-s", "warn", "-r", "is", "-f", "dangerous"]);
+        assert!(args.no_reset);
+        assert!(args.foreground.is_none());
+        assert_eq!(args.text, ["is", "-f", "dangerous"]);
+    }
+
+    #[test]
+    fn markup_never_panics_and_strips_to_its_plain_render() {
+        let alphabet = [
+            "{",
+            "}",
+            "{/}",
+            "{b}",
+            "{red}",
+            "{fg:",
+            "{bg:#123}",
+            "{style:warn}",
+            "{x}",
+            ":",
+            "{{",
+            "}}",
+            "text",
+            " ",
+            "\n",
+            "é",
+        ];
+        let mut rng = Rng::new(0x3a4c);
+        for _ in 0..CASES {
+            let source = rng.string(&alphabet, 10);
+            let markup = |plain| Markup {
+                resolve: markup_tag,
+                variables: None::<fn(&str) -> Option<String>>,
+                base: String::new(),
+                plain,
+            };
+            match markup(false).render(&source) {
+                Ok(rendered) => {
+                    let plain = markup(true).render(&source).unwrap();
+                    assert_eq!(
+                        ansi::strip(rendered.as_bytes()),
+                        plain.as_bytes(),
+                        "{source:?}"
+                    );
+                    assert!(ansi::first_undecodable(rendered.as_bytes()).is_none());
+                }
+                Err(error) => check_error(&source, &error),
+            }
+        }
+    }
+
+    #[test]
+    fn painted_text_strips_back() {
+        let colors = [
+            "red",
+            "B",
+            "202",
+            "#1e1e2e",
+            "grey:40",
+            "default",
+            "hsl(200, 50%, 50%)",
+        ];
+        let texts = ["a", "é", " ", "\t", "word", "\n", "", "{b}", "%"];
+        let mut rng = Rng::new(0x9a1);
+        for _ in 0..CASES {
+            let text = rng.string(&texts, 6);
+            let mut options = StyleBuilder::new().no_reset(rng.below(4) == 0);
+            if rng.below(2) == 0 {
+                options = options.foreground(
+                    colors[rng.below(colors.len())]
+                        .parse::<ColorSpec>()
+                        .unwrap(),
+                );
+            }
+            if rng.below(2) == 0 {
+                options = options.background(
+                    colors[rng.below(colors.len())]
+                        .parse::<ColorSpec>()
+                        .unwrap(),
+                );
+            }
+            let all = FormattingOption::value_variants();
+            for _ in 0..rng.below(3) {
+                options = options.option(all[rng.below(all.len())]);
+            }
+            let painted = paint(&text, &options);
+            assert_eq!(
+                ansi::strip(painted.as_bytes()),
+                text.as_bytes(),
+                "{text:?} {options:?}"
+            );
+            assert!(
+                ansi::first_undecodable(painted.as_bytes()).is_none(),
+                "{painted:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn emitted_sequences_decode_and_strip_to_the_text() {
+        let styles = [
+            None,
+            Some("ok"),
+            Some("notice"),
+            Some("error"),
+            Some("warn"),
+            Some("info"),
+        ];
+        let colors = [
+            "red",
+            "g",
+            "BLUE",
+            "bright-magenta",
+            "202",
+            "#ff8800",
+            "1e1e2e",
+            "#abc",
+            "rgb(1, 2, 3)",
+            "hsv(120, 50%, 50%)",
+            "hsl(300, 100%, 25%)",
+            "grey:40",
+            "default",
+        ];
+        let attributes: Vec<Option<String>> = FormattingOption::value_variants()
+            .iter()
+            .map(|attribute| Some(attribute.to_string()))
+            .chain([None])
+            .collect();
+        let flags: [&[&str]; 5] = [&[], &["-R"], &["-r"], &["-n"], &["-R", "-r", "-n"]];
+        let mut case = 0;
+        for style in styles {
+            for attribute in &attributes {
+                for flags in flags {
+                    // Cycle through the colors, so that each is used as both layers
+                    case += 1;
+                    let foreground = colors[case % colors.len()];
+                    let background = colors[case * 7 % colors.len()];
+                    let mut argv = vec!["--when", "always", "-f", foreground, "-b", background];
+                    if let Some(style) = style {
+                        argv.extend(["-s", style]);
+                    }
+                    if let Some(attribute) = attribute {
+                        argv.extend(["-o", attribute]);
+                    }
+                    argv.extend(flags);
+                    argv.extend(["hello", "world"]);
+                    let args = args(&argv);
+                    let plain = match args.no_newline {
+                        true => "hello world".to_owned(),
+                        false => format!("hello world{}", args.newline()),
+                    };
+                    let output = format(args).unwrap();
+                    assert_eq!(ansi::strip(output.as_bytes()), plain.as_bytes(), "{argv:?}");
+                    let undecodable = ansi::first_undecodable(output.as_bytes());
+                    assert!(undecodable.is_none(), "{argv:?}: {undecodable:?}");
+                }
+            }
+        }
     }
 }
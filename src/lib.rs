@@ -1 +1,24 @@
+//! Format text and display colors in the terminal.
+//!
+//! The binaries are built on this library. [`prelude`] exports the items for styling text;
+//! [`color`] has RGB colors and their conversions (HSV, HSL, OKLab, and the 256-color
+//! palette), [`format`](mod@format) builds the escape sequences, and [`table`] renders the
+//! tables and charts of colortable.
+
+pub mod ansi;
+pub mod color;
+pub(crate) mod explain;
 pub mod format;
+pub(crate) mod lscolors;
+pub(crate) mod markup;
+pub(crate) mod meter;
+pub mod names;
+pub mod output;
+pub mod parse;
+pub mod prelude;
+pub(crate) mod styled;
+pub(crate) mod styles;
+pub mod table;
+pub mod terminal;
+pub(crate) mod width;
+pub(crate) mod worddiff;
@@ -0,0 +1,222 @@
+use crate::parse::{self, ParseError};
+use anyhow::{anyhow, Context, Result};
+
+/// Parsed LS_COLORS entries (`key=codes:key=codes`).
+#[derive(Debug, Clone, Default)]
+pub struct LsColors {
+    source: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    key: String,
+    codes: String,
+    /// Byte offset of the codes in the source
+    offset: usize,
+}
+
+impl LsColors {
+    /// Parse the `LS_COLORS` environment variable.
+    pub fn from_env() -> Result<Self> {
+        let value = std::env::var("LS_COLORS").context("LS_COLORS is not set")?;
+        Self::parse(&value).map_err(|error| anyhow!("invalid LS_COLORS: {}", error.report(&value)))
+    }
+
+    /// Parse a `key=codes:key=codes` string.
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        for entry in value.split(':') {
+            let entry_offset = offset;
+            offset += entry.len() + 1;
+            if entry.is_empty() {
+                continue;
+            }
+            let Some((key, codes)) = entry.split_once('=') else {
+                return Err(ParseError::token(
+                    entry_offset,
+                    entry,
+                    "expected key=codes, found",
+                ));
+            };
+            if key.is_empty() {
+                return Err(ParseError::token(entry_offset, entry, "empty key in entry"));
+            }
+            entries.push(Entry {
+                key: key.to_owned(),
+                codes: codes.to_owned(),
+                offset: entry_offset + key.len() + 1,
+            });
+        }
+        Ok(Self {
+            source: value.to_owned(),
+            entries,
+        })
+    }
+
+    /// Entry for an exact key (e.g. `di`, `ex`, `*.tar`).
+    ///
+    /// Later entries override earlier ones, as with GNU ls.
+    fn entry(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().rev().find(|entry| entry.key == key)
+    }
+
+    /// Glob entry that best matches a path.
+    ///
+    /// The most specific pattern (most literal characters, i.e. the longest suffix) wins.
+    fn entry_for_path(&self, path: &str) -> Option<&Entry> {
+        let mut best: Option<(usize, &Entry)> = None;
+        for entry in self.entries.iter().filter(|entry| is_glob(&entry.key)) {
+            if !glob_match(&entry.key, path) {
+                continue;
+            }
+            let specificity = entry.key.chars().filter(|c| !is_glob_char(*c)).count();
+            if best.is_none_or(|(s, _)| specificity >= s) {
+                best = Some((specificity, entry));
+            }
+        }
+        best.map(|(_, entry)| entry)
+    }
+
+    /// Resolve SGR parameters by path (glob entries), falling back to key.
+    pub fn resolve(&self, key: Option<&str>, path: Option<&str>) -> Result<String> {
+        if let Some(entry) = path.and_then(|p| self.entry_for_path(p)) {
+            return self.validate(entry);
+        }
+        match (key, path) {
+            (Some(key), _) => match self.entry(key) {
+                Some(entry) => self.validate(entry),
+                None => {
+                    let keys = self.entries.iter().map(|entry| entry.key.as_str());
+                    match parse::closest(key, keys) {
+                        Some(close) => Err(anyhow!(
+                            "LS_COLORS has no entry for '{key}' (did you mean '{close}'?)"
+                        )),
+                        None => Err(anyhow!("LS_COLORS has no entry for '{key}'")),
+                    }
+                }
+            },
+            (None, Some(path)) => Err(anyhow!("LS_COLORS has no entry matching '{path}'")),
+            (None, None) => Err(anyhow!("no LS_COLORS key or path given")),
+        }
+    }
+
+    fn validate(&self, entry: &Entry) -> Result<String> {
+        let codes = &entry.codes;
+        if codes.is_empty()
+            || !codes
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == ';' || c == ':')
+        {
+            let error = ParseError::token(entry.offset, codes, "not an SGR sequence:");
+            return Err(anyhow!("invalid LS_COLORS: {}", error.report(&self.source)));
+        }
+        Ok(codes.to_owned())
+    }
+}
+
+fn is_glob_char(c: char) -> bool {
+    c == '*' || c == '?'
+}
+
+fn is_glob(key: &str) -> bool {
+    key.chars().any(is_glob_char)
+}
+
+/// Match a path against a glob pattern supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LS_COLORS: &str = "di=01;34:ex=01;32:*.gz=01;31:*.tar.gz=01;35:*.g?=33:di=34";
+
+    #[test]
+    fn later_keys_override_earlier_ones() {
+        let ls_colors = LsColors::parse(LS_COLORS).unwrap();
+        let get = |key| ls_colors.entry(key).map(|entry| entry.codes.as_str());
+        assert_eq!(get("di"), Some("34"));
+        assert_eq!(get("ex"), Some("01;32"));
+        assert_eq!(get("ln"), None);
+    }
+
+    #[test]
+    fn longest_suffix_wins() {
+        let ls_colors = LsColors::parse(LS_COLORS).unwrap();
+        let get_for_path = |path| {
+            ls_colors
+                .entry_for_path(path)
+                .map(|entry| entry.codes.as_str())
+        };
+        assert_eq!(get_for_path("backup.tar.gz"), Some("01;35"));
+        assert_eq!(get_for_path("notes.gz"), Some("01;31"));
+        assert_eq!(get_for_path("notes.go"), Some("33"));
+        assert_eq!(get_for_path("notes.txt"), None);
+    }
+
+    #[test]
+    fn resolve_prefers_the_path_and_falls_back_to_the_key() {
+        let ls_colors = LsColors::parse(LS_COLORS).unwrap();
+        let resolve = |key, path| ls_colors.resolve(key, path).map_err(|e| e.to_string());
+        assert_eq!(
+            resolve(Some("ex"), Some("a.tar.gz")),
+            Ok("01;35".to_owned())
+        );
+        assert_eq!(resolve(Some("ex"), Some("a.txt")), Ok("01;32".to_owned()));
+        assert_eq!(
+            resolve(Some("ln"), None),
+            Err("LS_COLORS has no entry for 'ln'".to_owned())
+        );
+        assert_eq!(
+            resolve(None, Some("a.txt")),
+            Err("LS_COLORS has no entry matching 'a.txt'".to_owned())
+        );
+    }
+
+    #[test]
+    fn invalid_entries_and_codes_are_errors() {
+        assert!(LsColors::parse("di").is_err());
+        assert!(LsColors::parse("=01").is_err());
+        assert!(LsColors::parse("::").unwrap().entry("di").is_none());
+        let ls_colors = LsColors::parse("di=bold").unwrap();
+        assert!(ls_colors.resolve(Some("di"), None).is_err());
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.tar.*", "a.tar.xz"));
+        assert!(glob_match("?.c", "a.c"));
+        assert!(!glob_match("?.c", "ab.c"));
+        assert!(glob_match("*a*b", "xaab"));
+        assert!(!glob_match("*a*b", "xaba"));
+    }
+}
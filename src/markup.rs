@@ -0,0 +1,172 @@
+use crate::parse::ParseError;
+
+const RESET: &str = "\x1b[m";
+
+/// Markup render options.
+pub struct Markup<R, V> {
+    /// Resolve a tag (without braces) to its escape sequence, with error offsets within the tag
+    pub resolve: R,
+    /// Look up `${VAR}` interpolations, or leave `$` literal if None
+    pub variables: Option<V>,
+    /// Escape sequence re-applied after popping a tag
+    pub base: String,
+    /// Validate tags but omit escape sequences
+    pub plain: bool,
+}
+
+impl<R, V> Markup<R, V>
+where
+    R: Fn(&str) -> Result<String, ParseError>,
+    V: Fn(&str) -> Option<String>,
+{
+    /// Render brace tag markup: `{tag}` pushes a style, `{/}` pops the most recent one, and
+    /// `{{`/`}}` are literal braces. With variables, `${VAR}` interpolates and `$$` is a literal `$`.
+    ///
+    /// Interpolated values are inserted literally and never parsed as markup.
+    pub fn render(&self, text: &str) -> Result<String, ParseError> {
+        let mut result = String::with_capacity(text.len());
+        let mut stack: Vec<String> = Vec::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((offset, ch)) = chars.next() {
+            match ch {
+                '{' if chars.next_if(|(_, c)| *c == '{').is_some() => result.push('{'),
+                '}' if chars.next_if(|(_, c)| *c == '}').is_some() => result.push('}'),
+                '}' => {
+                    return Err(ParseError::new(
+                        offset,
+                        1,
+                        "unmatched '}' (use '}}' for a literal brace)",
+                    ))
+                }
+                '{' => {
+                    let tag = take_until(text, offset + 1, '}').ok_or_else(|| {
+                        ParseError::new(offset, 1, "unclosed tag (use '{{' for a literal brace)")
+                    })?;
+                    while chars
+                        .next_if(|(i, _)| *i <= offset + tag.len() + 1)
+                        .is_some()
+                    {}
+                    let trimmed = tag.trim();
+                    if trimmed == "/" {
+                        if stack.pop().is_none() {
+                            let len = tag.len() + 2;
+                            return Err(ParseError::new(offset, len, "'{/}' without an open tag"));
+                        }
+                        if !self.plain {
+                            result.push_str(RESET);
+                            result.push_str(&self.base);
+                            stack.iter().for_each(|codes| result.push_str(codes));
+                        }
+                    } else {
+                        let tag_offset = offset + 1 + tag.len() - tag.trim_start().len();
+                        let codes = (self.resolve)(trimmed).map_err(|e| e.shifted(tag_offset))?;
+                        if !self.plain {
+                            result.push_str(&codes);
+                        }
+                        stack.push(codes);
+                    }
+                }
+                '$' => match (&self.variables, chars.peek()) {
+                    (Some(_), Some((_, '$'))) => {
+                        chars.next();
+                        result.push('$');
+                    }
+                    (Some(variables), Some((_, '{'))) => {
+                        let name = take_until(text, offset + 2, '}')
+                            .ok_or_else(|| ParseError::new(offset, 2, "unclosed '${'"))?;
+                        while chars
+                            .next_if(|(i, _)| *i <= offset + name.len() + 2)
+                            .is_some()
+                        {}
+                        let value = variables(name).ok_or_else(|| {
+                            ParseError::token(offset + 2, name, "undefined variable")
+                        })?;
+                        result.push_str(&value);
+                    }
+                    _ => result.push('$'),
+                },
+                ch => result.push(ch),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Text from start up to (not including) the next terminator on the same line.
+fn take_until(text: &str, start: usize, terminator: char) -> Option<&str> {
+    let rest = &text[start..];
+    let end = rest.find([terminator, '\n'])?;
+    (rest[end..].starts_with(terminator)).then(|| &rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(tag: &str) -> Result<String, ParseError> {
+        match tag {
+            "red" => Ok("<red>".to_owned()),
+            "bold" => Ok("<bold>".to_owned()),
+            _ => Err(ParseError::token(0, tag, "unknown tag")),
+        }
+    }
+
+    fn render(text: &str) -> Result<String, ParseError> {
+        let markup = Markup {
+            resolve,
+            variables: Some(|name: &str| (name == "USER").then(|| "{red}me".to_owned())),
+            base: "<base>".to_owned(),
+            plain: false,
+        };
+        markup.render(text)
+    }
+
+    #[test]
+    fn tags_push_and_pop_styles() {
+        assert_eq!(
+            render("a{red}b{bold}c{/}d{/}e").unwrap(),
+            "a<red>b<bold>c\x1b[m<base><red>d\x1b[m<base>e"
+        );
+        assert_eq!(render("{ red }x{ / }").unwrap(), "<red>x\x1b[m<base>");
+        assert_eq!(render("{{red}} }}").unwrap(), "{red} }");
+    }
+
+    #[test]
+    fn variables_are_interpolated_literally() {
+        assert_eq!(render("hi ${USER}, $$5 $x").unwrap(), "hi {red}me, $5 $x");
+        let without = Markup {
+            resolve,
+            variables: None::<fn(&str) -> Option<String>>,
+            base: String::new(),
+            plain: false,
+        };
+        assert_eq!(without.render("$5 $$ {{").unwrap(), "$5 $$ {");
+    }
+
+    #[test]
+    fn plain_validates_without_sequences() {
+        let plain = Markup {
+            resolve,
+            variables: None::<fn(&str) -> Option<String>>,
+            base: "<base>".to_owned(),
+            plain: true,
+        };
+        assert_eq!(plain.render("{red}a{/}b").unwrap(), "ab");
+        assert!(plain.render("{blue}a").is_err());
+    }
+
+    #[test]
+    fn errors_point_at_the_markup() {
+        let error = |text| render(text).unwrap_err();
+        assert_eq!(error("ab}").offset, 2);
+        assert_eq!(error("a{red").offset, 1);
+        assert_eq!(error("a{red\n}").offset, 1);
+        assert_eq!(error("{/}").message, "'{/}' without an open tag");
+        assert_eq!(
+            error("x{ blue}"),
+            ParseError::token(3, "blue", "unknown tag")
+        );
+        assert_eq!(error("${HOME}").message, "undefined variable 'HOME'");
+        assert_eq!(error("${USER").offset, 0);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{color::Rgb, width};
+use anyhow::{anyhow, bail, Result};
+use std::fmt::Write;
+
+const PARTIAL_BLOCKS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+const FULL_BLOCK: char = '█';
+const UNFILLED: Rgb = Rgb(48, 48, 48);
+const DARK_TEXT: Rgb = Rgb(0, 0, 0);
+const BRIGHT_TEXT: Rgb = Rgb(255, 255, 255);
+
+fn contrasting(color: Rgb) -> Rgb {
+    if color.luminosity() > 0.5 {
+        DARK_TEXT
+    } else {
+        BRIGHT_TEXT
+    }
+}
+
+/// Color at position t (0 to 1) in a gradient of evenly spaced stops.
+fn gradient_at(stops: &[Rgb], t: f64) -> Rgb {
+    if stops.len() == 1 {
+        return stops[0];
+    }
+    let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let index = (position.floor() as usize).min(stops.len() - 2);
+    stops[index].lerp(stops[index + 1], position - index as f64)
+}
+
+/// Render a horizontal bar for value out of max, with the value overlaid in the center.
+///
+/// The bar is not followed by a reset.
+pub fn render(value: f64, max: f64, width: usize, gradient: &[Rgb], plain: bool) -> Result<String> {
+    if max.is_nan() || max <= 0.0 {
+        bail!("meter max must be positive (got {max})");
+    }
+    if width == 0 {
+        bail!("meter width must be at least 1");
+    }
+    if gradient.is_empty() {
+        return Err(anyhow!("meter gradient needs at least one color"));
+    }
+    let fill = (value / max).clamp(0.0, 1.0) * width as f64;
+    let mut full = fill.floor() as usize;
+    let mut eighths = ((fill - full as f64) * 8.0).round() as usize;
+    if eighths == 8 {
+        full += 1;
+        eighths = 0;
+    }
+
+    if plain {
+        let mut result: String = std::iter::repeat_n(FULL_BLOCK, full).collect();
+        if eighths > 0 {
+            result.push(PARTIAL_BLOCKS[eighths]);
+        }
+        let remaining = width - width::width(&result);
+        result.extend(std::iter::repeat_n(' ', remaining));
+        result.push_str(&format!(" {value}"));
+        return Ok(result);
+    }
+
+    let label_text = value.to_string();
+    let label_width = width::width(&label_text);
+    let label: Vec<char> = label_text.chars().collect();
+    let label_start = (label_width <= width).then(|| (width - label_width) / 2);
+    let mut result = String::new();
+    for i in 0..width {
+        let t = if width > 1 {
+            i as f64 / (width - 1) as f64
+        } else {
+            0.0
+        };
+        let color = gradient_at(gradient, t);
+        let (mut background, mut foreground, mut ch) = if i < full {
+            (color, contrasting(color), ' ')
+        } else if i == full && eighths > 0 {
+            (UNFILLED, color, PARTIAL_BLOCKS[eighths])
+        } else {
+            (UNFILLED, contrasting(UNFILLED), ' ')
+        };
+        if let Some(start) = label_start.filter(|start| (*start..start + label_width).contains(&i))
+        {
+            // The label replaces a partial block, so round it to filled or unfilled
+            if i == full && eighths >= 4 {
+                background = color;
+            }
+            ch = label[i - start];
+            foreground = contrasting(background);
+        }
+        write!(result, "{}{}{ch}", background.bg_seq(), foreground.fg_seq())
+            .expect("writing to a String cannot fail");
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREEN_RED: [Rgb; 2] = [Rgb(0, 255, 0), Rgb(255, 0, 0)];
+
+    #[test]
+    fn plain_bars_fill_with_partial_blocks() {
+        let plain = |value, max, width| render(value, max, width, &GREEN_RED, true).unwrap();
+        assert_eq!(plain(5.0, 10.0, 4), "██   5");
+        assert_eq!(plain(3.0, 8.0, 2), "▊  3");
+        assert_eq!(plain(0.0, 8.0, 2), "   0");
+        assert_eq!(plain(15.0, 10.0, 2), "██ 15");
+        // Nearly full cells round up to a full block
+        assert_eq!(plain(0.99, 1.0, 2), "██ 0.99");
+    }
+
+    #[test]
+    fn value_is_overlaid_in_the_center() {
+        let bar = render(5.0, 10.0, 2, &GREEN_RED, false).unwrap();
+        assert_eq!(
+            bar,
+            "\x1b[48;2;0;255;0m\x1b[38;2;0;0;0m5\x1b[48;2;48;48;48m\x1b[38;2;255;255;255m "
+        );
+        let bar = render(1.0, 4.0, 4, &GREEN_RED, false).unwrap();
+        assert_eq!(
+            bar,
+            concat!(
+                "\x1b[48;2;0;255;0m\x1b[38;2;0;0;0m ",
+                "\x1b[48;2;48;48;48m\x1b[38;2;255;255;255m1",
+                "\x1b[48;2;48;48;48m\x1b[38;2;255;255;255m ",
+                "\x1b[48;2;48;48;48m\x1b[38;2;255;255;255m ",
+            )
+        );
+    }
+
+    #[test]
+    fn gradient_stops_are_evenly_spaced() {
+        let stops = [Rgb(0, 0, 0), Rgb(100, 200, 0), Rgb(100, 0, 200)];
+        assert_eq!(gradient_at(&stops, 0.0), Rgb(0, 0, 0));
+        assert_eq!(gradient_at(&stops, 0.25), Rgb(50, 100, 0));
+        assert_eq!(gradient_at(&stops, 0.5), Rgb(100, 200, 0));
+        assert_eq!(gradient_at(&stops, 1.0), Rgb(100, 0, 200));
+        assert_eq!(gradient_at(&stops, 2.0), Rgb(100, 0, 200));
+        assert_eq!(gradient_at(&stops[..1], 0.7), Rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn invalid_meters_are_errors() {
+        assert!(render(1.0, 0.0, 4, &GREEN_RED, false).is_err());
+        assert!(render(1.0, f64::NAN, 4, &GREEN_RED, false).is_err());
+        assert!(render(1.0, 2.0, 0, &GREEN_RED, false).is_err());
+        assert!(render(1.0, 2.0, 4, &[], false).is_err());
+        assert_eq!(Rgb::from_hex("#ff8000").unwrap(), Rgb(255, 128, 0));
+        assert!(Rgb::from_hex("#ff800").is_err());
+    }
+}
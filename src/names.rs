@@ -0,0 +1,183 @@
+use crate::{color::Rgb, parse::ParseError};
+
+/// The CSS named colors, in alphabetical order.
+pub const CSS_COLORS: [(&str, Rgb); 148] = [
+    ("aliceblue", Rgb(0xF0, 0xF8, 0xFF)),
+    ("antiquewhite", Rgb(0xFA, 0xEB, 0xD7)),
+    ("aqua", Rgb(0x00, 0xFF, 0xFF)),
+    ("aquamarine", Rgb(0x7F, 0xFF, 0xD4)),
+    ("azure", Rgb(0xF0, 0xFF, 0xFF)),
+    ("beige", Rgb(0xF5, 0xF5, 0xDC)),
+    ("bisque", Rgb(0xFF, 0xE4, 0xC4)),
+    ("black", Rgb(0x00, 0x00, 0x00)),
+    ("blanchedalmond", Rgb(0xFF, 0xEB, 0xCD)),
+    ("blue", Rgb(0x00, 0x00, 0xFF)),
+    ("blueviolet", Rgb(0x8A, 0x2B, 0xE2)),
+    ("brown", Rgb(0xA5, 0x2A, 0x2A)),
+    ("burlywood", Rgb(0xDE, 0xB8, 0x87)),
+    ("cadetblue", Rgb(0x5F, 0x9E, 0xA0)),
+    ("chartreuse", Rgb(0x7F, 0xFF, 0x00)),
+    ("chocolate", Rgb(0xD2, 0x69, 0x1E)),
+    ("coral", Rgb(0xFF, 0x7F, 0x50)),
+    ("cornflowerblue", Rgb(0x64, 0x95, 0xED)),
+    ("cornsilk", Rgb(0xFF, 0xF8, 0xDC)),
+    ("crimson", Rgb(0xDC, 0x14, 0x3C)),
+    ("cyan", Rgb(0x00, 0xFF, 0xFF)),
+    ("darkblue", Rgb(0x00, 0x00, 0x8B)),
+    ("darkcyan", Rgb(0x00, 0x8B, 0x8B)),
+    ("darkgoldenrod", Rgb(0xB8, 0x86, 0x0B)),
+    ("darkgray", Rgb(0xA9, 0xA9, 0xA9)),
+    ("darkgreen", Rgb(0x00, 0x64, 0x00)),
+    ("darkgrey", Rgb(0xA9, 0xA9, 0xA9)),
+    ("darkkhaki", Rgb(0xBD, 0xB7, 0x6B)),
+    ("darkmagenta", Rgb(0x8B, 0x00, 0x8B)),
+    ("darkolivegreen", Rgb(0x55, 0x6B, 0x2F)),
+    ("darkorange", Rgb(0xFF, 0x8C, 0x00)),
+    ("darkorchid", Rgb(0x99, 0x32, 0xCC)),
+    ("darkred", Rgb(0x8B, 0x00, 0x00)),
+    ("darksalmon", Rgb(0xE9, 0x96, 0x7A)),
+    ("darkseagreen", Rgb(0x8F, 0xBC, 0x8F)),
+    ("darkslateblue", Rgb(0x48, 0x3D, 0x8B)),
+    ("darkslategray", Rgb(0x2F, 0x4F, 0x4F)),
+    ("darkslategrey", Rgb(0x2F, 0x4F, 0x4F)),
+    ("darkturquoise", Rgb(0x00, 0xCE, 0xD1)),
+    ("darkviolet", Rgb(0x94, 0x00, 0xD3)),
+    ("deeppink", Rgb(0xFF, 0x14, 0x93)),
+    ("deepskyblue", Rgb(0x00, 0xBF, 0xFF)),
+    ("dimgray", Rgb(0x69, 0x69, 0x69)),
+    ("dimgrey", Rgb(0x69, 0x69, 0x69)),
+    ("dodgerblue", Rgb(0x1E, 0x90, 0xFF)),
+    ("firebrick", Rgb(0xB2, 0x22, 0x22)),
+    ("floralwhite", Rgb(0xFF, 0xFA, 0xF0)),
+    ("forestgreen", Rgb(0x22, 0x8B, 0x22)),
+    ("fuchsia", Rgb(0xFF, 0x00, 0xFF)),
+    ("gainsboro", Rgb(0xDC, 0xDC, 0xDC)),
+    ("ghostwhite", Rgb(0xF8, 0xF8, 0xFF)),
+    ("gold", Rgb(0xFF, 0xD7, 0x00)),
+    ("goldenrod", Rgb(0xDA, 0xA5, 0x20)),
+    ("gray", Rgb(0x80, 0x80, 0x80)),
+    ("green", Rgb(0x00, 0x80, 0x00)),
+    ("greenyellow", Rgb(0xAD, 0xFF, 0x2F)),
+    ("grey", Rgb(0x80, 0x80, 0x80)),
+    ("honeydew", Rgb(0xF0, 0xFF, 0xF0)),
+    ("hotpink", Rgb(0xFF, 0x69, 0xB4)),
+    ("indianred", Rgb(0xCD, 0x5C, 0x5C)),
+    ("indigo", Rgb(0x4B, 0x00, 0x82)),
+    ("ivory", Rgb(0xFF, 0xFF, 0xF0)),
+    ("khaki", Rgb(0xF0, 0xE6, 0x8C)),
+    ("lavender", Rgb(0xE6, 0xE6, 0xFA)),
+    ("lavenderblush", Rgb(0xFF, 0xF0, 0xF5)),
+    ("lawngreen", Rgb(0x7C, 0xFC, 0x00)),
+    ("lemonchiffon", Rgb(0xFF, 0xFA, 0xCD)),
+    ("lightblue", Rgb(0xAD, 0xD8, 0xE6)),
+    ("lightcoral", Rgb(0xF0, 0x80, 0x80)),
+    ("lightcyan", Rgb(0xE0, 0xFF, 0xFF)),
+    ("lightgoldenrodyellow", Rgb(0xFA, 0xFA, 0xD2)),
+    ("lightgray", Rgb(0xD3, 0xD3, 0xD3)),
+    ("lightgreen", Rgb(0x90, 0xEE, 0x90)),
+    ("lightgrey", Rgb(0xD3, 0xD3, 0xD3)),
+    ("lightpink", Rgb(0xFF, 0xB6, 0xC1)),
+    ("lightsalmon", Rgb(0xFF, 0xA0, 0x7A)),
+    ("lightseagreen", Rgb(0x20, 0xB2, 0xAA)),
+    ("lightskyblue", Rgb(0x87, 0xCE, 0xFA)),
+    ("lightslategray", Rgb(0x77, 0x88, 0x99)),
+    ("lightslategrey", Rgb(0x77, 0x88, 0x99)),
+    ("lightsteelblue", Rgb(0xB0, 0xC4, 0xDE)),
+    ("lightyellow", Rgb(0xFF, 0xFF, 0xE0)),
+    ("lime", Rgb(0x00, 0xFF, 0x00)),
+    ("limegreen", Rgb(0x32, 0xCD, 0x32)),
+    ("linen", Rgb(0xFA, 0xF0, 0xE6)),
+    ("magenta", Rgb(0xFF, 0x00, 0xFF)),
+    ("maroon", Rgb(0x80, 0x00, 0x00)),
+    ("mediumaquamarine", Rgb(0x66, 0xCD, 0xAA)),
+    ("mediumblue", Rgb(0x00, 0x00, 0xCD)),
+    ("mediumorchid", Rgb(0xBA, 0x55, 0xD3)),
+    ("mediumpurple", Rgb(0x93, 0x70, 0xDB)),
+    ("mediumseagreen", Rgb(0x3C, 0xB3, 0x71)),
+    ("mediumslateblue", Rgb(0x7B, 0x68, 0xEE)),
+    ("mediumspringgreen", Rgb(0x00, 0xFA, 0x9A)),
+    ("mediumturquoise", Rgb(0x48, 0xD1, 0xCC)),
+    ("mediumvioletred", Rgb(0xC7, 0x15, 0x85)),
+    ("midnightblue", Rgb(0x19, 0x19, 0x70)),
+    ("mintcream", Rgb(0xF5, 0xFF, 0xFA)),
+    ("mistyrose", Rgb(0xFF, 0xE4, 0xE1)),
+    ("moccasin", Rgb(0xFF, 0xE4, 0xB5)),
+    ("navajowhite", Rgb(0xFF, 0xDE, 0xAD)),
+    ("navy", Rgb(0x00, 0x00, 0x80)),
+    ("oldlace", Rgb(0xFD, 0xF5, 0xE6)),
+    ("olive", Rgb(0x80, 0x80, 0x00)),
+    ("olivedrab", Rgb(0x6B, 0x8E, 0x23)),
+    ("orange", Rgb(0xFF, 0xA5, 0x00)),
+    ("orangered", Rgb(0xFF, 0x45, 0x00)),
+    ("orchid", Rgb(0xDA, 0x70, 0xD6)),
+    ("palegoldenrod", Rgb(0xEE, 0xE8, 0xAA)),
+    ("palegreen", Rgb(0x98, 0xFB, 0x98)),
+    ("paleturquoise", Rgb(0xAF, 0xEE, 0xEE)),
+    ("palevioletred", Rgb(0xDB, 0x70, 0x93)),
+    ("papayawhip", Rgb(0xFF, 0xEF, 0xD5)),
+    ("peachpuff", Rgb(0xFF, 0xDA, 0xB9)),
+    ("peru", Rgb(0xCD, 0x85, 0x3F)),
+    ("pink", Rgb(0xFF, 0xC0, 0xCB)),
+    ("plum", Rgb(0xDD, 0xA0, 0xDD)),
+    ("powderblue", Rgb(0xB0, 0xE0, 0xE6)),
+    ("purple", Rgb(0x80, 0x00, 0x80)),
+    ("rebeccapurple", Rgb(0x66, 0x33, 0x99)),
+    ("red", Rgb(0xFF, 0x00, 0x00)),
+    ("rosybrown", Rgb(0xBC, 0x8F, 0x8F)),
+    ("royalblue", Rgb(0x41, 0x69, 0xE1)),
+    ("saddlebrown", Rgb(0x8B, 0x45, 0x13)),
+    ("salmon", Rgb(0xFA, 0x80, 0x72)),
+    ("sandybrown", Rgb(0xF4, 0xA4, 0x60)),
+    ("seagreen", Rgb(0x2E, 0x8B, 0x57)),
+    ("seashell", Rgb(0xFF, 0xF5, 0xEE)),
+    ("sienna", Rgb(0xA0, 0x52, 0x2D)),
+    ("silver", Rgb(0xC0, 0xC0, 0xC0)),
+    ("skyblue", Rgb(0x87, 0xCE, 0xEB)),
+    ("slateblue", Rgb(0x6A, 0x5A, 0xCD)),
+    ("slategray", Rgb(0x70, 0x80, 0x90)),
+    ("slategrey", Rgb(0x70, 0x80, 0x90)),
+    ("snow", Rgb(0xFF, 0xFA, 0xFA)),
+    ("springgreen", Rgb(0x00, 0xFF, 0x7F)),
+    ("steelblue", Rgb(0x46, 0x82, 0xB4)),
+    ("tan", Rgb(0xD2, 0xB4, 0x8C)),
+    ("teal", Rgb(0x00, 0x80, 0x80)),
+    ("thistle", Rgb(0xD8, 0xBF, 0xD8)),
+    ("tomato", Rgb(0xFF, 0x63, 0x47)),
+    ("turquoise", Rgb(0x40, 0xE0, 0xD0)),
+    ("violet", Rgb(0xEE, 0x82, 0xEE)),
+    ("wheat", Rgb(0xF5, 0xDE, 0xB3)),
+    ("white", Rgb(0xFF, 0xFF, 0xFF)),
+    ("whitesmoke", Rgb(0xF5, 0xF5, 0xF5)),
+    ("yellow", Rgb(0xFF, 0xFF, 0x00)),
+    ("yellowgreen", Rgb(0x9A, 0xCD, 0x32)),
+];
+
+/// The CSS named color with a name, ignoring case.
+pub fn named(name: &str) -> Option<Rgb> {
+    CSS_COLORS
+        .iter()
+        .find(|(css, _)| css.eq_ignore_ascii_case(name))
+        .map(|(_, rgb)| *rgb)
+}
+
+/// A CSS named color, or a color as [`Rgb`] parses it.
+pub fn parse(text: &str) -> Result<Rgb, ParseError> {
+    match named(text.trim()) {
+        Some(rgb) => Ok(rgb),
+        None => Ok(text.parse()?),
+    }
+}
+
+/// Name of the closest CSS named color by RGB distance.
+///
+/// Where several names share a color (e.g. gray and grey), the first alphabetically is used.
+pub fn nearest_named(color: Rgb) -> &'static str {
+    let distance = |Rgb(r, g, b): Rgb| {
+        let channel = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+        channel(r, color.0) + channel(g, color.1) + channel(b, color.2)
+    };
+    CSS_COLORS
+        .iter()
+        .min_by_key(|(_, named)| distance(*named))
+        .map_or("black", |(name, _)| name)
+}
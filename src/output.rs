@@ -0,0 +1,129 @@
+use std::io::{self, BufWriter, IsTerminal, Write};
+
+/// Buffer size for piped output, large enough for a wide table row.
+const CAPACITY: usize = 64 * 1024;
+
+/// Writer for whole records (table rows, formatted outputs) with a defined flushing contract.
+///
+/// - A record is never split across writes to the underlying stream: the buffer is flushed
+///   before a record that does not fit, and a record larger than the buffer is written alone.
+/// - Output to a terminal is flushed after every record, so it is at least line buffered.
+/// - Output to a pipe is flushed when full, on [`Self::finish`], and after every record with
+///   `flush_every_record` (for `tail -f` style consumers).
+pub struct RecordWriter<W: Write> {
+    inner: BufWriter<W>,
+    flush_every_record: bool,
+}
+
+impl RecordWriter<io::StdoutLock<'static>> {
+    /// Writer for stdout, flushing every record if stdout is a terminal or if asked to.
+    pub fn stdout(flush_every_record: bool) -> Self {
+        let stdout = io::stdout();
+        let flush_every_record = flush_every_record || stdout.is_terminal();
+        Self::new(stdout.lock(), flush_every_record)
+    }
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(inner: W, flush_every_record: bool) -> Self {
+        Self::with_capacity(CAPACITY, inner, flush_every_record)
+    }
+
+    /// Writer buffering up to capacity bytes of whole records.
+    pub fn with_capacity(capacity: usize, inner: W, flush_every_record: bool) -> Self {
+        Self {
+            inner: BufWriter::with_capacity(capacity, inner),
+            flush_every_record,
+        }
+    }
+
+    /// Write one complete record, including its line ending.
+    pub fn record(&mut self, record: &str) -> io::Result<()> {
+        self.record_bytes(record.as_bytes())
+    }
+
+    /// Write one complete record that may not be valid UTF-8.
+    pub fn record_bytes(&mut self, record: &[u8]) -> io::Result<()> {
+        if record.len() > self.inner.capacity() - self.inner.buffer().len() {
+            self.inner.flush()?;
+        }
+        self.inner.write_all(record)?;
+        if self.flush_every_record {
+            self.inner.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flush at the end of the stream.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Sink remembering each write it is given.
+    #[derive(Clone, Default)]
+    struct Writes(Rc<RefCell<Vec<Vec<u8>>>>);
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn records() -> Vec<String> {
+        (0..40)
+            .map(|i| format!("{}\n", "x".repeat(i % 23)))
+            .collect()
+    }
+
+    fn write_all(capacity: usize, flush_every_record: bool) -> Vec<Vec<u8>> {
+        let writes = Writes::default();
+        let mut writer = RecordWriter::with_capacity(capacity, writes.clone(), flush_every_record);
+        for record in records() {
+            writer.record(&record).unwrap();
+        }
+        writer.finish().unwrap();
+        writes.0.take()
+    }
+
+    #[test]
+    fn records_are_never_split_across_writes() {
+        for capacity in [1, 2, 7, 16, 64, 1024] {
+            let writes = write_all(capacity, false);
+            let mut records = records().into_iter();
+            for write in &writes {
+                // Each write is a run of whole records
+                let mut rest = &write[..];
+                while !rest.is_empty() {
+                    let record = records.next().expect("more written than recorded");
+                    assert!(rest.starts_with(record.as_bytes()), "capacity {capacity}");
+                    rest = &rest[record.len()..];
+                }
+            }
+            assert!(records.next().is_none(), "capacity {capacity}");
+        }
+    }
+
+    #[test]
+    fn small_records_are_batched() {
+        assert_eq!(write_all(1024, false).len(), 1);
+        assert!(write_all(64, false).len() < records().len());
+    }
+
+    #[test]
+    fn flushing_every_record_writes_each_alone() {
+        let writes = write_all(1024, true);
+        let records: Vec<Vec<u8>> = records().into_iter().map(String::into_bytes).collect();
+        assert_eq!(writes, records);
+    }
+}
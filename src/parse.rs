@@ -0,0 +1,289 @@
+/// Error for the small string grammars (markup, LS_COLORS, color specs).
+///
+/// Offsets are in bytes of the source text being parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub len: usize,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(offset: usize, len: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            len,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    /// Error covering the whole of a token, with the token quoted in the message.
+    pub fn token(offset: usize, token: &str, message: &str) -> Self {
+        Self::new(offset, token.len(), format!("{message} '{token}'"))
+    }
+
+    /// Suggest the closest candidate to the offending token, if one is close enough.
+    pub fn suggest<'a>(
+        mut self,
+        token: &str,
+        candidates: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.suggestion = closest(token, candidates).map(str::to_owned);
+        self
+    }
+
+    /// Shift the error by the offset of the parsed text within a larger source.
+    pub fn shifted(mut self, offset: usize) -> Self {
+        self.offset += offset;
+        self
+    }
+
+    /// 1-based line and column (in characters) of the error in the source text.
+    pub fn line_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..floor_char_boundary(source, self.offset)];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        (line, column)
+    }
+
+    /// Message with the offending line, a caret under the token, and any suggestion.
+    pub fn report(&self, source: &str) -> String {
+        let (line, column) = self.line_column(source);
+        let offset = floor_char_boundary(source, self.offset);
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let line_text = &source[line_start..line_end];
+        let token_end = floor_char_boundary(source, (offset + self.len).min(line_end));
+        let carets = source[offset..token_end].chars().count().max(1);
+        let gutter = line.to_string().len();
+        let mut report = format!(
+            "{}\n{:>gutter$} | {line_text}\n{:>gutter$} | {}{}",
+            self.message,
+            line,
+            "",
+            " ".repeat(column - 1),
+            "^".repeat(carets),
+        );
+        if let Some(suggestion) = &self.suggestion {
+            report.push_str(&format!(
+                "\n{:>gutter$} = help: did you mean '{suggestion}'?",
+                ""
+            ));
+        }
+        report
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at byte {})", self.message, self.offset)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean '{suggestion}'?")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Candidate with the smallest edit distance to the token, within a third of its length.
+pub fn closest<'a>(token: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (token.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(token, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Seeded generators of small inputs for the property tests of the grammars.
+#[cfg(test)]
+pub(crate) mod fuzz {
+    use super::ParseError;
+
+    /// Iterations of each property test.
+    pub const CASES: usize = 5000;
+
+    /// Xorshift generator, so failures reproduce without a fuzzer dependency.
+    pub struct Rng(u64);
+
+    impl Rng {
+        pub fn new(seed: u64) -> Self {
+            Self(seed.max(1))
+        }
+
+        pub fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Uniform below n (which must be positive).
+        pub fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        /// Up to max_len pieces, each picked from the alphabet.
+        pub fn string(&mut self, alphabet: &[&str], max_len: usize) -> String {
+            let len = self.below(max_len + 1);
+            (0..len)
+                .map(|_| alphabet[self.below(alphabet.len())])
+                .collect()
+        }
+
+        /// Up to max_len arbitrary bytes, biased towards ESC and sequence introducers.
+        pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+            const BIASED: [u8; 8] = [0x1b, b'[', b']', b'm', b';', b'\\', 0x07, b'P'];
+            let len = self.below(max_len + 1);
+            (0..len)
+                .map(|_| match self.below(2) {
+                    0 => BIASED[self.below(BIASED.len())],
+                    _ => self.next() as u8,
+                })
+                .collect()
+        }
+    }
+
+    /// Assert that the error points into the source and that its report shows the position.
+    pub fn check_error(source: &str, error: &ParseError) {
+        assert!(
+            error.offset <= source.len(),
+            "{source:?}: {error:?} is past the end"
+        );
+        let report = error.report(source);
+        assert!(report.starts_with(&error.message), "{source:?}: {report}");
+        assert!(report.contains('^'), "{source:?}: {report}");
+        assert!(error.to_string().contains("at byte"), "{source:?}: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzz::*, *};
+    use crate::{color::Rgb, format::ColorSpec, lscolors::LsColors, styles::UserStyles};
+
+    const COLOR_ALPHABET: [&str; 24] = [
+        "#", "rgb(", "hsv(", "hsl(", "oklch(", "grey:", ")", ",", " ", "%", ".", "-", "0", "5",
+        "9", "25", "255", "360", "f", "A", "red", "x", "é", "(",
+    ];
+
+    #[test]
+    fn report_marks_the_token() {
+        let source = "one\ntwo thre four";
+        let error = ParseError::token(8, "thre", "unknown word").suggest("thre", ["three"]);
+        assert_eq!(
+            error.report(source),
+            "unknown word 'thre'\n2 | two thre four\n  |     ^^^^\n  = help: did you mean 'three'?"
+        );
+        assert_eq!(error.line_column(source), (2, 5));
+    }
+
+    #[test]
+    fn report_clamps_offsets_inside_characters() {
+        let source = "aé";
+        let error = ParseError::new(2, 5, "bad");
+        assert_eq!(error.line_column(source), (1, 2));
+        assert!(error.report(source).ends_with("|  ^"));
+    }
+
+    #[test]
+    fn closest_within_a_third_of_the_length() {
+        assert_eq!(closest("grean", ["green", "red"]), Some("green"));
+        assert_eq!(closest("xyz", ["green", "red"]), None);
+    }
+
+    #[test]
+    fn color_specs_never_panic() {
+        let mut rng = Rng::new(0x5eed_c010);
+        for _ in 0..CASES {
+            let source = rng.string(&COLOR_ALPHABET, 8);
+            if let Err(error) = source.parse::<Rgb>() {
+                check_error(&source, &error.into());
+            }
+            if let Err(error) = source.parse::<ColorSpec>() {
+                check_error(&source, &error);
+            }
+        }
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let mut rng = Rng::new(0x4e8);
+        for _ in 0..CASES {
+            let value = rng.next();
+            let rgb = Rgb(value as u8, (value >> 8) as u8, (value >> 16) as u8);
+            assert_eq!(rgb.to_string().parse::<Rgb>(), Ok(rgb));
+        }
+    }
+
+    #[test]
+    fn ls_colors_never_panic() {
+        let alphabet = ["di", "*.rs", "=", ":", ";", "01", "38;5;12", "", "ex", "=="];
+        let mut rng = Rng::new(0x15);
+        for _ in 0..CASES {
+            let source = rng.string(&alphabet, 8);
+            if let Err(error) = LsColors::parse(&source) {
+                check_error(&source, &error);
+            }
+        }
+    }
+
+    #[test]
+    fn user_styles_never_panic() {
+        let alphabet = [
+            "[styles.a]",
+            "[styles.",
+            "]",
+            "\n",
+            "foreground",
+            "background",
+            "options",
+            " = ",
+            "\"red\"",
+            "\"#12\"",
+            "[\"bold\"",
+            ", ",
+            "\"x\"",
+            "#",
+            "=",
+            "\"",
+        ];
+        let mut rng = Rng::new(0x57);
+        for _ in 0..CASES {
+            let source = rng.string(&alphabet, 12);
+            if let Err(error) = UserStyles::parse(&source) {
+                check_error(&source, &error);
+            }
+        }
+    }
+}
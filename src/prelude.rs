@@ -0,0 +1,16 @@
+//! Commonly used items, for glob import.
+//!
+//! Everything exported here is part of the stable public API, each type under one name. [`Rgb`]
+//! is a 24-bit color that parses and displays as hex, and [`NamedColor`] one of the 16 colors of
+//! the terminal theme.
+
+pub use crate::color::{ColorParseError, Rgb};
+pub use crate::format::{
+    paint, Color as NamedColor, ColorSpec, FormattingOption as Attribute, Style, StyleBuilder,
+    RESET,
+};
+pub use crate::parse::ParseError;
+pub use crate::styled::{colors_disabled, set_colors_disabled, Styled};
+pub use crate::terminal::{
+    enable_vt_processing, newline, width as terminal_width, TerminalCapabilities,
+};
@@ -0,0 +1,215 @@
+use crate::format::{self, Annotations, ColorSpec, FormattingOption, Style, StyleBuilder, RESET};
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether [`Styled`] values display as plain text.
+static COLORS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Display every [`Styled`] value as plain text (or styled again), for output that is not a
+/// terminal or when `NO_COLOR` is set.
+pub fn set_colors_disabled(disabled: bool) {
+    COLORS_DISABLED.store(disabled, Ordering::Relaxed);
+}
+
+pub fn colors_disabled() -> bool {
+    COLORS_DISABLED.load(Ordering::Relaxed)
+}
+
+/// Text with colors and formatting options, composed of plain and nested styled segments.
+///
+/// Displays as printcolor prints it: every line is formatted and reset before its line ending,
+/// and the text is reset at the end. Nested segments are formatted with their own options on
+/// top of the enclosing ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Styled {
+    segments: Vec<Segment>,
+    options: StyleBuilder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Styled(Styled),
+}
+
+impl Styled {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            segments: vec![Segment::Text(text.into())],
+            options: StyleBuilder::default(),
+        }
+    }
+
+    /// Styled text with the colors and formatting options.
+    pub fn with_options(text: impl Into<String>, options: StyleBuilder) -> Self {
+        Self {
+            options,
+            ..Self::new(text)
+        }
+    }
+
+    /// Append a segment: plain text, or styled text formatted on top of this.
+    pub fn push(mut self, segment: impl Into<Styled>) -> Self {
+        let segment = segment.into();
+        if segment.options == StyleBuilder::default() {
+            self.segments.extend(segment.segments);
+        } else {
+            self.segments.push(Segment::Styled(segment));
+        }
+        self
+    }
+
+    pub fn fg(mut self, color: impl Into<ColorSpec>) -> Self {
+        self.options = self.options.foreground(color);
+        self
+    }
+
+    pub fn bg(mut self, color: impl Into<ColorSpec>) -> Self {
+        self.options = self.options.background(color);
+        self
+    }
+
+    pub fn option(mut self, option: FormattingOption) -> Self {
+        self.options = self.options.option(option);
+        self
+    }
+
+    /// Add the colors and formatting options of a premade style.
+    pub fn style(mut self, style: Style) -> Self {
+        self.options = self.options.style(style);
+        self
+    }
+
+    /// Leave the formatting on after the text.
+    pub fn no_reset(mut self) -> Self {
+        self.options = self.options.no_reset(true);
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.option(FormattingOption::Bold)
+    }
+
+    pub fn dim(self) -> Self {
+        self.option(FormattingOption::Dim)
+    }
+
+    pub fn italic(self) -> Self {
+        self.option(FormattingOption::Italic)
+    }
+
+    pub fn underline(self) -> Self {
+        self.option(FormattingOption::Underline)
+    }
+
+    pub fn double_underline(self) -> Self {
+        self.option(FormattingOption::DoubleUnderline)
+    }
+
+    pub fn overline(self) -> Self {
+        self.option(FormattingOption::Overline)
+    }
+
+    pub fn inverted(self) -> Self {
+        self.option(FormattingOption::Inverted)
+    }
+
+    pub fn strikethrough(self) -> Self {
+        self.option(FormattingOption::Strikethrough)
+    }
+
+    pub fn blink(self) -> Self {
+        self.option(FormattingOption::Blink)
+    }
+
+    pub fn hidden(self) -> Self {
+        self.option(FormattingOption::Hidden)
+    }
+
+    /// The text without any styling.
+    pub fn plain(&self) -> String {
+        let mut text = String::new();
+        self.push_plain(&mut text);
+        text
+    }
+
+    /// The text with its styling, even when colors are disabled.
+    pub fn render(&self) -> String {
+        let mut runs = Vec::new();
+        self.runs("", &mut runs);
+        let mut result = String::new();
+        let last = runs.len().saturating_sub(1);
+        for (i, (prop_codes, text)) in runs.into_iter().enumerate() {
+            let (lines, last_line) = match text.rsplit_once('\n') {
+                Some((lines, last_line)) => (Some(lines), last_line),
+                None => (None, text),
+            };
+            for line in lines.into_iter().flat_map(|lines| lines.split('\n')) {
+                format::push_line(&mut result, line, &prop_codes, &Annotations::default());
+            }
+            format::push_sgr(&mut result, &prop_codes);
+            result.push_str(last_line);
+            if i < last && !prop_codes.is_empty() {
+                result.push_str(RESET);
+            }
+        }
+        if !self.options.no_reset {
+            result.push_str(RESET);
+        }
+        result
+    }
+
+    fn push_plain(&self, buffer: &mut String) {
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => buffer.push_str(text),
+                Segment::Styled(styled) => styled.push_plain(buffer),
+            }
+        }
+    }
+
+    /// Flatten into runs of text with the SGR parameters they are formatted with.
+    fn runs<'a>(&'a self, outer: &str, runs: &mut Vec<(String, &'a str)>) {
+        let own = format::prop_codes(
+            self.options.foreground,
+            self.options.background,
+            &self.options.options,
+        );
+        let prop_codes = match (outer.is_empty(), own.is_empty()) {
+            (_, true) => outer.to_owned(),
+            (true, false) => own,
+            (false, false) => format!("{outer};{own}"),
+        };
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => runs.push((prop_codes.clone(), text)),
+                Segment::Styled(styled) => styled.runs(&prop_codes, runs),
+            }
+        }
+    }
+}
+
+/// Formats the styled text, or the plain text if colors are disabled.
+impl fmt::Display for Styled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if colors_disabled() {
+            f.write_str(&self.plain())
+        } else {
+            f.write_str(&self.render())
+        }
+    }
+}
+
+impl From<&str> for Styled {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for Styled {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    format::{ColorSpec, FormattingOption},
+    parse::ParseError,
+};
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+
+/// Keys of a style section.
+const KEYS: [&str; 3] = ["foreground", "background", "options"];
+
+/// Styles defined in a TOML-like config file.
+///
+/// Each style is a `[styles.NAME]` section with optional `foreground = "COLOR"`,
+/// `background = "COLOR"`, and `options = ["OPTION", ...]` entries. Lines starting with `#`
+/// are comments.
+#[derive(Debug, Clone, Default)]
+pub struct UserStyles {
+    styles: Vec<UserStyle>,
+}
+
+/// A style defined in the config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserStyle {
+    pub name: String,
+    pub foreground: Option<ColorSpec>,
+    pub background: Option<ColorSpec>,
+    pub options: Vec<FormattingOption>,
+}
+
+impl UserStyles {
+    /// Path of the config file: `$XDG_CONFIG_HOME/termcolor/styles.toml`, falling back to
+    /// `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("termcolor").join("styles.toml"))
+    }
+
+    /// Load the config file, which may be missing.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(error) => return Err(anyhow!("failed to read {}: {error}", path.display())),
+        };
+        Self::parse(&source).map_err(|error| {
+            let (line, column) = error.line_column(&source);
+            anyhow!(
+                "{}:{line}:{column}: {}",
+                path.display(),
+                error.report(&source)
+            )
+        })
+    }
+
+    /// Parse the config file contents.
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut styles: Vec<UserStyle> = Vec::new();
+        let mut offset = 0;
+        for line in source.split('\n') {
+            let line_offset = offset;
+            offset += line.len() + 1;
+            let start = line.len() - line.trim_start().len();
+            let line = line.trim();
+            let at = line_offset + start;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let Some(header) = header.strip_suffix(']') else {
+                    return Err(ParseError::token(at, line, "unterminated section header"));
+                };
+                let Some(name) = header.trim().strip_prefix("styles.") else {
+                    return Err(ParseError::token(at, line, "expected [styles.NAME], found"));
+                };
+                let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+                if name.is_empty() || !name.chars().all(valid) {
+                    return Err(ParseError::token(at, line, "invalid style name in"));
+                }
+                if styles.iter().any(|style| style.name == name) {
+                    return Err(ParseError::token(at, line, "duplicate style"));
+                }
+                styles.push(UserStyle {
+                    name: name.to_owned(),
+                    foreground: None,
+                    background: None,
+                    options: Vec::new(),
+                });
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(ParseError::token(at, line, "expected key = value, found"));
+            };
+            let Some(style) = styles.last_mut() else {
+                return Err(ParseError::token(
+                    at,
+                    line,
+                    "entry outside of a [styles.NAME] section",
+                ));
+            };
+            let value_offset = at + key.len() + 1 + (value.len() - value.trim_start().len());
+            let key = key.trim();
+            let value = value.trim();
+            match key {
+                "foreground" => style.foreground = Some(string(value, value_offset)?.parse_at()?),
+                "background" => style.background = Some(string(value, value_offset)?.parse_at()?),
+                "options" => {
+                    style.options = array(value, value_offset)?
+                        .into_iter()
+                        .map(|item| item.parse_at())
+                        .collect::<Result<_, _>>()?;
+                }
+                _ => {
+                    let error = ParseError::token(at, key, "unknown key");
+                    return Err(error.suggest(key, KEYS));
+                }
+            }
+        }
+        Ok(Self { styles })
+    }
+
+    /// The style with a name.
+    pub fn get(&self, name: &str) -> Option<&UserStyle> {
+        self.styles.iter().find(|style| style.name == name)
+    }
+
+    /// Names of all styles.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.styles.iter().map(|style| style.name.as_str())
+    }
+}
+
+/// A string value and its byte offset in the source.
+struct Spanned<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl Spanned<'_> {
+    fn parse_at<T: std::str::FromStr<Err = ParseError>>(&self) -> Result<T, ParseError> {
+        self.text
+            .parse()
+            .map_err(|error: ParseError| error.shifted(self.offset))
+    }
+}
+
+/// A double-quoted string, optionally followed by a comment.
+fn string(value: &str, offset: usize) -> Result<Spanned<'_>, ParseError> {
+    let (text, rest) = quoted(value, offset)?;
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        let at = offset + value.len() - rest.len();
+        return Err(ParseError::token(at, rest, "unexpected text after value"));
+    }
+    Ok(text)
+}
+
+/// A one-line array of double-quoted strings, optionally followed by a comment.
+fn array(value: &str, offset: usize) -> Result<Vec<Spanned<'_>>, ParseError> {
+    let Some(mut rest) = value.strip_prefix('[') else {
+        return Err(ParseError::token(offset, value, "expected an array, found"));
+    };
+    let mut items = Vec::new();
+    loop {
+        let trimmed = rest.trim_start();
+        let at = offset + value.len() - trimmed.len();
+        if let Some(after) = trimmed.strip_prefix(']') {
+            let after = after.trim_start();
+            if !after.is_empty() && !after.starts_with('#') {
+                let at = offset + value.len() - after.len();
+                return Err(ParseError::token(at, after, "unexpected text after value"));
+            }
+            return Ok(items);
+        }
+        let (item, after) = quoted(trimmed, at)?;
+        items.push(item);
+        let after = after.trim_start();
+        rest = match after.strip_prefix(',') {
+            Some(after) => after,
+            None if after.starts_with(']') => after,
+            None => {
+                let at = offset + value.len() - after.len();
+                return Err(ParseError::new(at, 1, "expected ',' or ']' in array"));
+            }
+        };
+    }
+}
+
+/// Split a leading double-quoted string from the rest of the value.
+fn quoted(value: &str, offset: usize) -> Result<(Spanned<'_>, &str), ParseError> {
+    let Some(body) = value.strip_prefix('"') else {
+        return Err(ParseError::token(
+            offset,
+            value,
+            "expected a quoted string, found",
+        ));
+    };
+    let Some(end) = body.find('"') else {
+        return Err(ParseError::token(offset, value, "unterminated string"));
+    };
+    let text = Spanned {
+        text: &body[..end],
+        offset: offset + 1,
+    };
+    Ok((text, &body[end + 1..]))
+}
@@ -0,0 +1,2093 @@
+use crate::{
+    color::{self, ColorSequence, Deficiency, Depth, Rgb},
+    format::When,
+    names,
+    output::RecordWriter,
+    parse::ParseError,
+    terminal::{self, TerminalCapabilities},
+};
+use anyhow::{bail, Context, Result};
+use clap::{parser::ValueSource, ArgMatches, FromArgMatches, Parser};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, IsTerminal, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+    str::FromStr,
+};
+
+const RESET: &str = "\x1b[m";
+const CARD_WIDTH: usize = 32;
+const FG_BLACK: &str = "\x1b[38;2;0;0;0m";
+const FG_GREY: &str = "\x1b[38;5;250m";
+/// Width of a row header cell, fitting a hex code with a margin.
+const ROW_HEADER_WIDTH: usize = 9;
+/// Neutral background and text of row header cells.
+const ROW_HEADER_CODES: &str = "\x1b[48;5;236m\x1b[38;5;250m";
+/// Sample text of foreground previews without other text to show.
+const PREVIEW_TEXT: &str = "Abc123";
+/// Width to wrap at when not writing to a terminal.
+const DEFAULT_WIDTH: usize = 80;
+/// Highest resolution of an axis, far more than fits a terminal, for fine-grained exports.
+const MAX_RESOLUTION: u16 = 4096;
+/// Lowest value of the heuristic text color on dark swatches.
+const MIN_BRIGHT_TEXT: f64 = 0.25;
+/// Names and printcolor aliases of the basic colors, in the order of their codes.
+const BASIC_NAMES: [(&str, &str); 16] = [
+    ("black", "k"),
+    ("red", "r"),
+    ("green", "g"),
+    ("yellow", "y"),
+    ("blue", "b"),
+    ("magenta", "m"),
+    ("cyan", "c"),
+    ("white", "w"),
+    ("bright-black", "K"),
+    ("bright-red", "R"),
+    ("bright-green", "G"),
+    ("bright-yellow", "Y"),
+    ("bright-blue", "B"),
+    ("bright-magenta", "M"),
+    ("bright-cyan", "C"),
+    ("bright-white", "W"),
+];
+/// Width of a cell of the basic color matrix, fitting the sample text and its mark.
+const MATRIX_CELL_WIDTH: usize = 4;
+/// Lowest WCAG contrast ratio of readable text (level AA).
+const MIN_CONTRAST: f64 = 4.5;
+
+/// Options of the table, chart, or card to render, as colortable takes them on the command
+/// line, built up by chaining from the defaults.
+#[derive(Debug, Clone, Parser)]
+#[non_exhaustive]
+#[clap(about = "Display terminal colors.")]
+#[clap(author = "https://ariel.ninja")]
+#[clap(version)]
+#[clap(disable_version_flag = true)]
+pub struct Args {
+    /// Hue resolution (1 to 4096)
+    #[arg(short = 'H', long, default_value_t = 16, value_parser = hue_resolution)]
+    pub hues: u16,
+    /// Value resolution (up to 4096, or 0 for none)
+    #[arg(short = 'V', long, default_value_t = 4, value_parser = resolution)]
+    pub values: u16,
+    /// Saturation resolution (up to 4096, or 0 for none)
+    #[arg(short = 'S', long, default_value_t = 4, value_parser = resolution)]
+    pub saturations: u16,
+    /// Resolution (overwrites both value and saturation resolutions, 0 shows only the pure hues)
+    #[arg(short, long, value_parser = resolution)]
+    pub resolution: Option<u16>,
+    /// Value levels from 0 to 1 (overwrites the value resolution)
+    #[arg(long, value_name = "LEVELS", value_delimiter = ',', value_parser = level)]
+    pub value_levels: Vec<f64>,
+    /// Saturation levels from 0 to 1 (overwrites the saturation resolution)
+    #[arg(long, value_name = "LEVELS", value_delimiter = ',', value_parser = level)]
+    pub saturation_levels: Vec<f64>,
+    /// Exponent of the value level spacing (above 1 concentrates levels near dark)
+    #[arg(long, value_name = "G", default_value_t = 1.0, value_parser = gamma, conflicts_with = "value_levels")]
+    pub value_gamma: f64,
+    /// Exponent of the saturation level spacing (above 1 concentrates levels near pale)
+    #[arg(long, value_name = "G", default_value_t = 1.0, value_parser = gamma, conflicts_with = "saturation_levels")]
+    pub saturation_gamma: f64,
+    /// Pick the largest resolutions that fit the terminal (explicit -H/-V/-S/-r take precedence)
+    #[arg(long)]
+    pub fit: bool,
+    /// Lowest hue in degrees
+    #[arg(long, default_value_t = 0.0)]
+    pub hue_min: f64,
+    /// Highest hue in degrees (may be below --hue-min to wrap around 0)
+    #[arg(long, default_value_t = 360.0)]
+    pub hue_max: f64,
+    /// Offset hue in degrees
+    #[arg(short, long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub offset: f64,
+    /// Color model of the grid
+    #[arg(short, long, value_enum, default_value_t = Model::Hsv)]
+    pub model: Model,
+    /// Rows of color temperatures from MIN to MAX kelvin (1000 to 40000) instead of hues, as
+    /// many as the hue resolution, with the value and saturation levels applied in HSV
+    #[arg(
+        long,
+        value_name = "MIN:MAX",
+        value_parser = kelvin_range,
+        conflicts_with_all = ["hue_min", "hue_max", "offset", "model"]
+    )]
+    pub kelvin: Option<(f64, f64)>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Terminal)]
+    pub format: Format,
+    /// Width of a table cell in characters [default: fits the display option]
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub cell_width: Option<u16>,
+    /// Size of a swatch in pixels for SVG output
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u32).range(8..))]
+    pub cell_size: u32,
+    /// Value percent of the normal (not bright) colors in theme output
+    #[arg(long, default_value_t = 75.0)]
+    pub theme_value: f64,
+    /// Omit the header line of CSV output
+    #[arg(long)]
+    pub no_header: bool,
+    /// Variable name prefix for shell output
+    #[arg(long, default_value = "COLOR")]
+    pub prefix: String,
+    /// Display options
+    #[arg(short, long, value_enum, default_value_t = DisplayOptions::Rgb)]
+    pub display: DisplayOptions,
+    /// Simulate how colors appear with a color vision deficiency
+    #[arg(long, value_enum)]
+    pub simulate: Option<Deficiency>,
+    /// How to choose the text color of a swatch
+    #[arg(long, value_enum, default_value_t = Contrast::Heuristic)]
+    pub contrast: Contrast,
+    /// Luminance formula for the lum display and the heuristic text color
+    #[arg(long, value_enum, default_value_t = Luminance::Legacy)]
+    pub luminance: Luminance,
+    /// Dark color threshold
+    #[arg(long, default_value_t = 50.0)]
+    pub dark: f64,
+    /// Dark color factor
+    #[arg(short = 'D', long, default_value_t = 5.0)]
+    pub dark_factor: f64,
+    /// Text color of every swatch (a color or CSS name), or auto to choose by contrast
+    #[arg(long, value_name = "COLOR", default_value = "auto")]
+    pub text_color: TextColor,
+    /// Text color on bright swatches, shaded by --dark-factor with the heuristic contrast
+    #[arg(long, value_name = "COLOR", default_value = "#000000", value_parser = names::parse)]
+    pub text_dark: Rgb,
+    /// Text color on dark swatches, shaded by --dark-factor with the heuristic contrast
+    #[arg(long, value_name = "COLOR", default_value = "#ffffff", value_parser = names::parse)]
+    pub text_bright: Rgb,
+    /// Show legend (hue, saturation, and value)
+    #[arg(short, long)]
+    pub legend: bool,
+    /// Show colors as swatches, as text on the default background, or both side by side
+    #[arg(long, value_enum, default_value_t = Preview::Bg)]
+    pub preview: Preview,
+    /// With --display ansi, blank out codes already shown, or list each code once (collapse)
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "blank"
+    )]
+    pub unique: Option<Unique>,
+    /// Spaces between cells
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub gap: usize,
+    /// Blank line between rows
+    #[arg(long)]
+    pub row_gap: bool,
+    /// Frame each cell with box-drawing characters
+    #[arg(long)]
+    pub border: bool,
+    /// Start each hue row with the hex of its pure hue
+    #[arg(long)]
+    pub row_header: bool,
+    /// Print hues as columns and value and saturation levels as rows
+    #[arg(short, long)]
+    pub transpose: bool,
+    /// Print an information card for one color (#RRGGBB, rgb(R, G, B), hsv(H, S, V),
+    /// hsl(H, S, L), or a CSS name)
+    #[arg(long, value_name = "COLOR", value_parser = names::parse)]
+    pub pick: Option<Rgb>,
+    /// Print the differences between two colors
+    #[arg(long, num_args = 2, value_names = ["COLOR1", "COLOR2"], value_parser = names::parse)]
+    pub distance: Vec<Rgb>,
+    /// Print a gradient between two colors instead of the table
+    #[arg(long, num_args = 2, value_names = ["FROM", "TO"], value_parser = names::parse)]
+    pub gradient: Vec<Rgb>,
+    /// Number of gradient steps
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub steps: u16,
+    /// Color space to interpolate the gradient in
+    #[arg(long, value_enum, default_value_t = Space::Oklab)]
+    pub space: Space,
+    /// Print a chart of palette colors instead of the table
+    #[arg(long, value_enum)]
+    pub chart: Option<Chart>,
+    /// Mark the cells of the matrix16 chart with a contrast ratio below 4.5:1 in the default
+    /// palette
+    #[arg(long, requires = "chart")]
+    pub mark_low_contrast: bool,
+    /// Print a ramp of this many greys from black to white instead of the table
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=256))]
+    pub greyscale: Option<u16>,
+    /// Pick a color from the table with the arrow keys or hjkl, copying it with enter
+    #[arg(long)]
+    pub interactive: bool,
+    /// Print the colors of the table as one deduplicated list, sorted by --sort
+    #[arg(long)]
+    pub flat: bool,
+    /// Order of the colors in flat mode
+    #[arg(long, value_enum, default_value_t = Sort::Luminosity)]
+    pub sort: Sort,
+    /// Write to a file instead of stdout
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    /// Color depth of the swatches [default: detected from COLORTERM and TERM]
+    #[arg(long, value_enum)]
+    pub depth: Option<Depth>,
+    /// When to use escape sequences (auto: only to a terminal, and unless NO_COLOR is set)
+    #[arg(long, value_enum, default_value_t = When::Auto)]
+    pub color: When,
+    /// Keep escape sequences when writing to a file or pipe (same as --color always)
+    #[arg(long, conflicts_with = "color")]
+    pub force_ansi: bool,
+    /// Flush after every row even when piped
+    #[arg(long)]
+    pub flush_every_line: bool,
+    /// Print version
+    #[arg(long, action = clap::ArgAction::Version)]
+    version: (),
+    /// Omit escape sequences (set when not writing to a terminal)
+    #[arg(skip)]
+    plain: bool,
+}
+
+/// Color model of the grid columns
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Model {
+    /// Value then saturation, at full saturation and value respectively
+    Hsv,
+    /// Lightness from black through the pure hue to white, at full saturation
+    Hsl,
+    /// Lightness then chroma in OKLCH, for perceptually even hue steps
+    Oklch,
+}
+
+impl Model {
+    /// Colors of the left columns (darker than the pure hue).
+    pub fn dark(self, h: f64, v: f64) -> Rgb {
+        match self {
+            Model::Hsv => Rgb::from_hsv(h, 1.0, v),
+            Model::Hsl => Rgb::from_hsl(h, 1.0, v / 2.0),
+            Model::Oklch => Rgb::from_oklch(OKLCH_LIGHTNESS * v, OKLCH_CHROMA, h),
+        }
+    }
+
+    /// Colors of the right columns (lighter than the pure hue).
+    pub fn light(self, h: f64, s: f64) -> Rgb {
+        match self {
+            Model::Hsv => Rgb::from_hsv(h, s, 1.0),
+            Model::Hsl => Rgb::from_hsl(h, 1.0, 1.0 - s / 2.0),
+            Model::Oklch => Rgb::from_oklch(
+                OKLCH_LIGHTNESS + (1.0 - OKLCH_LIGHTNESS) * (1.0 - s),
+                OKLCH_CHROMA * s,
+                h,
+            ),
+        }
+    }
+
+    fn dark_label(self, v: f64) -> String {
+        match self {
+            Model::Hsv => format!("{:>3}% v", (v * 100.0).round()),
+            Model::Hsl => format!("{:>3}% l", (v * 50.0).round()),
+            Model::Oklch => format!("{:>3}% L", (OKLCH_LIGHTNESS * v * 100.0).round()),
+        }
+    }
+
+    fn center_label(self) -> &'static str {
+        match self {
+            Model::Hsv => "val/sat",
+            Model::Hsl => "lum",
+            Model::Oklch => "L/C",
+        }
+    }
+
+    fn light_label(self, s: f64) -> String {
+        match self {
+            Model::Hsv => format!("{:>3}% s", (s * 100.0).round()),
+            Model::Hsl => format!("{:>3}% l", (100.0 - s * 50.0).round()),
+            Model::Oklch => format!("{:>3}% c", (s * 100.0).round()),
+        }
+    }
+}
+
+impl Default for Args {
+    /// The defaults of colortable.
+    fn default() -> Self {
+        Self::parse_from(["colortable"])
+    }
+}
+
+impl Args {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hues(mut self, hues: u16) -> Self {
+        self.hues = hues;
+        self
+    }
+
+    pub fn values(mut self, values: u16) -> Self {
+        self.values = values;
+        self
+    }
+
+    pub fn saturations(mut self, saturations: u16) -> Self {
+        self.saturations = saturations;
+        self
+    }
+
+    /// Hues from min to max degrees, wrapping around 0 if max is below min.
+    pub fn hue_range(mut self, min: f64, max: f64) -> Self {
+        self.hue_min = min;
+        self.hue_max = max;
+        self
+    }
+
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Rows of color temperatures from min to max kelvin instead of hues.
+    pub fn kelvin(mut self, min: f64, max: f64) -> Self {
+        self.kelvin = Some((min, max));
+        self
+    }
+
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn display(mut self, display: DisplayOptions) -> Self {
+        self.display = display;
+        self
+    }
+
+    pub fn depth(mut self, depth: Depth) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn legend(mut self, legend: bool) -> Self {
+        self.legend = legend;
+        self
+    }
+
+    pub fn transpose(mut self, transpose: bool) -> Self {
+        self.transpose = transpose;
+        self
+    }
+
+    /// Omit escape sequences, as colortable does when not writing to a terminal.
+    pub fn plain(mut self, plain: bool) -> Self {
+        self.plain = plain;
+        self
+    }
+
+    /// Check the hue range and apply the resolution and level options to the resolutions.
+    fn resolve(&mut self) -> Result<()> {
+        for hue in [self.hue_min, self.hue_max] {
+            if !(0.0..=360.0).contains(&hue) {
+                bail!("hue {hue} is not between 0 and 360 degrees");
+            }
+        }
+        if let Some(resolution) = self.resolution {
+            self.values = resolution;
+            self.saturations = resolution;
+        }
+        // Only used to fit the table, the levels themselves are used as given
+        if !self.value_levels.is_empty() {
+            self.values = self.value_levels.len().min(MAX_RESOLUTION.into()) as u16;
+        }
+        if !self.saturation_levels.is_empty() {
+            self.saturations = self.saturation_levels.len().min(MAX_RESOLUTION.into()) as u16;
+        }
+        Ok(())
+    }
+
+    /// Set the resolutions of the axes not given explicitly to the largest that fit the terminal.
+    ///
+    /// Leaves one row free for the shell prompt.
+    fn fit_to(&mut self, columns: usize, rows: usize, hues: bool, values: bool, saturations: bool) {
+        let width = self.column_width() + self.frame_width() + self.gap;
+        let height = if self.border { 3 } else { 1 } + usize::from(self.row_gap);
+        // Gaps are only between cells and rows
+        let (columns, mut rows) = (columns + self.gap, rows + usize::from(self.row_gap));
+        rows = rows.saturating_sub(1);
+        if self.display == DisplayOptions::Ansi {
+            // The basic palette and greyscale footer
+            rows = rows.saturating_sub(7);
+        }
+        if self.legend {
+            // The header row
+            rows = rows.saturating_sub(1);
+        }
+        let (hue_cells, level_cells) = if self.transpose {
+            let label = if self.legend {
+                let widest = [
+                    self.model.dark_label(1.0).len(),
+                    self.model.center_label().len(),
+                    self.model.light_label(1.0).len(),
+                ];
+                1 + widest.into_iter().max().unwrap_or_default()
+            } else {
+                0
+            };
+            if self.row_header {
+                rows = rows.saturating_sub(height);
+            }
+            (columns.saturating_sub(label) / width, rows / height)
+        } else {
+            let mut label = if self.legend { " hue: 360".len() } else { 0 };
+            if self.row_header {
+                label += ROW_HEADER_WIDTH + self.frame_width() + self.gap;
+            }
+            (rows / height, columns.saturating_sub(label) / width)
+        };
+        if !hues {
+            self.hues = hue_cells.clamp(1, MAX_RESOLUTION.into()) as u16;
+        }
+        // The pure hue column is always shown, and level counts are incremented by two
+        let levels = level_cells.saturating_sub(1);
+        match (values, saturations) {
+            (true, true) => {}
+            (true, false) => {
+                self.saturations = levels
+                    .saturating_sub(self.values.into())
+                    .min(MAX_RESOLUTION.into()) as u16
+            }
+            (false, true) => {
+                self.values = levels
+                    .saturating_sub(self.saturations.into())
+                    .min(MAX_RESOLUTION.into()) as u16
+            }
+            (false, false) => {
+                self.values = levels.div_ceil(2).min(MAX_RESOLUTION.into()) as u16;
+                self.saturations = (levels / 2).min(MAX_RESOLUTION.into()) as u16;
+            }
+        }
+    }
+
+    fn cell_width(&self) -> usize {
+        self.cell_width
+            .map_or(self.display.cell_width(), usize::from)
+    }
+
+    /// Width added to each cell by --border.
+    fn frame_width(&self) -> usize {
+        if self.border {
+            2
+        } else {
+            0
+        }
+    }
+
+    /// Width of the cells of one color, which are paired when previewing both.
+    fn column_width(&self) -> usize {
+        match self.preview {
+            Preview::Both => 2 * self.cell_width(),
+            _ => self.cell_width(),
+        }
+    }
+
+    /// Color depth of the swatches, 24-bit unless set or detected.
+    fn swatch_depth(&self) -> Depth {
+        self.depth.unwrap_or(Depth::Truecolor)
+    }
+
+    /// The color as seen with the simulated color vision deficiency, if any.
+    fn simulated(&self, color: Rgb) -> Rgb {
+        self.simulate
+            .map_or(color, |deficiency| color.simulate(deficiency))
+    }
+
+    /// Whether to omit escape sequences, given whether the output is a terminal.
+    fn is_plain(&self, terminal: bool) -> bool {
+        match (self.color, self.force_ansi) {
+            (When::Always, _) | (_, true) => false,
+            (When::Never, _) => true,
+            (When::Auto, _) => {
+                !terminal || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+            }
+        }
+    }
+}
+
+/// Output format of the grid
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// Colored swatches
+    Terminal,
+    /// JSON document of the grid, without escape sequences
+    Json,
+    /// One line per swatch with its generating parameters, without escape sequences
+    Csv,
+    /// SVG image of the grid
+    Svg,
+    /// Shell variable assignments of the hex colors and their escape sequences
+    Sh,
+    /// Alacritty theme (TOML) of 16 colors picked from the grid
+    Alacritty,
+    /// Kitty theme of 16 colors picked from the grid
+    Kitty,
+}
+
+/// Chart of palette colors
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Chart {
+    /// All 256 palette colors: basic, 6×6×6 cube, and greyscale ramp
+    Ansi256,
+    /// Every basic foreground on every basic background, in the terminal's own palette
+    Matrix16,
+}
+
+/// How a cell shows its color
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Preview {
+    /// Swatches with contrasting text
+    Bg,
+    /// Text in the color on the default background
+    Fg,
+    /// A swatch and text side by side
+    Both,
+}
+
+/// Treatment of repeated palette codes
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Unique {
+    /// Keep the table, with blank cells for repeated codes
+    Blank,
+    /// Print each code once in a wrapped list instead of the table
+    Collapse,
+}
+
+/// Order of the colors in flat mode
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Sort {
+    /// Darkest to brightest, by the legacy luminosity
+    Luminosity,
+    /// Around the wheel from red
+    Hue,
+}
+
+/// Color space of gradients
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Space {
+    /// Channels of the gamma encoded color
+    Rgb,
+    /// Hue along the shorter way around the circle, saturation, and value
+    Hsv,
+    /// OKLab, perceptually uniform
+    Oklab,
+}
+
+impl Space {
+    /// Color a fraction t (0 to 1) of the way from one color to another.
+    pub fn interpolate(self, from: Rgb, to: Rgb, t: f64) -> Rgb {
+        match self {
+            Space::Rgb => from.lerp(to, t),
+            Space::Hsv => from.lerp_hsv(to, t),
+            Space::Oklab => from.lerp_oklab(to, t),
+        }
+    }
+}
+
+/// Luminance formula
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Luminance {
+    /// Weighted sum of the gamma encoded channels
+    Legacy,
+    /// Weighted sum of the linearized channels
+    Linear,
+}
+
+impl Luminance {
+    /// Brightness of a color from 0 to 1 by this formula.
+    pub fn of(self, color: Rgb) -> f64 {
+        match self {
+            Luminance::Legacy => color.luminosity(),
+            Luminance::Linear => color.relative_luminance(),
+        }
+    }
+}
+
+/// Text color of the swatches
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextColor {
+    /// Chosen by contrast with each swatch
+    Auto,
+    /// The same color on every swatch
+    Fixed(Rgb),
+}
+
+impl FromStr for TextColor {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(Self::Auto),
+            _ => names::parse(s).map(Self::Fixed),
+        }
+    }
+}
+
+/// How to choose the text color of a swatch
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum Contrast {
+    /// Grey text from the dark threshold and factor
+    Heuristic,
+    /// Black or white, whichever has the higher WCAG 2.1 contrast ratio
+    Wcag,
+}
+
+/// Text shown in each cell
+#[derive(Debug, Copy, Clone, PartialEq, clap::ValueEnum)]
+pub enum DisplayOptions {
+    /// RGB
+    Rgb,
+    /// ANSI color codes
+    Ansi,
+    /// Luminosity (according to EIC-1931)
+    Lum,
+    /// CIE L*a*b* (D65 white point)
+    Lab,
+    /// WCAG contrast ratio of the text against the swatch
+    Contrast,
+    /// Nearest CSS named color
+    Name,
+    /// Hue degrees, saturation percent, and value percent
+    Hsv,
+    /// none
+    None,
+}
+
+impl DisplayOptions {
+    /// Default width of a table cell, fitting the widest text.
+    fn cell_width(self) -> usize {
+        match self {
+            DisplayOptions::Lab => 15,
+            DisplayOptions::Hsv => 13,
+            _ => 9,
+        }
+    }
+}
+
+/// Print the table, chart, or card that the arguments of colortable ask for.
+///
+/// Takes the matches rather than [`Args`] to tell explicit resolutions from defaults.
+pub fn print(matches: &ArgMatches) -> Result<()> {
+    let mut args = Args::from_arg_matches(matches)?;
+    args.resolve()?;
+    if args.depth.is_none() {
+        args.depth = Some(TerminalCapabilities::detect().depth());
+    }
+    if args.fit {
+        if let Some((columns, rows)) = terminal::size() {
+            let explicit = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+            let resolution = explicit("resolution");
+            args.fit_to(
+                columns,
+                rows,
+                explicit("hues"),
+                explicit("values") || explicit("value_levels") || resolution,
+                explicit("saturations") || explicit("saturation_levels") || resolution,
+            );
+        }
+    }
+    match args.output.clone() {
+        Some(path) => {
+            let file = File::create(&path)
+                .with_context(|| format!("failed to create '{}'", path.display()))?;
+            args.plain = args.is_plain(false);
+            run(&args, RecordWriter::new(file, args.flush_every_line))
+        }
+        None => {
+            // A console without escape processing would show the sequences as text
+            args.plain =
+                !terminal::enable_vt_processing() || args.is_plain(io::stdout().is_terminal());
+            run(&args, RecordWriter::stdout(args.flush_every_line))
+        }
+    }
+}
+
+/// Write the table, chart, or card that the arguments ask for, with escape sequences unless
+/// [`Args::plain`] is set.
+///
+/// Unlike [`print()`], this does not detect the color depth or the terminal size.
+pub fn render(args: &Args, out: impl Write) -> Result<()> {
+    let mut args = args.clone();
+    args.resolve()?;
+    run(&args, RecordWriter::new(out, args.flush_every_line))
+}
+
+fn run(args: &Args, mut out: RecordWriter<impl Write>) -> Result<()> {
+    if let Some(color) = args.pick {
+        for line in card(args, args.simulated(color)) {
+            out.record(&line)?;
+        }
+        out.finish()?;
+        return Ok(());
+    }
+    if let [a, b] = args.distance[..] {
+        let (a, b) = (args.simulated(a), args.simulated(b));
+        let swatch = |color: Rgb| paint(args, bg(args, color), &" ".repeat(CARD_WIDTH / 2));
+        let lines = [
+            format!("{}{}", swatch(a), swatch(b)),
+            format!("colors        #{a:X} #{b:X}"),
+            format!("rgb distance  {:.2}", a.rgb_distance(b)),
+            format!("ΔE 1976       {:.2}", a.delta_e76(b)),
+            format!("ΔE 2000       {:.2}", a.delta_e2000(b)),
+        ];
+        for line in lines.iter().skip(usize::from(args.plain)) {
+            out.record(&format!("{line}\n"))?;
+        }
+        out.finish()?;
+        return Ok(());
+    }
+    if let [from, to] = args.gradient[..] {
+        let (from, to) = (from, to);
+        for step in 0..args.steps {
+            let t = match args.steps {
+                1 => 0.0,
+                steps => f64::from(step) / f64::from(steps - 1),
+            };
+            let color = args.simulated(args.space.interpolate(from, to, t));
+            let row = match (args.legend, args.display) {
+                (true, DisplayOptions::None) => format!("#{color:X}\n"),
+                (true, _) => format!("{} #{color:X}\n", cell(args, color)),
+                (false, _) => format!("{}\n", cell(args, color)),
+            };
+            out.record(&row)?;
+        }
+        out.finish()?;
+        return Ok(());
+    }
+    if let Some(chart) = args.chart {
+        match chart {
+            Chart::Ansi256 => print_ansi256_chart(args, &mut out)?,
+            Chart::Matrix16 => print_matrix16_chart(args, &mut out)?,
+        }
+        out.finish()?;
+        return Ok(());
+    }
+    if let Some(steps) = args.greyscale {
+        for step in 0..steps {
+            let v = match steps {
+                1 => 0.0,
+                steps => f64::from(step) / f64::from(steps - 1),
+            };
+            let color = Rgb::from_channels([v; 3]);
+            let cell = match args.display {
+                DisplayOptions::Ansi => grey_cell(args, color),
+                _ => cell(args, color),
+            };
+            let row = match (args.legend, args.display) {
+                (true, DisplayOptions::None) => format!("#{color:X}\n"),
+                (true, _) => format!("{cell} #{color:X}\n"),
+                (false, _) => format!("{cell}\n"),
+            };
+            out.record(&row)?;
+        }
+        out.finish()?;
+        return Ok(());
+    }
+    if args.unique.is_some() && args.display != DisplayOptions::Ansi {
+        bail!("--unique needs --display ansi");
+    }
+    let grid = Grid::new(args);
+    if args.unique == Some(Unique::Collapse) {
+        print_unique_codes(args, &grid, &mut out)?;
+        out.finish()?;
+        return Ok(());
+    }
+    if args.interactive {
+        return interactive(args, &grid);
+    }
+    if args.flat {
+        print_flat(args, &grid, &mut out)?;
+        out.finish()?;
+        return Ok(());
+    }
+    match args.format {
+        Format::Terminal => print_table(args, &grid, &mut out)?,
+        Format::Json => print_json(&grid, &mut out)?,
+        Format::Csv => print_csv(args, &grid, &mut out)?,
+        Format::Svg => print_svg(args, &grid, &mut out)?,
+        Format::Sh => print_sh(args, &grid, &mut out)?,
+        Format::Alacritty | Format::Kitty => print_theme(args, &grid, &mut out)?,
+    }
+    out.finish()?;
+    Ok(())
+}
+
+/// Colors of the table, one row per hue or color temperature, as generated by the [`Args`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// Parameters of the columns left of the pure hue
+    values: Vec<f64>,
+    /// Parameters of the columns right of the pure hue
+    saturations: Vec<f64>,
+    /// Hue or color temperature and colors of each row
+    rows: Vec<(RowKey, Vec<Rgb>)>,
+}
+
+/// Parameter of a row of the grid
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RowKey {
+    /// Hue from 0 to 1
+    Hue(f64),
+    /// Color temperature in kelvin
+    Kelvin(f64),
+}
+
+impl RowKey {
+    /// Name of the parameter in labels and exports.
+    pub fn name(self) -> &'static str {
+        match self {
+            RowKey::Hue(_) => "hue",
+            RowKey::Kelvin(_) => "kelvin",
+        }
+    }
+
+    /// The hue in degrees, or the color temperature in kelvin.
+    pub fn value(self) -> f64 {
+        match self {
+            RowKey::Hue(h) => h * 360.0,
+            RowKey::Kelvin(kelvin) => kelvin,
+        }
+    }
+
+    /// The pure hue, or the blackbody color.
+    pub fn color(self) -> Rgb {
+        match self {
+            RowKey::Hue(h) => Rgb::from_hsv(h, 1.0, 1.0),
+            RowKey::Kelvin(kelvin) => Rgb::from_channels(color::blackbody(kelvin)),
+        }
+    }
+}
+
+impl Grid {
+    /// Value levels of the columns left of the pure hue.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Saturation levels of the columns right of the pure hue.
+    pub fn saturations(&self) -> &[f64] {
+        &self.saturations
+    }
+
+    /// The hue or color temperature and the colors of each row, from the darkest column.
+    pub fn rows(&self) -> &[(RowKey, Vec<Rgb>)] {
+        &self.rows
+    }
+
+    /// Number of colors in each row.
+    pub fn columns(&self) -> usize {
+        self.values.len() + 1 + self.saturations.len()
+    }
+
+    /// Legend label of a column.
+    fn column_label(&self, model: Model, index: usize) -> String {
+        match index.checked_sub(self.values.len()) {
+            None => model.dark_label(self.values[index]),
+            Some(0) => model.center_label().to_owned(),
+            Some(right) => model.light_label(self.saturations[right - 1]),
+        }
+    }
+
+    /// Saturation and value (as generated in the HSV model) of a column.
+    pub fn column(&self, index: usize) -> (f64, f64) {
+        match index.checked_sub(self.values.len()) {
+            None => (1.0, self.values[index]),
+            Some(0) => (1.0, 1.0),
+            Some(right) => (self.saturations[right - 1], 1.0),
+        }
+    }
+
+    /// Colors of the hues (or color temperatures) and levels of the arguments.
+    pub fn new(args: &Args) -> Self {
+        let hues = arc(
+            args.hues,
+            args.hue_min / 360.0,
+            args.hue_max / 360.0,
+            args.offset / 360.0,
+        );
+        let values = if args.value_levels.is_empty() {
+            range(args.values + 2, 1, 1, 0.0, args.value_gamma)
+        } else {
+            args.value_levels.clone()
+        };
+        let saturations = if !args.saturation_levels.is_empty() {
+            args.saturation_levels.clone()
+        } else if args.model == Model::Hsl {
+            // Down to 0 so that the last column is white, at 100% lightness
+            (0..args.saturations)
+                .rev()
+                .map(|i| (f64::from(i) / f64::from(args.saturations)).powf(args.saturation_gamma))
+                .collect()
+        } else {
+            let mut saturations = range(args.saturations + 2, 1, 1, 0.0, args.saturation_gamma);
+            saturations.reverse();
+            saturations
+        };
+        let keys: Vec<RowKey> = match args.kelvin {
+            Some((min, max)) => (0..args.hues)
+                .map(|step| {
+                    let t = match args.hues {
+                        1 => 0.0,
+                        steps => f64::from(step) / f64::from(steps - 1),
+                    };
+                    RowKey::Kelvin(min + (max - min) * t)
+                })
+                .collect(),
+            None => hues.into_iter().map(RowKey::Hue).collect(),
+        };
+        let rows = keys
+            .into_iter()
+            .map(|key| {
+                let colors: Vec<Rgb> = match key {
+                    RowKey::Hue(h) => values
+                        .iter()
+                        .map(|v| args.model.dark(h, *v))
+                        .chain([args.model.dark(h, 1.0)])
+                        .chain(saturations.iter().map(|s| args.model.light(h, *s)))
+                        .collect(),
+                    // Scale the value and saturation of the blackbody color
+                    RowKey::Kelvin(_) => {
+                        let (h, s, v) = key.color().to_hsv();
+                        values
+                            .iter()
+                            .map(|level| Rgb::from_hsv(h, s, v * level))
+                            .chain([key.color()])
+                            .chain(
+                                saturations
+                                    .iter()
+                                    .map(|level| Rgb::from_hsv(h, s * level, v)),
+                            )
+                            .collect()
+                    }
+                };
+                let colors = colors
+                    .into_iter()
+                    .map(|color| args.simulated(color))
+                    .collect();
+                (key, colors)
+            })
+            .collect();
+        Self {
+            values,
+            saturations,
+            rows,
+        }
+    }
+}
+
+fn print_table(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let width = args.cell_width();
+    let column = args.column_width();
+    let key_label = |key: &RowKey| key.value().round().to_string();
+    // Codes shown so far, with --unique
+    let mut seen = [false; 256];
+    let mut unique_cell = |color: &Rgb| {
+        if args.unique.is_some() {
+            let code = usize::from(color.nearest_ansi_code());
+            if std::mem::replace(&mut seen[code], true) {
+                return " ".repeat(column);
+            }
+        }
+        cell(args, *color)
+    };
+    let rows = if args.transpose {
+        grid.columns()
+    } else {
+        grid.rows.len()
+    };
+    if args.transpose {
+        if let (true, Some((key, _))) = (args.legend, grid.rows.first()) {
+            let labels: Vec<String> = grid.rows.iter().map(|(key, _)| key_label(key)).collect();
+            out.record(&label_row(
+                args,
+                &labels,
+                column,
+                &format!(" {}", key.name()),
+            ))?;
+        }
+        if args.row_header {
+            let cells: Vec<String> = grid
+                .rows
+                .iter()
+                .map(|(key, _)| row_header(args, *key, column))
+                .collect();
+            out.record(&table_row(args, &cells, column, None, ""))?;
+            if args.row_gap && rows > 0 {
+                out.record("\n")?;
+            }
+        }
+        for index in 0..grid.columns() {
+            if args.row_gap && index > 0 {
+                out.record("\n")?;
+            }
+            let cells: Vec<String> = grid
+                .rows
+                .iter()
+                .map(|(_, colors)| unique_cell(&colors[index]))
+                .collect();
+            let label = match args.legend {
+                true => format!(" {}", grid.column_label(args.model, index)),
+                false => String::new(),
+            };
+            out.record(&table_row(args, &cells, column, None, &label))?;
+        }
+    } else {
+        // Without value and saturation levels, this labels the lone pure hue column
+        if args.legend {
+            let labels: Vec<String> = (0..grid.columns())
+                .map(|index| grid.column_label(args.model, index))
+                .collect();
+            let mut row = String::new();
+            if args.row_header {
+                row.push_str(&" ".repeat(ROW_HEADER_WIDTH + args.frame_width() + args.gap));
+            }
+            row.push_str(&label_row(args, &labels, column, ""));
+            out.record(&row)?;
+        }
+        for (i, (key, colors)) in grid.rows.iter().enumerate() {
+            if args.row_gap && i > 0 {
+                out.record("\n")?;
+            }
+            let header = args
+                .row_header
+                .then(|| row_header(args, *key, ROW_HEADER_WIDTH));
+            let cells: Vec<String> = colors.iter().map(&mut unique_cell).collect();
+            let label = match args.legend {
+                true => format!(" {}: {}", key.name(), key_label(key)),
+                false => String::new(),
+            };
+            out.record(&table_row(args, &cells, column, header, &label))?;
+        }
+    }
+    if let DisplayOptions::Ansi = args.display {
+        // Basic palette
+        out.record("\n")?;
+        for row in [0..8, 8..16] {
+            let mut line = String::new();
+            for i in row {
+                let foreground = if i == 0 { FG_GREY } else { FG_BLACK };
+                let codes = format!("\x1b[48;5;{i}m{foreground}");
+                line.push_str(&paint(args, &codes, &fit(&i.to_string(), width)));
+            }
+            line.push('\n');
+            out.record(&line)?;
+        }
+        // Greyscale
+        out.record("\n")?;
+        for row in [232..240, 240..248, 248..256] {
+            let mut line = String::new();
+            for i in row {
+                let foreground = if i <= 237 { FG_GREY } else { FG_BLACK };
+                let codes = format!("\x1b[48;5;{i}m{foreground}");
+                line.push_str(&paint(args, &codes, &fit(&i.to_string(), width)));
+            }
+            line.push('\n');
+            out.record(&line)?;
+        }
+    }
+    if args.unique.is_some() {
+        let distinct = seen.iter().filter(|seen| **seen).count();
+        out.record(&format!("\n{distinct} distinct codes\n"))?;
+    }
+    Ok(())
+}
+
+/// Alternate screen with a hidden cursor on the terminal in raw mode, restored when dropped.
+#[cfg(unix)]
+struct Screen(terminal::RawTerminal);
+
+#[cfg(unix)]
+impl Screen {
+    fn enter() -> Result<Self> {
+        let mut tty = terminal::RawTerminal::open().context("interactive mode needs a terminal")?;
+        tty.write_all(b"\x1b[?1049h\x1b[?25l")?;
+        Ok(Self(tty))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Screen {
+    fn drop(&mut self) {
+        self.0.write_all(b"\x1b[?25h\x1b[?1049l").ok();
+    }
+}
+
+/// Move a cursor over the table, showing the focused color and copying it on enter.
+#[cfg(unix)]
+fn interactive(args: &Args, grid: &Grid) -> Result<()> {
+    use terminal::Key;
+    if grid.rows.is_empty() {
+        bail!("interactive mode needs at least one hue");
+    }
+    // Hue and column index of a cell on the screen
+    let at = |y: usize, x: usize| if args.transpose { (x, y) } else { (y, x) };
+    let (height, width) = at(grid.rows.len(), grid.columns());
+    let mut screen = Screen::enter()?;
+    let (mut y, mut x) = (0, 0);
+    let mut message = String::new();
+    loop {
+        let mut frame = String::from("\x1b[H");
+        for row in 0..height {
+            for column in 0..width {
+                let (hue, index) = at(row, column);
+                if (row, column) == (y, x) {
+                    frame.push_str("\x1b[7m");
+                }
+                frame.push_str(&cell(args, grid.rows[hue].1[index]));
+            }
+            frame.push_str("\x1b[K\n");
+        }
+        let (hue, index) = at(y, x);
+        let color = grid.rows[hue].1[index];
+        let Rgb(r, g, b) = color;
+        let (h, s, v) = color.to_hsv();
+        frame.push_str(&format!(
+            "\x1b[K\n#{color:X}  rgb({r}, {g}, {b})  hsv({}, {}%, {}%)  ansi {}\x1b[K\n",
+            (h * 360.0).round(),
+            (s * 100.0).round(),
+            (v * 100.0).round(),
+            color.nearest_ansi_code(),
+        ));
+        frame.push_str(&format!(
+            "arrows/hjkl: move  enter: copy  q: quit  {message}\x1b[J"
+        ));
+        screen.0.write_all(frame.as_bytes())?;
+        screen.0.flush()?;
+        for key in screen.0.read_keys()? {
+            match key {
+                Key::Up | Key::Char('k') => y = y.saturating_sub(1),
+                Key::Down | Key::Char('j') => y = (y + 1).min(height - 1),
+                Key::Left | Key::Char('h') => x = x.saturating_sub(1),
+                Key::Right | Key::Char('l') => x = (x + 1).min(width - 1),
+                Key::Enter => {
+                    let (hue, index) = at(y, x);
+                    let hex = format!("#{:X}", grid.rows[hue].1[index]);
+                    screen
+                        .0
+                        .write_all(terminal::clipboard_sequence(&hex).as_bytes())?;
+                    message = format!("copied {hex}");
+                }
+                Key::Escape | Key::Interrupt | Key::Char('q') => return Ok(()),
+                Key::Char(_) => {}
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn interactive(_args: &Args, _grid: &Grid) -> Result<()> {
+    bail!("interactive mode is only supported on Unix")
+}
+
+/// Colors of the grid without duplicates, sorted and wrapped at the terminal width.
+fn print_flat(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let mut colors: Vec<Rgb> = Vec::new();
+    for color in grid.rows.iter().flat_map(|(_, colors)| colors) {
+        if !colors.iter().any(|c| c == color) {
+            colors.push(*color);
+        }
+    }
+    // Stable, so that colors of equal luminosity stay in hue order
+    match args.sort {
+        Sort::Luminosity => colors.sort_by(|a, b| a.luminosity().total_cmp(&b.luminosity())),
+        Sort::Hue => colors.sort_by(|a, b| a.to_hsv().0.total_cmp(&b.to_hsv().0)),
+    }
+    let index_width = colors.len().saturating_sub(1).to_string().len();
+    // Cell, then a space, the index, and the hex with its own leading space and #
+    let legend_width = if args.legend { index_width + 10 } else { 0 };
+    let per_line =
+        (terminal::width().unwrap_or(DEFAULT_WIDTH) / (args.column_width() + legend_width)).max(1);
+    for (line, chunk) in colors.chunks(per_line).enumerate() {
+        let mut row = String::new();
+        for (i, color) in chunk.iter().enumerate() {
+            row.push_str(&cell(args, *color));
+            if args.legend {
+                let index = line * per_line + i;
+                row.push_str(&format!(" {index:>index_width$} #{color:X} "));
+            }
+        }
+        row.push('\n');
+        out.record(&row)?;
+    }
+    Ok(())
+}
+
+/// Each palette code of the grid once, in the order first shown, wrapped at the terminal width.
+fn print_unique_codes(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let mut codes: Vec<u8> = Vec::new();
+    for color in grid.rows.iter().flat_map(|(_, colors)| colors) {
+        let code = color.nearest_ansi_code();
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    let per_line = (terminal::width().unwrap_or(DEFAULT_WIDTH) / args.column_width()).max(1);
+    for chunk in codes.chunks(per_line) {
+        let mut row = String::new();
+        for code in chunk {
+            row.push_str(&cell(args, Rgb::from_ansi_code(*code)));
+        }
+        row.push('\n');
+        out.record(&row)?;
+    }
+    out.record(&format!("\n{} distinct codes\n", codes.len()))?;
+    Ok(())
+}
+
+/// Every color of the 256-color palette, labeled with its code.
+fn print_ansi256_chart(args: &Args, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let width = args.cell_width();
+    let line = |codes: RangeInclusive<u8>| {
+        let mut line = String::new();
+        for code in codes {
+            let color = Rgb::from_ansi_code(code);
+            let foreground = fg(args, text_color(args, color));
+            let codes = format!("\x1b[48;5;{code}m{foreground}");
+            line.push_str(&paint(args, &codes, &fit(&code.to_string(), width)));
+        }
+        line.push('\n');
+        line
+    };
+    // Basic palette
+    for row in [0..=7, 8..=15] {
+        out.record(&line(row))?;
+    }
+    // Color cube, one plane per red level
+    for red in 0..6 {
+        out.record("\n")?;
+        for green in 0..6 {
+            let start = 16 + 36 * red + 6 * green;
+            out.record(&line(start..=start + 5))?;
+        }
+    }
+    // Greyscale
+    out.record("\n")?;
+    for row in [232..=239, 240..=247, 248..=255] {
+        out.record(&line(row))?;
+    }
+    Ok(())
+}
+
+/// Every basic foreground (columns) on every basic background (rows).
+///
+/// The cells use the basic SGR codes rather than approximations, so they show the terminal's
+/// own palette. Only the contrast marks assume the default palette.
+fn print_matrix16_chart(args: &Args, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let width = args.cell_width.map_or(MATRIX_CELL_WIDTH, usize::from);
+    let label = |(name, alias): (&str, &str)| format!("{name} ({alias})");
+    let header_width = BASIC_NAMES
+        .iter()
+        .map(|&names| label(names).len() + 1)
+        .max()
+        .unwrap_or_default();
+    let mut header = " ".repeat(header_width);
+    for (_, alias) in BASIC_NAMES {
+        header.push_str(&fit(alias, width));
+    }
+    header.push('\n');
+    out.record(&header)?;
+    // Codes 0-7 are normal colors and 8-15 bright ones
+    let sgr = |code: u8, base: u8| match code {
+        0..=7 => base + code,
+        _ => base + 60 + code - 8,
+    };
+    for bg in 0..16 {
+        let mut row = format!("{:<header_width$}", label(BASIC_NAMES[usize::from(bg)]));
+        for fg in 0..16 {
+            let contrast = Rgb::from_ansi_code(fg).contrast_ratio(Rgb::from_ansi_code(bg));
+            let text = match args.mark_low_contrast && contrast < MIN_CONTRAST {
+                true => "Aa•",
+                false => "Aa",
+            };
+            let codes = format_args!("\x1b[{};{}m", sgr(fg, 30), sgr(bg, 40));
+            row.push_str(&paint(args, codes, &fit(text, width)));
+        }
+        row.push('\n');
+        out.record(&row)?;
+    }
+    Ok(())
+}
+
+/// Labels centered over the cells of a table row, followed by the suffix.
+fn label_row(args: &Args, labels: &[String], width: usize, suffix: &str) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|label| fit(label, width + args.frame_width()))
+        .collect();
+    format!("{}{suffix}\n", labels.join(&" ".repeat(args.gap)))
+}
+
+/// Lines of a table row of cells, separated by the gap and framed with --border.
+///
+/// The cells are reset before the gap, so that it has the default background. The header is a
+/// row header cell of its own width, and the suffix follows the cells on the middle line.
+fn table_row(
+    args: &Args,
+    cells: &[String],
+    width: usize,
+    header: Option<String>,
+    suffix: &str,
+) -> String {
+    let gap = " ".repeat(args.gap);
+    let cells: Vec<(&str, usize)> = header
+        .as_deref()
+        .map(|header| (header, ROW_HEADER_WIDTH))
+        .into_iter()
+        .chain(cells.iter().map(|cell| (cell.as_str(), width)))
+        .collect();
+    let line = |left: &str, fill: &str, right: &str| {
+        let edges: Vec<String> = cells
+            .iter()
+            .map(|(_, width)| format!("{left}{}{right}", fill.repeat(*width)))
+            .collect();
+        format!("{}\n", edges.join(&gap))
+    };
+    let middle: Vec<String> = cells
+        .iter()
+        .map(|(cell, _)| match args.border {
+            true => format!("│{cell}│"),
+            false => (*cell).to_owned(),
+        })
+        .collect();
+    let middle = format!("{}{suffix}\n", middle.join(&gap));
+    match args.border {
+        true => format!("{}{middle}{}", line("┌", "─", "┐"), line("└", "─", "┘")),
+        false => middle,
+    }
+}
+
+/// Header cell with the hex of the pure hue, on a neutral background.
+fn row_header(args: &Args, key: RowKey, width: usize) -> String {
+    let hex = format!("#{:X}", key.color());
+    paint(args, ROW_HEADER_CODES, &fit(&hex, width))
+}
+
+/// The grid as a JSON document, one row per line.
+fn print_json(grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    out.record("{\"rows\":[\n")?;
+    for (i, (key, colors)) in grid.rows.iter().enumerate() {
+        let cells: Vec<String> = colors.iter().map(|color| json(*color)).collect();
+        let separator = if i + 1 < grid.rows.len() { "," } else { "" };
+        out.record(&format!(
+            "{{\"{}\":{},\"cells\":[{}]}}{separator}\n",
+            key.name(),
+            key.value().round(),
+            cells.join(",")
+        ))?;
+    }
+    out.record("]}\n")?;
+    Ok(())
+}
+
+/// The grid as CSV, with the hue, saturation, and value exactly as used to generate each swatch.
+fn print_csv(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    if !args.no_header {
+        let key = match args.kelvin {
+            Some(_) => "kelvin",
+            None => "hue_deg",
+        };
+        out.record(&format!(
+            "{key},saturation,value,hex,r,g,b,luminosity,ansi256\n"
+        ))?;
+    }
+    for (key, colors) in &grid.rows {
+        for (index, color) in colors.iter().enumerate() {
+            let (s, v) = grid.column(index);
+            let Rgb(r, g, b) = color;
+            out.record(&format!(
+                "{},{s},{v},#{color:X},{r},{g},{b},{:.4},{}\n",
+                key.value(),
+                color.luminosity(),
+                color.nearest_ansi_code(),
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// The grid as a standalone SVG document, one element per line.
+fn print_svg(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let size = args.cell_size;
+    let columns = grid.rows.first().map_or(0, |(_, colors)| colors.len()) as u32;
+    let legend = args.legend;
+    let top = if legend { size / 2 } else { 0 };
+    let right = if legend { size * 3 / 2 } else { 0 };
+    let width = columns * size + right;
+    let height = grid.rows.len() as u32 * size + top;
+    let font_size = size / 6;
+    let text = |x: u32, y: u32, fill: &str, anchor: &str, content: &str| {
+        format!(
+            "<text x=\"{x}\" y=\"{y}\" fill=\"{fill}\" font-family=\"monospace\" \
+             font-size=\"{font_size}\" text-anchor=\"{anchor}\" \
+             dominant-baseline=\"middle\">{}</text>\n",
+            xml_escape(content.trim())
+        )
+    };
+    out.record(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n"
+    ))?;
+    if legend {
+        let labels = (0..grid.columns()).map(|index| grid.column_label(args.model, index));
+        for (column, label) in (0..).zip(labels) {
+            let x = column * size + size / 2;
+            out.record(&text(x, top / 2, "#808080", "middle", &label))?;
+        }
+    }
+    for (row, (key, colors)) in (0..).zip(&grid.rows) {
+        let y = top + row * size;
+        for (column, color) in (0..).zip(colors) {
+            let x = column * size;
+            let fill = match args.display {
+                DisplayOptions::Ansi => Rgb::from_ansi_code(color.nearest_ansi_code()),
+                _ => *color,
+            };
+            out.record(&format!(
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{size}\" height=\"{size}\" fill=\"#{fill:X}\"/>\n"
+            ))?;
+            let label = cell_text(args, *color);
+            if !label.trim().is_empty() {
+                let text_color = format!("#{:X}", text_color(args, *color));
+                out.record(&text(
+                    x + size / 2,
+                    y + size / 2,
+                    &text_color,
+                    "middle",
+                    &label,
+                ))?;
+            }
+        }
+        if legend {
+            let label = format!("{}: {}", key.name(), key.value().round());
+            let x = columns * size + size / 8;
+            out.record(&text(x, y + size / 2, "#808080", "start", &label))?;
+        }
+    }
+    out.record("</svg>\n")?;
+    Ok(())
+}
+
+/// Shell assignments for every swatch, named after the hue, saturation, and value generating it.
+fn print_sh(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let prefix = &args.prefix;
+    let valid = |(i, c): (usize, char)| {
+        c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit())
+    };
+    if prefix.is_empty() || !prefix.char_indices().all(valid) {
+        bail!("prefix '{prefix}' is not a valid shell variable name");
+    }
+    for (key, colors) in &grid.rows {
+        let initial = match key {
+            RowKey::Hue(_) => 'H',
+            RowKey::Kelvin(_) => 'K',
+        };
+        for (index, color) in colors.iter().enumerate() {
+            let (s, v) = grid.column(index);
+            let name = format!(
+                "{prefix}_{initial}{}_S{}_V{}",
+                key.value().round(),
+                (s * 100.0).round(),
+                (v * 100.0).round()
+            );
+            let Rgb(r, g, b) = color;
+            out.record(&format!(
+                "{name}='{color}'\n{name}_FG=$'\\e[38;2;{r};{g};{b}m'\n{name}_BG=$'\\e[48;2;{r};{g};{b}m'\n"
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// Slot names of the 16 color palette, with the hue (in degrees) of the chromatic ones.
+const THEME_SLOTS: [(&str, Option<f64>); 8] = [
+    ("black", None),
+    ("red", Some(0.0)),
+    ("green", Some(120.0)),
+    ("yellow", Some(60.0)),
+    ("blue", Some(240.0)),
+    ("magenta", Some(300.0)),
+    ("cyan", Some(180.0)),
+    ("white", None),
+];
+
+/// A terminal theme: the normal colors at the theme value level and the bright colors at full
+/// value, each slot taking the row nearest to its hue, with greys from the value levels.
+fn print_theme(args: &Args, grid: &Grid, out: &mut RecordWriter<impl Write>) -> Result<()> {
+    let (Some(darkest), Some(lightest)) = (grid.values.first(), grid.values.last()) else {
+        bail!("theme output needs at least one value level");
+    };
+    if grid.rows.is_empty() {
+        bail!("theme output needs at least one hue");
+    }
+    let pure = grid.values.len();
+    let level = args.theme_value / 100.0;
+    let normal = (0..=pure)
+        .min_by(|a, b| {
+            let distance = |i: usize| (grid.column(i).1 - level).abs();
+            distance(*a).total_cmp(&distance(*b))
+        })
+        .unwrap_or(pure);
+    let nearest_row = |target: f64| {
+        let distance = |colors: &Vec<Rgb>| {
+            let hue = colors[pure].to_hsv().0 * 360.0;
+            let difference = (hue - target).rem_euclid(360.0);
+            difference.min(360.0 - difference)
+        };
+        grid.rows
+            .iter()
+            .map(|(_, colors)| colors)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .expect("rows checked to be non-empty")
+    };
+    let grey = |v: f64| args.simulated(Rgb::from_hsv(0.0, 0.0, v));
+    let pick = |slot: usize, bright: bool| match (THEME_SLOTS[slot].1, bright) {
+        (Some(hue), false) => nearest_row(hue)[normal],
+        (Some(hue), true) => nearest_row(hue)[pure],
+        (None, false) if slot == 0 => grey(*darkest),
+        (None, true) if slot == 0 => grey((darkest + lightest) / 2.0),
+        (None, false) => grey(*lightest),
+        (None, true) => grey(1.0),
+    };
+    let (background, foreground) = (grey(*darkest), grey(*lightest));
+    let mut lines = Vec::new();
+    if args.format == Format::Kitty {
+        lines.push(format!("background {background}"));
+        lines.push(format!("foreground {foreground}"));
+        for bright in [false, true] {
+            for slot in 0..THEME_SLOTS.len() {
+                let index = slot + if bright { THEME_SLOTS.len() } else { 0 };
+                lines.push(format!("color{index} {}", pick(slot, bright)));
+            }
+        }
+    } else {
+        lines.push("[colors.primary]".to_owned());
+        lines.push(format!("background = \"{background}\""));
+        lines.push(format!("foreground = \"{foreground}\""));
+        for (section, bright) in [("normal", false), ("bright", true)] {
+            lines.push(String::new());
+            lines.push(format!("[colors.{section}]"));
+            for (slot, (name, _)) in THEME_SLOTS.iter().enumerate() {
+                lines.push(format!("{name} = \"{}\"", pick(slot, bright)));
+            }
+        }
+    }
+    for line in lines {
+        out.record(&format!("{line}\n"))?;
+    }
+    Ok(())
+}
+
+/// Text centered in the width, truncated if longer.
+fn fit(text: &str, width: usize) -> String {
+    let text: String = text.chars().take(width).collect();
+    format!("{text:^width$}")
+}
+
+/// Text with escape sequences applied and reset, or only the text in plain output.
+fn paint(args: &Args, codes: impl fmt::Display, text: &str) -> String {
+    if args.plain {
+        text.to_owned()
+    } else {
+        format!("{codes}{text}{RESET}")
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Evenly spaced positions from 0 to 1, truncated at either end, offset (wrapping around 1),
+/// and raised to the power of gamma.
+fn range(
+    resolution: u16,
+    truncate_head: u16,
+    truncate_tail: u16,
+    offset: f64,
+    gamma: f64,
+) -> Vec<f64> {
+    if resolution
+        .saturating_sub(truncate_head)
+        .saturating_sub(truncate_tail)
+        == 0
+    {
+        return Vec::new();
+    }
+    let factor = 1.0 / f64::from(resolution.saturating_sub(1));
+    (truncate_head..resolution.saturating_sub(truncate_tail))
+        .map(|i| (f64::from(i) * factor + offset).rem_euclid(1.0).powf(gamma))
+        .collect()
+}
+
+/// Parse a resolution of an axis, up to [`MAX_RESOLUTION`].
+fn resolution(text: &str) -> Result<u16, String> {
+    let resolution: u32 = text
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{text}' is not a whole number"))?;
+    u16::try_from(resolution)
+        .ok()
+        .filter(|resolution| *resolution <= MAX_RESOLUTION)
+        .ok_or_else(|| format!("resolution {resolution} is above the maximum of {MAX_RESOLUTION}"))
+}
+
+/// Parse a resolution of the hue axis, which needs at least one hue.
+fn hue_resolution(text: &str) -> Result<u16, String> {
+    match resolution(text)? {
+        0 => Err("there must be at least one hue".to_owned()),
+        hues => Ok(hues),
+    }
+}
+
+/// Parse a range of color temperatures in kelvin (MIN:MAX).
+fn kelvin_range(text: &str) -> Result<(f64, f64), String> {
+    let Some((min, max)) = text.split_once(':') else {
+        return Err(format!("'{text}' is not a range (expected MIN:MAX)"));
+    };
+    let kelvin = |text: &str| {
+        let kelvin: f64 = text
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{text}' is not a number"))?;
+        if !(1000.0..=40000.0).contains(&kelvin) {
+            return Err(format!("{kelvin} K is not from 1000 to 40000 K"));
+        }
+        Ok(kelvin)
+    };
+    Ok((kelvin(min)?, kelvin(max)?))
+}
+
+/// Parse a positive level spacing exponent.
+fn gamma(text: &str) -> Result<f64, String> {
+    let gamma: f64 = text
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{text}' is not a number"))?;
+    if !(gamma.is_finite() && gamma > 0.0) {
+        return Err(format!("gamma {gamma} is not a positive number"));
+    }
+    Ok(gamma)
+}
+
+/// Parse a value or saturation level from 0 to 1.
+fn level(text: &str) -> Result<f64, String> {
+    let level: f64 = text
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{text}' is not a number"))?;
+    if !(0.0..=1.0).contains(&level) {
+        return Err(format!("level {level} is not between 0 and 1"));
+    }
+    Ok(level)
+}
+
+/// Hues (0 to 1) evenly dividing the arc from start to end, wrapping around 1 if end < start.
+///
+/// The end is excluded as it is for the full circle, and the offset rotates within the arc.
+fn arc(resolution: u16, start: f64, end: f64, offset: f64) -> Vec<f64> {
+    let length = if end - start >= 1.0 {
+        1.0
+    } else {
+        (end - start).rem_euclid(1.0)
+    };
+    if resolution == 0 {
+        return Vec::new();
+    }
+    if length == 0.0 {
+        return vec![start % 1.0];
+    }
+    let step = length / f64::from(resolution);
+    (0..resolution)
+        .map(|i| (start + (f64::from(i) * step + offset).rem_euclid(length)) % 1.0)
+        .collect()
+}
+
+/// Escape sequence setting the background of a swatch at the color depth.
+fn bg(args: &Args, color: Rgb) -> ColorSequence {
+    color.bg_seq().depth(args.swatch_depth())
+}
+
+/// Escape sequence setting the text color of a swatch at the color depth.
+fn fg(args: &Args, color: Rgb) -> ColorSequence {
+    color.fg_seq().depth(args.swatch_depth())
+}
+
+/// Color of text shown on a color.
+fn text_color(args: &Args, color: Rgb) -> Rgb {
+    if let TextColor::Fixed(text) = args.text_color {
+        return text;
+    }
+    match args.contrast {
+        Contrast::Heuristic => {
+            let luminosity = args.luminance.of(color);
+            let dark = args.dark / 100.0;
+            let fgv = if luminosity > dark {
+                (1.0 - luminosity).powf(args.dark_factor) // bright color, dark text
+            } else {
+                // dark color, bright text (never as dark as black itself)
+                luminosity.powf(1.0 / args.dark_factor).max(MIN_BRIGHT_TEXT)
+            };
+            args.text_dark.lerp(args.text_bright, fgv)
+        }
+        Contrast::Wcag => {
+            let (dark, bright) = (args.text_dark, args.text_bright);
+            if color.contrast_ratio(dark) >= color.contrast_ratio(bright) {
+                dark
+            } else {
+                bright
+            }
+        }
+    }
+}
+
+/// A color as one table cell (or a pair when previewing both), with its own reset.
+fn cell(args: &Args, color: Rgb) -> String {
+    match args.preview {
+        Preview::Bg => swatch_cell(args, color),
+        Preview::Fg => text_cell(args, color),
+        Preview::Both => swatch_cell(args, color) + &text_cell(args, color),
+    }
+}
+
+/// A color as text on the default background, with its own reset.
+fn text_cell(args: &Args, color: Rgb) -> String {
+    let foreground = match args.display {
+        DisplayOptions::Ansi => color.fg_seq().depth(Depth::Ansi256),
+        _ => fg(args, color),
+    };
+    let text = match cell_text(args, color) {
+        text if text.trim().is_empty() => PREVIEW_TEXT.to_owned(),
+        text => text,
+    };
+    paint(args, foreground, &fit(&text, args.cell_width()))
+}
+
+/// A color as a background with contrasting text, with its own reset.
+fn swatch_cell(args: &Args, color: Rgb) -> String {
+    let background = match args.display {
+        DisplayOptions::Ansi => color.bg_seq().depth(Depth::Ansi256),
+        _ => bg(args, color),
+    };
+    let foreground = fg(args, text_color(args, color));
+    let text = cell_text(args, color);
+    paint(
+        args,
+        format_args!("{background}{foreground}"),
+        &fit(&text, args.cell_width()),
+    )
+}
+
+/// A color as one table cell of the nearest palette grey, showing its code.
+fn grey_cell(args: &Args, color: Rgb) -> String {
+    let code = color.nearest_grey_code();
+    let foreground = fg(args, text_color(args, color));
+    paint(
+        args,
+        format_args!("\x1b[48;5;{code}m{foreground}"),
+        &fit(&format!("{code:^6}"), args.cell_width()),
+    )
+}
+
+/// Text shown in a cell of a color.
+fn cell_text(args: &Args, color: Rgb) -> String {
+    match args.display {
+        DisplayOptions::Ansi => format!("{:^6}", color.nearest_ansi_code()),
+        DisplayOptions::Rgb => format!("{color:X}"),
+        DisplayOptions::Lum => {
+            let luminosity = args.luminance.of(color);
+            format!("{:>3}%", (luminosity * 100.0).round())
+        }
+        DisplayOptions::Lab => {
+            let [l, a, b] = color.lab();
+            format!("{:>3} {:>4} {:>4}", l.round(), a.round(), b.round())
+        }
+        DisplayOptions::Contrast => {
+            format!("{:.1}:1", color.contrast_ratio(text_color(args, color)))
+        }
+        DisplayOptions::Name => {
+            let name = names::nearest_named(color);
+            // Leave a margin around names, which have no fixed length
+            let room = args.cell_width().saturating_sub(2).max(1);
+            name.chars().take(room).collect()
+        }
+        DisplayOptions::Hsv => {
+            let (h, s, v) = color.to_hsv();
+            let hue = if s == 0.0 {
+                "-".to_owned()
+            } else {
+                (h * 360.0).round().to_string()
+            };
+            let (s, v) = ((s * 100.0).round(), (v * 100.0).round());
+            format!("{hue:>3} {s:>3} {v:>3}")
+        }
+        DisplayOptions::None => String::new(),
+    }
+}
+
+/// A color as a JSON object of its notations.
+fn json(color: Rgb) -> String {
+    let Rgb(r, g, b) = color;
+    let (h, s, v) = color.to_hsv();
+    format!(
+        "{{\"hex\":\"#{color:X}\",\"r\":{r},\"g\":{g},\"b\":{b},\"h\":{:.1},\"s\":{:.4},\"v\":{:.4},\"luminosity\":{:.4},\"ansi256\":{}}}",
+        h * 360.0,
+        s,
+        v,
+        color.luminosity(),
+        color.nearest_ansi_code(),
+    )
+}
+
+/// Information card lines of a color: a large swatch, the components, and readable text
+/// colors.
+fn card(args: &Args, color: Rgb) -> Vec<String> {
+    let swatch = paint(args, bg(args, color), &" ".repeat(CARD_WIDTH));
+    let Rgb(r, g, b) = color;
+    let (h, s, v) = color.to_hsv();
+    let (_, sl, l) = color.to_hsl();
+    let percent = |x: f64| (x * 100.0).round();
+    let ansi = color.nearest_ansi_code();
+    let info = [
+        format!("hex         #{color:X}"),
+        format!("rgb         {r}, {g}, {b}"),
+        format!(
+            "hsv         {}°, {}%, {}%",
+            (h * 360.0).round(),
+            percent(s),
+            percent(v)
+        ),
+        format!(
+            "hsl         {}°, {}%, {}%",
+            (h * 360.0).round(),
+            percent(sl),
+            percent(l)
+        ),
+        format!("luminosity  {}%", percent(color.luminosity())),
+        format!(
+            "ansi 256    {ansi:<4}{}{}",
+            paint(args, format_args!("\x1b[48;5;{ansi}m"), "    "),
+            paint(args, color.bg_seq(), "    "),
+        ),
+    ];
+    let swatches = if args.plain { 0 } else { 2 };
+    let mut lines: Vec<String> = std::iter::repeat_n(swatch, swatches).chain(info).collect();
+    let readable = [
+        ("black", Rgb(0, 0, 0)),
+        ("white", Rgb(255, 255, 255)),
+        ("table", text_color(args, color)),
+    ];
+    let mut samples = String::from("readable   ");
+    for (name, text) in readable {
+        let ratio = color.contrast_ratio(text);
+        let sample = format!(" {name} {ratio:.1}:1 ");
+        let codes = format!("{}{}", bg(args, color), fg(args, text));
+        samples.push_str(&format!(" {}", paint(args, &codes, &sample)));
+    }
+    lines.push(samples);
+    lines.iter_mut().for_each(|line| line.push('\n'));
+    lines
+}
+
+const OKLCH_LIGHTNESS: f64 = 0.75;
+const OKLCH_CHROMA: f64 = 0.15;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(argv: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("colortable").chain(argv.iter().copied()))
+            .unwrap_or_else(|error| panic!("{argv:?}: {error}"))
+    }
+
+    fn grid(argv: &[&str]) -> Grid {
+        Grid::new(&args(argv))
+    }
+
+    fn hexes(colors: &[Rgb]) -> Vec<String> {
+        colors.iter().map(|color| format!("{color:X}")).collect()
+    }
+
+    #[test]
+    fn hsl_lightens_to_white() {
+        for resolution in ["1", "3", "8"] {
+            let grid = grid(&["-m", "hsl", "-H", "3", "-V", "2", "-S", resolution]);
+            for (_, colors) in &grid.rows {
+                assert_eq!(format!("{:X}", colors.last().unwrap()), "FFFFFF");
+            }
+        }
+        let grid = grid(&["-m", "hsl", "-H", "1", "-V", "3", "-S", "3"]);
+        assert_eq!(
+            hexes(&grid.rows[0].1),
+            ["400000", "800000", "BF0000", "FF0000", "FF5555", "FFAAAA", "FFFFFF"]
+        );
+    }
+
+    #[test]
+    fn hsv_keeps_the_hue_in_every_column() {
+        let grid = grid(&["-H", "1", "-V", "1", "-S", "1"]);
+        assert_eq!(hexes(&grid.rows[0].1), ["800000", "FF0000", "FF8080"]);
+    }
+
+    #[test]
+    fn negative_offsets_wrap_around_the_hue_circle() {
+        assert_eq!(arc(4, 0.0, 1.0, -0.25), [0.75, 0.0, 0.25, 0.5]);
+        let grid = grid(&["-H", "4", "-V", "1", "-S", "1", "-o", "-90"]);
+        let pure: Vec<Rgb> = grid.rows.iter().map(|(_, colors)| colors[1]).collect();
+        assert_eq!(hexes(&pure), ["8000FF", "FF0000", "80FF00", "00FFFF"]);
+    }
+
+    #[test]
+    fn linear_luminance_darkens_mid_grey() {
+        let (legacy, linear) = (
+            args(&["-d", "lum"]),
+            args(&["-d", "lum", "--luminance", "linear"]),
+        );
+        let grey = Rgb(128, 128, 128);
+        assert_eq!(cell_text(&legacy, grey).trim(), "50%");
+        assert_eq!(cell_text(&linear, grey).trim(), "22%");
+        // Legacy puts mid grey above the dark threshold, so its text is dark
+        assert!(text_color(&legacy, grey).luminosity() < 0.1);
+        assert!(text_color(&linear, grey).luminosity() > 0.6);
+        for primary in [Rgb(255, 0, 0), Rgb(0, 255, 0), Rgb(0, 0, 255)] {
+            assert_eq!(cell_text(&legacy, primary), cell_text(&linear, primary));
+        }
+    }
+
+    #[test]
+    fn gamma_spaces_the_levels() {
+        let close = |levels: &[f64], expected: &[f64]| {
+            levels.len() == expected.len()
+                && levels
+                    .iter()
+                    .zip(expected)
+                    .all(|(a, b)| (a - b).abs() < 1e-9)
+        };
+        let linear = range(5, 1, 1, 0.0, 1.0);
+        assert!(close(&linear, &[0.25, 0.5, 0.75]), "{linear:?}");
+        let dark = range(5, 1, 1, 0.0, 2.0);
+        assert!(close(&dark, &[0.0625, 0.25, 0.5625]), "{dark:?}");
+        let light = range(5, 1, 1, 0.0, 0.5);
+        assert!(
+            close(&light, &[0.5, 0.5f64.sqrt(), 0.75f64.sqrt()]),
+            "{light:?}"
+        );
+
+        let grid = grid(&[
+            "-V",
+            "3",
+            "-S",
+            "2",
+            "--value-gamma",
+            "2",
+            "--saturation-gamma",
+            "0.5",
+        ]);
+        assert!(close(&grid.values, &dark), "{:?}", grid.values);
+        let saturations = [(2.0f64 / 3.0).sqrt(), (1.0f64 / 3.0).sqrt()];
+        assert!(
+            close(&grid.saturations, &saturations),
+            "{:?}",
+            grid.saturations
+        );
+        // The legend shows the levels as spaced
+        let labels: Vec<String> = (grid.values.iter().map(|v| Model::Hsv.dark_label(*v)))
+            .chain(grid.saturations.iter().map(|s| Model::Hsv.light_label(*s)))
+            .collect();
+        assert_eq!(labels, ["  6% v", " 25% v", " 56% v", " 82% s", " 58% s"]);
+    }
+
+    /// Output of render to a buffer.
+    fn rendered(args: &Args) -> String {
+        let mut out = Vec::new();
+        render(args, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn builder_matches_the_command_line() {
+        let built = Args::new()
+            .hues(3)
+            .values(1)
+            .saturations(2)
+            .hue_range(90.0, 270.0)
+            .model(Model::Hsl);
+        let parsed = args(&[
+            "-H",
+            "3",
+            "-V",
+            "1",
+            "-S",
+            "2",
+            "--hue-min",
+            "90",
+            "--hue-max",
+            "270",
+            "-m",
+            "hsl",
+        ]);
+        assert_eq!(Grid::new(&built), Grid::new(&parsed));
+        assert_eq!(Grid::new(&Args::new()), Grid::new(&args(&[])));
+    }
+
+    #[test]
+    fn grid_rows_and_levels() {
+        let grid = Grid::new(&Args::new().hues(2).values(1).saturations(1));
+        assert_eq!(grid.values(), [0.5]);
+        assert_eq!(grid.saturations(), [0.5]);
+        assert_eq!(grid.columns(), 3);
+        assert_eq!(grid.column(0), (1.0, 0.5));
+        assert_eq!(grid.column(2), (0.5, 1.0));
+        let keys: Vec<RowKey> = grid.rows().iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, [RowKey::Hue(0.0), RowKey::Hue(0.5)]);
+        assert_eq!(hexes(&grid.rows()[1].1), ["008080", "00FFFF", "80FFFF"]);
+        let kelvin = Grid::new(
+            &Args::new()
+                .kelvin(1000.0, 6500.0)
+                .hues(2)
+                .values(0)
+                .saturations(0),
+        );
+        let (key, colors) = &kelvin.rows()[0];
+        assert_eq!((key.name(), key.value()), ("kelvin", 1000.0));
+        assert_eq!(hexes(colors), ["FF4400"]);
+    }
+
+    #[test]
+    fn render_writes_the_asked_output() {
+        let args = Args::new().hues(2).values(0).saturations(0);
+        assert_eq!(
+            rendered(&args.clone().plain(true)),
+            " FF0000  \n 00FFFF  \n"
+        );
+        assert_eq!(
+            rendered(&args.clone().depth(Depth::Ansi16)),
+            "\x1b[101m\x1b[37m FF0000  \x1b[m\n\x1b[106m\x1b[30m 00FFFF  \x1b[m\n"
+        );
+        assert_eq!(
+            rendered(&args.clone().legend(true).transpose(true).plain(true)),
+            "    0       180    hue\n FF0000   00FFFF   val/sat\n"
+        );
+        assert_eq!(
+            rendered(&args.clone().format(Format::Csv)),
+            concat!(
+                "hue_deg,saturation,value,hex,r,g,b,luminosity,ansi256\n",
+                "0,1,1,#FF0000,255,0,0,0.2126,196\n",
+                "180,1,1,#00FFFF,0,255,255,0.7874,51\n",
+            )
+        );
+        let error = render(&args.hue_range(0.0, 400.0), Vec::new()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "hue 400 is not between 0 and 360 degrees"
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use crate::{
+    ansi::{self, Kind},
+    color::Depth,
+};
+use std::{io::IsTerminal, time::Duration};
+
+/// Enable processing of ANSI escape sequences for stdout.
+///
+/// Returns false if stdout is a console that does not support escape sequences.
+pub fn enable_vt_processing() -> bool {
+    #[cfg(windows)]
+    {
+        windows::enable_vt_processing()
+    }
+    #[cfg(not(windows))]
+    {
+        true
+    }
+}
+
+/// Width of the terminal attached to stdout, in columns.
+pub fn width() -> Option<usize> {
+    terminal_size::terminal_size_of(std::io::stdout()).map(|(w, _)| usize::from(w.0))
+}
+
+/// Size of the terminal attached to stdout, in columns and rows.
+pub fn size() -> Option<(usize, usize)> {
+    terminal_size::terminal_size_of(std::io::stdout())
+        .map(|(w, h)| (usize::from(w.0), usize::from(h.0)))
+}
+
+/// What the terminal attached to stdout supports.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct TerminalCapabilities {
+    /// Stdout is a terminal
+    pub tty: bool,
+    /// Basic 16 colors
+    pub color: bool,
+    /// Bright foreground and background codes (90 to 97 and 100 to 107), rather than bold
+    pub bright: bool,
+    /// 256-color palette
+    pub ansi256: bool,
+    /// 24-bit color
+    pub truecolor: bool,
+    /// OSC 8 hyperlinks
+    pub hyperlinks: bool,
+    /// Sixel graphics
+    pub sixel: bool,
+    /// OSC 52 clipboard access
+    pub clipboard: bool,
+}
+
+impl TerminalCapabilities {
+    /// Detect capabilities from the environment (COLORTERM, TERM, and terminal-specific variables).
+    pub fn detect() -> Self {
+        Self::from_env(
+            |name| std::env::var(name).ok(),
+            std::io::stdout().is_terminal(),
+        )
+    }
+
+    /// Detect capabilities from the environment, then confirm truecolor and sixel support by
+    /// querying the controlling terminal.
+    ///
+    /// Queries are skipped if there is no controlling terminal or the process is in the
+    /// background, and give up after the timeout if the terminal does not answer.
+    pub fn detect_with_queries(timeout: Duration) -> Self {
+        let mut capabilities = Self::detect();
+        if let Some(response) = query(QUERY, timeout) {
+            let mut answered_truecolor = false;
+            for token in ansi::tokens(&response) {
+                match token.kind {
+                    Kind::ControlString {
+                        introducer: b'P',
+                        data,
+                    } => {
+                        let data = String::from_utf8_lossy(data);
+                        answered_truecolor = true;
+                        capabilities.truecolor |= data.starts_with("1$r")
+                            && (data.contains("2:1:2:3") || data.contains("2;1;2;3"));
+                    }
+                    Kind::Csi {
+                        params,
+                        final_byte: b'c',
+                        ..
+                    } if params.first() == Some(&b'?') => {
+                        let params = String::from_utf8_lossy(&params[1..]);
+                        capabilities.sixel |= params.split(';').any(|p| p == "4");
+                    }
+                    _ => {}
+                }
+            }
+            if answered_truecolor {
+                capabilities.ansi256 |= capabilities.truecolor;
+                capabilities.bright |= capabilities.truecolor;
+                capabilities.color |= capabilities.truecolor;
+            }
+        }
+        capabilities
+    }
+
+    /// Detect capabilities from environment variables given by a lookup function.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>, tty: bool) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let has = |name: &str| var(name).is_some_and(|value| !value.is_empty());
+        let kitty = has("KITTY_WINDOW_ID") || term == "xterm-kitty";
+        let windows_terminal = has("WT_SESSION");
+        let modern_program = matches!(program.as_str(), "iTerm.app" | "WezTerm" | "vscode");
+        let vte = var("VTE_VERSION")
+            .and_then(|version| version.parse::<u32>().ok())
+            .unwrap_or(0);
+
+        let truecolor = matches!(colorterm.as_str(), "truecolor" | "24bit")
+            || term.ends_with("-direct")
+            || kitty
+            || windows_terminal
+            || modern_program;
+        let ansi256 = truecolor || term.contains("256color");
+        let color = ansi256 || (!term.is_empty() && term != "dumb") || cfg!(windows);
+        // The Linux console and 8-color terminals show bright codes as normal colors
+        let bright = ansi256 || term.contains("16color");
+        let hyperlinks = kitty
+            || windows_terminal
+            || modern_program
+            || vte >= 5000
+            || term.starts_with("foot")
+            || term.starts_with("alacritty")
+            || has("DOMTERM");
+        let sixel = term.contains("sixel") || term.starts_with("foot") || program == "WezTerm";
+        let clipboard = kitty
+            || windows_terminal
+            || matches!(program.as_str(), "iTerm.app" | "WezTerm")
+            || term.starts_with("foot")
+            || term.starts_with("alacritty");
+        Self {
+            tty,
+            color,
+            bright,
+            ansi256,
+            truecolor,
+            hyperlinks,
+            sixel,
+            clipboard,
+        }
+    }
+
+    /// Deepest color depth supported.
+    pub fn depth(&self) -> Depth {
+        if self.truecolor {
+            Depth::Truecolor
+        } else if self.ansi256 {
+            Depth::Ansi256
+        } else {
+            Depth::Ansi16
+        }
+    }
+
+    /// Name and support of each capability.
+    pub fn features(&self) -> [(&'static str, bool); 8] {
+        [
+            ("tty", self.tty),
+            ("color", self.color),
+            ("bright", self.bright),
+            ("ansi256", self.ansi256),
+            ("truecolor", self.truecolor),
+            ("hyperlinks", self.hyperlinks),
+            ("sixel", self.sixel),
+            ("clipboard", self.clipboard),
+        ]
+    }
+
+    /// Capabilities as a JSON object.
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .features()
+            .iter()
+            .map(|(name, supported)| format!("\"{name}\":{supported}"))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+// Set a truecolor background and request it back (DECRQSS), reset, then request primary device
+// attributes (DA1), which every terminal answers and so marks the end of the response.
+const QUERY: &[u8] = b"\x1b[48;2;1;2;3m\x1bP$qm\x1b\\\x1b[m\x1b[c";
+
+/// Write a query to the controlling terminal and read the response, until a DA1 response arrives or
+/// the timeout expires.
+#[cfg(unix)]
+fn query(request: &[u8], timeout: Duration) -> Option<Vec<u8>> {
+    unix::query(request, timeout)
+}
+
+#[cfg(not(unix))]
+fn query(_request: &[u8], _timeout: Duration) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(unix)]
+fn is_da1_response(response: &[u8]) -> bool {
+    ansi::tokens(response).any(|token| {
+        matches!(
+            token.kind,
+            Kind::Csi {
+                params: [b'?', ..],
+                final_byte: b'c',
+                ..
+            }
+        )
+    })
+}
+
+/// Key read from a terminal in raw mode.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Escape,
+    /// Ctrl-C, which is read as a key in raw mode instead of interrupting
+    Interrupt,
+    Char(char),
+}
+
+/// Decode the keys in bytes read from a terminal, skipping sequences that are not keys.
+///
+/// A lone ESC is the escape key, since the terminal sends each sequence in a single write.
+pub fn decode_keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut rest = input;
+    while let Some((&first, tail)) = rest.split_first() {
+        let (key, len) = match (first, tail) {
+            // CSI or SS3 (application cursor keys): parameters then the final byte
+            (b'\x1b', [b'[' | b'O', body @ ..]) => {
+                let params = body
+                    .iter()
+                    .take_while(|b| (0x30..=0x3f).contains(*b))
+                    .count();
+                let key = match body.get(params) {
+                    Some(b'A') => Some(Key::Up),
+                    Some(b'B') => Some(Key::Down),
+                    Some(b'C') => Some(Key::Right),
+                    Some(b'D') => Some(Key::Left),
+                    _ => None,
+                };
+                (key, (2 + params + 1).min(rest.len()))
+            }
+            (b'\x1b', _) => (Some(Key::Escape), 1),
+            (b'\r' | b'\n', _) => (Some(Key::Enter), 1),
+            (0x03, _) => (Some(Key::Interrupt), 1),
+            (byte, _) if byte.is_ascii_graphic() || byte == b' ' => {
+                (Some(Key::Char(char::from(byte))), 1)
+            }
+            _ => (None, 1),
+        };
+        keys.extend(key);
+        rest = &rest[len..];
+    }
+    keys
+}
+
+/// OSC 52 sequence setting the clipboard to the text, which also works over ssh.
+pub fn clipboard_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// OSC 8 sequence starting a hyperlink, with an id to group its parts (e.g. across lines).
+pub fn hyperlink_start(url: &str, id: Option<&str>) -> String {
+    match id {
+        Some(id) => format!("\x1b]8;id={id};{url}\x1b\\"),
+        None => format!("\x1b]8;;{url}\x1b\\"),
+    }
+}
+
+/// OSC 8 sequence ending a hyperlink.
+pub const HYPERLINK_END: &str = "\x1b]8;;\x1b\\";
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize],
+                ));
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+#[cfg(unix)]
+pub use unix::RawTerminal;
+
+/// Platform newline.
+pub const fn newline() -> &'static str {
+    if cfg!(windows) {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+
+    type Handle = *mut c_void;
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> Handle;
+        fn GetConsoleMode(console_handle: Handle, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console_handle: Handle, mode: u32) -> i32;
+    }
+
+    pub fn enable_vt_processing() -> bool {
+        // SAFETY: the handle is only passed to console functions which validate it.
+        unsafe {
+            let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+            if handle.is_null() || handle as isize == -1 {
+                return true;
+            }
+            let mut mode = 0;
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                // Not a console (pipe, file, or a pty like mintty), escapes pass through as-is
+                return true;
+            }
+            if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+                return true;
+            }
+            SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::Key;
+    use std::{
+        fs::{File, OpenOptions},
+        io::{self, Read, Write},
+        os::fd::{AsRawFd, RawFd},
+        time::{Duration, Instant},
+    };
+
+    /// Disables echo and line buffering (and optionally signal keys) until dropped.
+    struct RawMode {
+        fd: RawFd,
+        original: libc::termios,
+    }
+
+    impl RawMode {
+        fn enable(fd: RawFd, signals: bool) -> Option<Self> {
+            // SAFETY: termios is plain data, filled in by tcgetattr before use.
+            unsafe {
+                let mut original: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(fd, &mut original) != 0 {
+                    return None;
+                }
+                let mut raw = original;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+                if !signals {
+                    raw.c_lflag &= !libc::ISIG;
+                }
+                raw.c_cc[libc::VMIN] = 0;
+                raw.c_cc[libc::VTIME] = 0;
+                if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+                    return None;
+                }
+                Some(Self { fd, original })
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: restores the attributes read in enable.
+            unsafe {
+                libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+            }
+        }
+    }
+
+    /// The controlling terminal in raw mode, for interactive programs.
+    ///
+    /// Keys are read without echo, and Ctrl-C is read as [`Key::Interrupt`] instead of
+    /// interrupting. The original mode is restored when dropped, including during a panic.
+    pub struct RawTerminal {
+        // Dropped first, while the terminal is still open
+        _raw_mode: RawMode,
+        tty: File,
+    }
+
+    impl RawTerminal {
+        /// Open the controlling terminal and enable raw mode.
+        pub fn open() -> io::Result<Self> {
+            let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+            let raw_mode =
+                RawMode::enable(tty.as_raw_fd(), false).ok_or_else(io::Error::last_os_error)?;
+            Ok(Self {
+                _raw_mode: raw_mode,
+                tty,
+            })
+        }
+
+        /// Wait for and read the next keys.
+        pub fn read_keys(&mut self) -> io::Result<Vec<Key>> {
+            let mut buffer = [0; 64];
+            loop {
+                let mut poll = libc::pollfd {
+                    fd: self.tty.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                // SAFETY: poll is given exactly one valid pollfd.
+                if unsafe { libc::poll(&mut poll, 1, -1) } == -1 {
+                    let error = io::Error::last_os_error();
+                    if error.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(error);
+                }
+                match self.tty.read(&mut buffer)? {
+                    0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    n => {
+                        let keys = super::decode_keys(&buffer[..n]);
+                        if !keys.is_empty() {
+                            return Ok(keys);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    impl Write for RawTerminal {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.tty.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.tty.flush()
+        }
+    }
+
+    pub fn query(request: &[u8], timeout: Duration) -> Option<Vec<u8>> {
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .ok()?;
+        let fd = tty.as_raw_fd();
+        // SAFETY: fd is a valid open file descriptor.
+        unsafe {
+            // Reading from the terminal in a background process group would stop the process
+            if libc::isatty(fd) != 1 || libc::tcgetpgrp(fd) != libc::getpgrp() {
+                return None;
+            }
+        }
+        let _raw_mode = RawMode::enable(fd, true)?;
+        tty.write_all(request).ok()?;
+        tty.flush().ok()?;
+        let deadline = Instant::now() + timeout;
+        let mut response = Vec::new();
+        let mut buffer = [0; 256];
+        while !super::is_da1_response(&response) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            let mut poll = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let millis = i32::try_from(remaining.as_millis())
+                .unwrap_or(i32::MAX)
+                .max(1);
+            // SAFETY: poll is given exactly one valid pollfd.
+            if unsafe { libc::poll(&mut poll, 1, millis) } <= 0 {
+                break;
+            }
+            match tty.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => response.extend_from_slice(&buffer[..n]),
+            }
+        }
+        Some(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn escapes_are_always_processed_outside_windows() {
+        assert!(enable_vt_processing());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn enabling_escape_processing_is_idempotent() {
+        let enabled = enable_vt_processing();
+        assert_eq!(enable_vt_processing(), enabled);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn redirected_stdout_processes_escapes() {
+        // The test harness captures output, so stdout is not a console unless --nocapture
+        if !std::io::stdout().is_terminal() {
+            assert!(enable_vt_processing());
+        }
+    }
+
+    #[test]
+    fn bright_colors_need_a_16_color_hint() {
+        let bright = |vars: &[(&str, &str)]| {
+            let var = |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| (*value).to_owned())
+            };
+            TerminalCapabilities::from_env(var, true).bright
+        };
+        assert!(!bright(&[("TERM", "linux")]));
+        assert!(!bright(&[("TERM", "xterm")]));
+        assert!(!bright(&[]));
+        assert!(bright(&[("TERM", "xterm-16color")]));
+        assert!(bright(&[("TERM", "xterm-256color")]));
+        assert!(bright(&[("TERM", "xterm"), ("COLORTERM", "truecolor")]));
+        assert!(bright(&[("TERM", "xterm-kitty")]));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::ansi;
+
+/// Wide (two column) ranges: East Asian wide and fullwidth characters, and emoji.
+const WIDE: [(u32, u32); 46] = [
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f5),
+    (0x26fa, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274e),
+    (0x2753, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x18aff),
+    (0x1b000, 0x1b2ff),
+    (0x1f300, 0x1f64f),
+    (0x1f680, 0x1f6ff),
+    (0x1f900, 0x1faff),
+    (0x20000, 0x3fffd),
+];
+
+/// Zero width ranges: combining marks, zero width spaces and joiners, and variation selectors.
+const ZERO: [(u32, u32); 14] = [
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x2060, 0x2064),
+    (0x20d0, 0x20ff),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0xe0100, 0xe01ef),
+];
+
+fn in_ranges(ranges: &[(u32, u32)], c: char) -> bool {
+    let c = u32::from(c);
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Columns a character takes in the terminal: 0 for control characters and combining marks,
+/// 2 for wide characters, and 1 otherwise.
+pub fn char_width(c: char) -> usize {
+    if c.is_control() || in_ranges(&ZERO, c) {
+        0
+    } else if in_ranges(&WIDE, c) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Columns the text takes in the terminal, not counting escape sequences.
+pub fn width(text: &str) -> usize {
+    ansi::tokens(text.as_bytes())
+        .filter(|token| !token.is_escape())
+        .map(|token| {
+            String::from_utf8_lossy(token.raw)
+                .chars()
+                .map(char_width)
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_are_sorted_for_the_binary_search() {
+        for ranges in [&WIDE[..], &ZERO[..]] {
+            assert!(ranges.iter().all(|(start, end)| start <= end));
+            assert!(ranges.windows(2).all(|pair| pair[0].1 < pair[1].0));
+        }
+    }
+
+    #[test]
+    fn characters_take_zero_one_or_two_columns() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('\t'), 0);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('\u{200d}'), 0);
+        assert_eq!(char_width('漢'), 2);
+        assert_eq!(char_width('🎉'), 2);
+    }
+
+    #[test]
+    fn escape_sequences_take_no_columns() {
+        assert_eq!(width(""), 0);
+        assert_eq!(width("e\u{301}te"), 3);
+        assert_eq!(width("\x1b[1;31m漢字\x1b[m!"), 5);
+        assert_eq!(
+            width("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            4
+        );
+    }
+}
@@ -0,0 +1,117 @@
+/// Kind of change for a word.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChangeKind {
+    Equal,
+    Insert,
+    Remove,
+}
+
+/// A word with its leading whitespace, as it appears in the old or new text.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub kind: ChangeKind,
+    pub whitespace: &'a str,
+    pub word: &'a str,
+}
+
+/// Diff two texts by whitespace-separated words using the longest common subsequence.
+///
+/// Equal and inserted words carry the whitespace of the new text, so concatenating every
+/// non-removed change reproduces the new text exactly.
+pub fn word_diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let (old_tokens, _) = tokens(old);
+    let (new_tokens, trailing) = tokens(new);
+    let (n, m) = (old_tokens.len(), new_tokens.len());
+    // lcs[i][j] is the LCS length of old_tokens[i..] and new_tokens[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_tokens[i].1 == new_tokens[j].1 {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let change = |kind, (whitespace, word)| Change {
+        kind,
+        whitespace,
+        word,
+    };
+    let mut changes = Vec::with_capacity(n.max(m) + 1);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_tokens[i].1 == new_tokens[j].1 {
+            changes.push(change(ChangeKind::Equal, new_tokens[j]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(change(ChangeKind::Remove, old_tokens[i]));
+            i += 1;
+        } else {
+            changes.push(change(ChangeKind::Insert, new_tokens[j]));
+            j += 1;
+        }
+    }
+    if !trailing.is_empty() {
+        changes.push(change(ChangeKind::Equal, (trailing, "")));
+    }
+    changes
+}
+
+/// Split text into (leading whitespace, word) pairs and the trailing whitespace.
+fn tokens(text: &str) -> (Vec<(&str, &str)>, &str) {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        let word_start = rest.len() - rest.trim_start().len();
+        if word_start == rest.len() {
+            return (tokens, rest);
+        }
+        let (whitespace, word) = rest.split_at(word_start);
+        let word_end = word.find(char::is_whitespace).unwrap_or(word.len());
+        tokens.push((whitespace, &word[..word_end]));
+        rest = &word[word_end..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(old: &'a str, new: &'a str) -> Vec<(ChangeKind, &'a str)> {
+        word_diff(old, new)
+            .into_iter()
+            .map(|change| (change.kind, change.word))
+            .collect()
+    }
+
+    #[test]
+    fn words_are_inserted_and_removed() {
+        use ChangeKind::*;
+        assert_eq!(
+            kinds("the quick fox", "the slow fox jumps"),
+            [
+                (Equal, "the"),
+                (Remove, "quick"),
+                (Insert, "slow"),
+                (Equal, "fox"),
+                (Insert, "jumps")
+            ]
+        );
+        assert_eq!(kinds("", "a"), [(Insert, "a")]);
+        assert_eq!(kinds("a", ""), [(Remove, "a")]);
+        assert_eq!(kinds("a b", "a b"), [(Equal, "a"), (Equal, "b")]);
+    }
+
+    #[test]
+    fn kept_changes_reproduce_the_new_text() {
+        let new = "  the slow\tfox  jumps \n";
+        let text: String = word_diff("the quick fox", new)
+            .iter()
+            .filter(|change| change.kind != ChangeKind::Remove)
+            .map(|change| format!("{}{}", change.whitespace, change.word))
+            .collect();
+        assert_eq!(text, new);
+    }
+}
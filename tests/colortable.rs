@@ -0,0 +1,461 @@
+//! Golden output of the colortable binary.
+use std::process::Command;
+
+/// Output to a pipe of a truecolor terminal, plain unless asked for escape sequences.
+fn piped(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_colortable"))
+        .args(args)
+        .env("COLORTERM", "truecolor")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{args:?}: {}", output.status);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Output with escape sequences, as shown in a terminal.
+fn colortable(args: &[&str]) -> String {
+    piped(&[&["--force-ansi"], args].concat())
+}
+
+fn colortable_error(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_colortable"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{args:?} succeeded");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn gradient_in_each_space() {
+    let gradient = |space| {
+        let args = [
+            "--gradient",
+            "ff0000",
+            "0000ff",
+            "--steps",
+            "3",
+            "-l",
+            "-d",
+            "none",
+        ];
+        colortable(&[&args[..], &["--space", space]].concat())
+    };
+    assert_eq!(gradient("rgb"), "#FF0000\n#800080\n#0000FF\n");
+    // The shorter way around the hue circle from red to blue passes magenta
+    assert_eq!(gradient("hsv"), "#FF0000\n#FF00FF\n#0000FF\n");
+    assert_eq!(gradient("oklab"), "#FF0000\n#8C53A2\n#0000FF\n");
+}
+
+#[test]
+fn gradient_cells_and_legend() {
+    assert_eq!(
+        colortable(&["--gradient", "ff0000", "0000ff", "--steps", "2"]),
+        concat!(
+            "\x1b[48;2;255;0;0m\x1b[38;2;187;187;187m FF0000  \x1b[m\n",
+            "\x1b[48;2;0;0;255m\x1b[38;2;151;151;151m 0000FF  \x1b[m\n",
+        )
+    );
+    assert_eq!(
+        colortable(&["--gradient", "ff0000", "0000ff", "--steps", "1", "-l"]),
+        "\x1b[48;2;255;0;0m\x1b[38;2;187;187;187m FF0000  \x1b[m #FF0000\n"
+    );
+}
+
+#[test]
+fn pick_card() {
+    assert_eq!(
+        colortable(&["--pick", "ff8000"]),
+        concat!(
+            "\x1b[48;2;255;128;0m                                \x1b[m\n",
+            "\x1b[48;2;255;128;0m                                \x1b[m\n",
+            "hex         #FF8000\n",
+            "rgb         255, 128, 0\n",
+            "hsv         30°, 100%, 100%\n",
+            "hsl         30°, 100%, 50%\n",
+            "luminosity  57%\n",
+            "ansi 256    208 \x1b[48;5;208m    \x1b[m\x1b[48;2;255;128;0m    \x1b[m\n",
+            "readable    \x1b[48;2;255;128;0m\x1b[38;2;0;0;0m black 8.3:1 \x1b[m",
+            " \x1b[48;2;255;128;0m\x1b[38;2;255;255;255m white 2.5:1 \x1b[m",
+            " \x1b[48;2;255;128;0m\x1b[38;2;4;4;4m table 8.1:1 \x1b[m\n",
+        )
+    );
+}
+
+#[test]
+fn pick_accepts_rgb_and_hsv() {
+    let hex = |color| {
+        colortable(&["--pick", color])
+            .lines()
+            .nth(2)
+            .unwrap()
+            .to_owned()
+    };
+    assert_eq!(hex("rgb(255,128,0)"), "hex         #FF8000");
+    assert_eq!(hex("hsv(30, 100, 100)"), "hex         #FF8000");
+    assert!(colortable_error(&["--pick", "rgb(1, 2)"]).contains("expected 3 components"));
+    assert!(colortable_error(&["--pick", "rgb(1, 2, 300)"])
+        .contains("blue must be a number from 0 to 255, found '300' (at byte 10)"));
+}
+
+#[test]
+fn distance_between_two_colors() {
+    assert_eq!(
+        colortable(&["--distance", "000000", "ffffff"]),
+        concat!(
+            "\x1b[48;2;0;0;0m                \x1b[m\x1b[48;2;255;255;255m                \x1b[m\n",
+            "colors        #000000 #FFFFFF\n",
+            "rgb distance  441.67\n",
+            "ΔE 1976       100.00\n",
+            "ΔE 2000       100.00\n",
+        )
+    );
+    let distance = colortable(&["--distance", "ff0000", "ff8000"]);
+    let lines: Vec<&str> = distance.lines().skip(2).collect();
+    assert_eq!(
+        lines,
+        [
+            "rgb distance  128.00",
+            "ΔE 1976       40.32",
+            "ΔE 2000       21.16"
+        ]
+    );
+}
+
+#[test]
+fn table_rows_per_hue() {
+    assert_eq!(
+        colortable(&["-H", "2", "-V", "1", "-S", "1"]),
+        concat!(
+            "\x1b[48;2;128;0;0m\x1b[38;2;163;163;163m 800000  \x1b[m",
+            "\x1b[48;2;255;0;0m\x1b[38;2;187;187;187m FF0000  \x1b[m",
+            "\x1b[48;2;255;128;128m\x1b[38;2;2;2;2m FF8080  \x1b[m\n",
+            "\x1b[48;2;0;128;128m\x1b[38;2;212;212;212m 008080  \x1b[m",
+            "\x1b[48;2;0;255;255m\x1b[38;2;0;0;0m 00FFFF  \x1b[m",
+            "\x1b[48;2;128;255;255m\x1b[38;2;0;0;0m 80FFFF  \x1b[m\n",
+        )
+    );
+}
+
+#[test]
+fn json_document() {
+    assert_eq!(
+        colortable(&["--format", "json", "-H", "2", "-V", "0", "-S", "0"]),
+        concat!(
+            "{\"rows\":[\n",
+            "{\"hue\":0,\"cells\":[{\"hex\":\"#FF0000\",\"r\":255,\"g\":0,\"b\":0,\"h\":0.0,",
+            "\"s\":1.0000,\"v\":1.0000,\"luminosity\":0.2126,\"ansi256\":196}]},\n",
+            "{\"hue\":180,\"cells\":[{\"hex\":\"#00FFFF\",\"r\":0,\"g\":255,\"b\":255,\"h\":180.0,",
+            "\"s\":1.0000,\"v\":1.0000,\"luminosity\":0.7874,\"ansi256\":51}]}\n",
+            "]}\n",
+        )
+    );
+}
+
+#[test]
+fn svg_document_with_legend() {
+    let args = ["--format", "svg", "-H", "2", "-V", "0", "-S", "0"];
+    let text = |x, y, fill, anchor, content| {
+        format!(
+            "<text x=\"{x}\" y=\"{y}\" fill=\"{fill}\" font-family=\"monospace\" font-size=\"2\" \
+             text-anchor=\"{anchor}\" dominant-baseline=\"middle\">{content}</text>\n"
+        )
+    };
+    let expected = [
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"30\" viewBox=\"0 0 30 30\">\n"
+            .to_owned(),
+        text(6, 3, "#808080", "middle", "val/sat"),
+        "<rect x=\"0\" y=\"6\" width=\"12\" height=\"12\" fill=\"#FF0000\"/>\n".to_owned(),
+        text(6, 12, "#BBBBBB", "middle", "FF0000"),
+        text(13, 12, "#808080", "start", "hue: 0"),
+        "<rect x=\"0\" y=\"18\" width=\"12\" height=\"12\" fill=\"#00FFFF\"/>\n".to_owned(),
+        text(6, 24, "#000000", "middle", "00FFFF"),
+        text(13, 24, "#808080", "start", "hue: 180"),
+        "</svg>\n".to_owned(),
+    ];
+    assert_eq!(
+        colortable(&[&args[..], &["--cell-size", "12", "-l"]].concat()),
+        expected.concat()
+    );
+}
+
+#[test]
+fn svg_ansi_fills_with_the_palette_color() {
+    let svg = colortable(&[
+        "--format", "svg", "-H", "1", "-V", "0", "-S", "0", "-d", "ansi", "-o", "30",
+    ]);
+    assert!(svg.contains("fill=\"#FF8700\"/>"), "{svg}");
+    assert!(svg.contains(">208</text>"), "{svg}");
+}
+
+#[test]
+fn sh_assignments_are_named_after_the_column() {
+    assert_eq!(
+        colortable(&["--format", "sh", "-H", "1", "-V", "1", "-S", "0", "--prefix", "X"]),
+        concat!(
+            "X_H0_S100_V50='#800000'\n",
+            "X_H0_S100_V50_FG=$'\\e[38;2;128;0;0m'\n",
+            "X_H0_S100_V50_BG=$'\\e[48;2;128;0;0m'\n",
+            "X_H0_S100_V100='#ff0000'\n",
+            "X_H0_S100_V100_FG=$'\\e[38;2;255;0;0m'\n",
+            "X_H0_S100_V100_BG=$'\\e[48;2;255;0;0m'\n",
+        )
+    );
+    assert!(colortable_error(&["--format", "sh", "--prefix", "1X"])
+        .contains("prefix '1X' is not a valid shell variable name"));
+}
+
+#[test]
+fn kitty_theme() {
+    let theme = colortable(&["--format", "kitty", "-H", "6", "-V", "3"]);
+    let lines: Vec<&str> = theme.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "background #404040",
+            "foreground #bfbfbf",
+            "color0 #404040",
+            "color1 #bf0000",
+            "color2 #00bf00",
+            "color3 #bfbf00",
+            "color4 #0000bf",
+            "color5 #bf00bf",
+            "color6 #00bfbf",
+            "color7 #bfbfbf",
+            "color8 #808080",
+            "color9 #ff0000",
+            "color10 #00ff00",
+            "color11 #ffff00",
+            "color12 #0000ff",
+            "color13 #ff00ff",
+            "color14 #00ffff",
+            "color15 #ffffff",
+        ]
+    );
+    assert!(colortable_error(&["--format", "kitty", "-V", "0"])
+        .contains("theme output needs at least one value level"));
+}
+
+#[test]
+fn alacritty_theme() {
+    assert_eq!(
+        colortable(&["--format", "alacritty", "-H", "6", "-V", "3"]),
+        concat!(
+            "[colors.primary]\n",
+            "background = \"#404040\"\n",
+            "foreground = \"#bfbfbf\"\n",
+            "\n",
+            "[colors.normal]\n",
+            "black = \"#404040\"\n",
+            "red = \"#bf0000\"\n",
+            "green = \"#00bf00\"\n",
+            "yellow = \"#bfbf00\"\n",
+            "blue = \"#0000bf\"\n",
+            "magenta = \"#bf00bf\"\n",
+            "cyan = \"#00bfbf\"\n",
+            "white = \"#bfbfbf\"\n",
+            "\n",
+            "[colors.bright]\n",
+            "black = \"#808080\"\n",
+            "red = \"#ff0000\"\n",
+            "green = \"#00ff00\"\n",
+            "yellow = \"#ffff00\"\n",
+            "blue = \"#0000ff\"\n",
+            "magenta = \"#ff00ff\"\n",
+            "cyan = \"#00ffff\"\n",
+            "white = \"#ffffff\"\n",
+        )
+    );
+}
+
+#[test]
+fn plain_unless_writing_to_a_terminal() {
+    assert_eq!(
+        piped(&["-H", "1", "-V", "1", "-S", "0"]),
+        " 800000   FF0000  \n"
+    );
+    assert_eq!(
+        piped(&["--distance", "000000", "ffffff"]).lines().next(),
+        Some("colors        #000000 #FFFFFF")
+    );
+    let path = std::env::temp_dir().join(format!("colortable-{}.txt", std::process::id()));
+    let output = path.to_str().unwrap();
+    assert_eq!(
+        piped(&["-H", "1", "-V", "1", "-S", "0", "--output", output]),
+        ""
+    );
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, " 800000   FF0000  \n");
+}
+
+#[test]
+fn depth_falls_back_to_palette_colors() {
+    let cell = |depth| colortable(&["-H", "1", "-V", "0", "-S", "0", "--depth", depth]);
+    assert_eq!(cell("256"), "\x1b[48;5;196m\x1b[38;5;250m FF0000  \x1b[m\n");
+    assert_eq!(cell("16"), "\x1b[101m\x1b[37m FF0000  \x1b[m\n");
+    let detected = Command::new(env!("CARGO_BIN_EXE_colortable"))
+        .args(["--force-ansi", "-H", "1", "-V", "0", "-S", "0"])
+        .env_remove("COLORTERM")
+        .env("TERM", "xterm-256color")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(detected.stdout).unwrap(), cell("256"));
+}
+
+#[test]
+fn csv_has_the_generating_parameters() {
+    let args = ["--format", "csv", "-H", "2", "-V", "1", "-S", "1"];
+    let rows = concat!(
+        "0,1,0.5,#800000,128,0,0,0.1067,88\n",
+        "0,1,1,#FF0000,255,0,0,0.2126,196\n",
+        "0,0.5,1,#FF8080,255,128,128,0.6078,210\n",
+        "180,1,0.5,#008080,0,128,128,0.3952,30\n",
+        "180,1,1,#00FFFF,0,255,255,0.7874,51\n",
+        "180,0.5,1,#80FFFF,128,255,255,0.8941,123\n",
+    );
+    assert_eq!(
+        colortable(&args),
+        format!("hue_deg,saturation,value,hex,r,g,b,luminosity,ansi256\n{rows}")
+    );
+    assert_eq!(colortable(&[&args[..], &["--no-header"]].concat()), rows);
+}
+
+#[test]
+fn ansi256_chart_has_every_palette_color() {
+    let chart = colortable(&["--chart", "ansi256", "--depth", "16"]);
+    let lines: Vec<&str> = chart.lines().collect();
+    // Basic colors, six planes of the cube, and the greyscale ramp, with blank lines between
+    assert_eq!(lines.len(), 2 + 6 * 7 + 1 + 3);
+    assert_eq!(
+        lines[0],
+        [
+            "\x1b[48;5;0m\x1b[90m    0    \x1b[m",
+            "\x1b[48;5;1m\x1b[37m    1    \x1b[m",
+            "\x1b[48;5;2m\x1b[30m    2    \x1b[m",
+            "\x1b[48;5;3m\x1b[30m    3    \x1b[m",
+            "\x1b[48;5;4m\x1b[90m    4    \x1b[m",
+            "\x1b[48;5;5m\x1b[37m    5    \x1b[m",
+            "\x1b[48;5;6m\x1b[30m    6    \x1b[m",
+            "\x1b[48;5;7m\x1b[30m    7    \x1b[m",
+        ]
+        .concat()
+    );
+    assert_eq!(lines[2], "");
+    let codes: Vec<u32> = piped(&["--chart", "ansi256"])
+        .split_whitespace()
+        .map(|code| code.parse().unwrap())
+        .collect();
+    assert_eq!(codes, (0..256).collect::<Vec<_>>());
+}
+
+#[test]
+fn flat_list_is_sorted_and_wrapped() {
+    let args = ["--flat", "-H", "3", "-V", "1", "-S", "1"];
+    assert_eq!(
+        piped(&args),
+        " 000080   0000FF   800000   FF0000   008000   8080FF   FF8080   00FF00  \n 80FF80  \n"
+    );
+    assert_eq!(
+        piped(&[&args[..], &["--sort", "hue", "-l"]].concat()),
+        concat!(
+            " 800000   0 #800000  FF0000   1 #FF0000  FF8080   2 #FF8080  008000   3 #008000 \n",
+            " 00FF00   4 #00FF00  80FF80   5 #80FF80  000080   6 #000080  0000FF   7 #0000FF \n",
+            " 8080FF   8 #8080FF \n",
+        )
+    );
+    // Without value and saturation levels, HSL has one row of pure hues
+    assert_eq!(
+        piped(&["--flat", "-H", "2", "-V", "0", "-S", "0", "-m", "hsl"]),
+        " FF0000   00FFFF  \n"
+    );
+}
+
+#[test]
+fn unique_blanks_repeated_codes() {
+    let args = ["-d", "ansi", "-H", "48", "-V", "0", "-S", "0"];
+    let table = piped(&[&args[..], &["--unique"]].concat());
+    assert!(
+        table.starts_with("  196    \n         \n  202    \n         \n"),
+        "{table}"
+    );
+    assert!(table.ends_with("\n30 distinct codes\n"), "{table}");
+    assert_eq!(
+        piped(&[&args[..], &["--unique=collapse"]].concat()),
+        concat!(
+            "  196      202      208      214      220      226      190      154    \n",
+            "  118       82       46       47       48       49       50       51    \n",
+            "   45       39       33       27       21       57       93      129    \n",
+            "  165      201      200      199      198      197    \n",
+            "\n",
+            "30 distinct codes\n",
+        )
+    );
+    assert!(colortable_error(&["--unique"]).contains("--unique needs --display ansi"));
+}
+
+#[test]
+fn matrix16_chart_of_basic_combinations() {
+    let chart = piped(&["--chart", "matrix16", "--mark-low-contrast"]);
+    let lines: Vec<&str> = chart.lines().collect();
+    assert_eq!(lines.len(), 17);
+    assert_eq!(
+        lines[0],
+        "                    k   r   g   y   b   m   c   w   K   R   G   Y   B   M   C   W  "
+    );
+    assert_eq!(
+        lines[1],
+        "black (k)          Aa• Aa•  Aa  Aa Aa• Aa•  Aa  Aa  Aa  Aa  Aa  Aa Aa•  Aa  Aa  Aa "
+    );
+    assert!(lines[16].starts_with("bright-white (W)   "), "{chart}");
+    let colored = colortable(&["--chart", "matrix16"]);
+    let black = colored.lines().nth(1).unwrap();
+    assert!(
+        black.starts_with("black (k)          \x1b[30;40m Aa \x1b[m\x1b[31;40m Aa \x1b[m"),
+        "{black:?}"
+    );
+    assert!(black.ends_with("\x1b[97;40m Aa \x1b[m"), "{black:?}");
+}
+
+#[test]
+fn kelvin_rows_of_color_temperatures() {
+    assert_eq!(
+        piped(&[
+            "--kelvin",
+            "1000:10000",
+            "-H",
+            "3",
+            "-V",
+            "0",
+            "-S",
+            "0",
+            "-l"
+        ]),
+        concat!(
+            " val/sat \n",
+            " FF4400   kelvin: 1000\n",
+            " FFEDDE   kelvin: 5500\n",
+            " CADAFF   kelvin: 10000\n",
+        )
+    );
+    assert_eq!(
+        piped(&[
+            "--kelvin",
+            "1000:6500",
+            "-H",
+            "2",
+            "-V",
+            "0",
+            "-S",
+            "0",
+            "--format",
+            "csv"
+        ]),
+        concat!(
+            "kelvin,saturation,value,hex,r,g,b,luminosity,ansi256\n",
+            "1000,1,1,#FF4400,255,68,0,0.4033,202\n",
+            "6500,1,1,#FFFEFA,255,254,250,0.9958,231\n",
+        )
+    );
+    assert!(colortable_error(&["--kelvin", "10:5"]).contains("10 K is not from 1000 to 40000 K"));
+}
@@ -0,0 +1,24 @@
+//! Helpers shared by the integration tests, which run the built binaries.
+#![allow(dead_code)]
+
+#[cfg(unix)]
+pub mod pty;
+
+use std::process::Command;
+
+/// Command for one of the binaries, with an environment that does not depend on the caller's.
+pub fn command(binary: &str, args: &[&str]) -> Command {
+    let path = match binary {
+        "printcolor" => env!("CARGO_BIN_EXE_printcolor"),
+        "colortable" => env!("CARGO_BIN_EXE_colortable"),
+        "termcolor" => env!("CARGO_BIN_EXE_termcolor"),
+        _ => panic!("unknown binary {binary}"),
+    };
+    let mut command = Command::new(path);
+    command
+        .args(args)
+        .env_clear()
+        .env("TERM", "xterm-256color")
+        .env("COLORTERM", "truecolor");
+    command
+}
@@ -0,0 +1,184 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::process::CommandExt,
+    },
+    process::{Child, Command, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+/// End of file character in the default terminal settings (Ctrl-D).
+const EOF: u8 = 0x04;
+
+/// A child process running with a pseudo-terminal as its controlling terminal and stdio.
+///
+/// Output passes through the terminal's line discipline, so a `\n` written by the child is
+/// read back as `\r\n`, and input is echoed unless the child disables echo.
+pub struct PtySession {
+    master: File,
+    child: Child,
+    output: Vec<u8>,
+}
+
+impl PtySession {
+    /// Spawn the command on a new pseudo-terminal of the given size.
+    pub fn spawn(command: &mut Command, columns: u16, rows: u16) -> Result<Self> {
+        let size = libc::winsize {
+            ws_row: rows,
+            ws_col: columns,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let (mut master, mut slave) = (-1, -1);
+        // SAFETY: the out pointers are valid, and the name and termios are optional.
+        let opened = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                &size,
+            )
+        };
+        if opened != 0 {
+            return Err(io::Error::last_os_error()).context("failed to open a pseudo-terminal");
+        }
+        // SAFETY: openpty returned two new descriptors that nothing else owns.
+        let (master, slave) =
+            unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+        command
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        // SAFETY: only async-signal-safe calls are made between fork and exec. Pre-exec hooks
+        // run after stdio is redirected, so stdin is already the terminal.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let spawned = command.spawn();
+        // Drop our copies of the terminal, otherwise reads never see the child hang up
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let child = spawned.context("failed to spawn on the pseudo-terminal")?;
+        Ok(Self {
+            master: File::from(master),
+            child,
+            output: Vec::new(),
+        })
+    }
+
+    /// Type input into the terminal.
+    pub fn write(&mut self, input: &[u8]) -> Result<()> {
+        self.master.write_all(input)?;
+        self.master.flush()?;
+        Ok(())
+    }
+
+    /// Signal end of input, as typing Ctrl-D at the start of a line does.
+    pub fn send_eof(&mut self) -> Result<()> {
+        self.write(&[EOF])
+    }
+
+    /// Everything read from the terminal so far.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Read until the output read so far satisfies the condition.
+    ///
+    /// Fails if the timeout passes or the child hangs up first.
+    pub fn read_until(&mut self, timeout: Duration, done: impl Fn(&[u8]) -> bool) -> Result<&[u8]> {
+        let deadline = Instant::now() + timeout;
+        while !done(&self.output) {
+            if !self.read_some(deadline)? {
+                bail!(
+                    "terminal output did not match before {}: {:?}",
+                    if Instant::now() >= deadline {
+                        "the timeout"
+                    } else {
+                        "hang up"
+                    },
+                    String::from_utf8_lossy(&self.output),
+                );
+            }
+        }
+        Ok(&self.output)
+    }
+
+    /// Read until the output contains the pattern.
+    pub fn expect(&mut self, timeout: Duration, pattern: &[u8]) -> Result<&[u8]> {
+        self.read_until(timeout, |output| {
+            output
+                .windows(pattern.len())
+                .any(|window| window == pattern)
+        })
+    }
+
+    /// Read all remaining output and wait for the child to exit, killing it after the timeout.
+    pub fn wait(mut self, timeout: Duration) -> Result<(ExitStatus, Vec<u8>)> {
+        let deadline = Instant::now() + timeout;
+        while self.read_some(deadline)? {}
+        if Instant::now() >= deadline {
+            self.child.kill().ok();
+            self.child.wait().ok();
+            return Err(anyhow!(
+                "child did not exit before the timeout: {:?}",
+                String::from_utf8_lossy(&self.output)
+            ));
+        }
+        let status = self.child.wait()?;
+        Ok((status, std::mem::take(&mut self.output)))
+    }
+
+    /// Wait for and append one chunk of output, returning false on timeout or hang up.
+    fn read_some(&mut self, deadline: Instant) -> Result<bool> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+        let mut poll = libc::pollfd {
+            fd: self.master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = i32::try_from(remaining.as_millis())
+            .unwrap_or(i32::MAX)
+            .max(1);
+        // SAFETY: poll is given exactly one valid pollfd.
+        match unsafe { libc::poll(&mut poll, 1, millis) } {
+            -1 => return Err(io::Error::last_os_error().into()),
+            0 => return Ok(false),
+            _ => {}
+        }
+        let mut buffer = [0; 4096];
+        match self.master.read(&mut buffer) {
+            Ok(0) => Ok(false),
+            Ok(n) => {
+                self.output.extend_from_slice(&buffer[..n]);
+                Ok(true)
+            }
+            // Linux reports a hang up of the other side as EIO
+            Err(error) if error.raw_os_error() == Some(libc::EIO) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().ok();
+            self.child.wait().ok();
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Output of the binaries to a pipe, where they do not detect a terminal.
+mod common;
+
+use common::command;
+use std::process::{Output, Stdio};
+
+fn run(binary: &str, args: &[&str], no_color: bool) -> String {
+    let mut command = command(binary, args);
+    command.stdin(Stdio::null());
+    if no_color {
+        command.env("NO_COLOR", "1");
+    }
+    let Output { status, stdout, .. } = command.output().unwrap();
+    assert!(status.success(), "{binary} {args:?}: {status}");
+    String::from_utf8(stdout).unwrap()
+}
+
+#[test]
+fn printcolor_when() {
+    let args = |when| ["--when", when, "-f", "red", "hello"];
+    assert_eq!(run("printcolor", &args("auto"), false), "hello\n");
+    assert_eq!(
+        run("printcolor", &args("always"), false),
+        "\x1b[31mhello\x1b[m\n"
+    );
+    assert_eq!(
+        run("printcolor", &args("always"), true),
+        "\x1b[31mhello\x1b[m\n"
+    );
+    assert_eq!(run("printcolor", &args("never"), false), "hello\n");
+    assert_eq!(run("printcolor", &["-f", "red", "hello"], false), "hello\n");
+}
+
+#[test]
+fn printcolor_auto_keeps_codes_asked_for() {
+    // Codes on their own and prompt escapes are captured by the shell, not shown
+    assert_eq!(
+        run("printcolor", &["-f", "red", "--code-only"], false),
+        "\x1b[31m"
+    );
+    assert_eq!(run("printcolor", &["-f", "red", "--code-only"], true), "");
+}
+
+#[test]
+fn colortable_when() {
+    let args = |when| ["--color", when, "-H", "1", "-r", "0", "--depth", "16"];
+    let plain = " FF0000  \n";
+    assert_eq!(run("colortable", &args("auto"), false), plain);
+    assert_eq!(run("colortable", &args("never"), false), plain);
+    let colored = run("colortable", &args("always"), true);
+    assert!(colored.starts_with("\x1b[101m"), "{colored:?}");
+    assert_eq!(
+        String::from_utf8(termcolors::ansi::strip(colored.as_bytes())).unwrap(),
+        plain
+    );
+}
@@ -0,0 +1,79 @@
+//! Uses every item exported by the prelude, and nothing else of the crate, so that breaking the
+//! public API fails here.
+use termcolors::prelude::*;
+
+#[test]
+fn rgb_parses_and_displays_as_hex() -> Result<(), ColorParseError> {
+    let color: Rgb = "#1e1e2e".parse()?;
+    assert_eq!(color, Rgb(0x1e, 0x1e, 0x2e));
+    assert_eq!(color.to_string(), "#1e1e2e");
+    assert_eq!(Rgb::from_hex("f5c2e7")?.to_string(), "#f5c2e7");
+    assert_eq!("rgb(1, 2, 3)".parse::<Rgb>()?, Rgb(1, 2, 3));
+    let error = "#12345".parse::<Rgb>().unwrap_err();
+    assert!(matches!(error, ColorParseError::HexLength { .. }));
+    let error = ParseError::from(error);
+    assert_eq!((error.offset, error.len), (0, 6));
+    Ok(())
+}
+
+#[test]
+fn names_parse_and_display() -> Result<(), ParseError> {
+    let named: NamedColor = "R".parse()?;
+    assert_eq!(named.to_string(), "bright-red");
+    let attribute: Attribute = "u".parse()?;
+    assert_eq!(attribute.to_string(), "underline");
+    let style: Style = "warn".parse()?;
+    assert_eq!(style.to_string(), "warn");
+    let spec: ColorSpec = "202".parse()?;
+    assert_eq!(spec, ColorSpec::from(202));
+    assert_eq!(
+        "#ff8800".parse::<ColorSpec>()?,
+        ColorSpec::from(Rgb(255, 136, 0))
+    );
+    let error: ParseError = "grean".parse::<NamedColor>().unwrap_err();
+    assert_eq!(error.suggestion.as_deref(), Some("green"));
+    assert!(error.report("grean").contains("did you mean 'green'?"));
+    Ok(())
+}
+
+#[test]
+fn style_builder_paints() {
+    let builder = StyleBuilder::new()
+        .foreground(NamedColor::Red)
+        .background(Rgb(0, 0, 255))
+        .option(Attribute::Bold);
+    assert_eq!(builder.sgr(), "\x1b[31;48;2;0;0;255;1m");
+    assert_eq!(
+        builder.paint("hi"),
+        format!("\x1b[31;48;2;0;0;255;1mhi{RESET}")
+    );
+    assert_eq!(
+        paint("hi", &builder.clone().no_reset(true)),
+        "\x1b[31;48;2;0;0;255;1mhi"
+    );
+    assert_eq!(StyleBuilder::new().paint("hi"), format!("hi{RESET}"));
+}
+
+#[test]
+fn styled_nests_segments() {
+    let styled = Styled::new("a ")
+        .fg(NamedColor::Green)
+        .push(Styled::new("b").bold());
+    assert_eq!(styled.plain(), "a b");
+    assert_eq!(styled.render(), "\x1b[32ma \x1b[m\x1b[32;1mb\x1b[m");
+    assert!(!colors_disabled());
+    set_colors_disabled(false);
+}
+
+#[test]
+fn terminal_detection() {
+    let capabilities = TerminalCapabilities::detect();
+    assert!(!TerminalCapabilities::default().color);
+    if enable_vt_processing() && capabilities.truecolor {
+        assert!(capabilities.ansi256);
+    }
+    if let Some(width) = terminal_width() {
+        assert!(width > 0);
+    }
+    assert!(["\n", "\r\n"].contains(&newline()));
+}
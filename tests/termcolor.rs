@@ -0,0 +1,138 @@
+//! Golden output of the termcolor binary.
+
+use std::process::Command;
+
+/// Output of a successful run.
+fn termcolor(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_termcolor"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{args:?} failed");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn convert_to_each_notation() {
+    assert_eq!(
+        termcolor(&[
+            "convert",
+            "coral",
+            "--to",
+            "hex,rgb,hsv,hsl,ansi256,ansi16,name"
+        ]),
+        concat!(
+            "#ff7f50\n",
+            "rgb(255, 127, 80)\n",
+            "hsv(16, 69%, 100%)\n",
+            "hsl(16, 100%, 66%)\n",
+            "209\n",
+            "3\n",
+            "coral\n",
+        )
+    );
+}
+
+#[test]
+fn convert_from_each_notation() {
+    assert_eq!(termcolor(&["convert", "#FF7F50"]), "#ff7f50\n");
+    assert_eq!(termcolor(&["convert", "200"]), "#ff00d7\n");
+    assert_eq!(
+        termcolor(&["convert", "hsl(120, 100%, 25%)", "--to", "rgb,name"]),
+        "rgb(0, 128, 0)\ngreen\n"
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_termcolor"))
+        .args(["convert", "rgb(300,0,0)"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("red must be a number from 0 to 255, found '300' (at byte 4)"));
+}
+
+#[test]
+fn info_is_plain_when_piped() {
+    assert_eq!(
+        termcolor(&["info", "coral"]),
+        concat!(
+            "hex         #ff7f50\n",
+            "rgb         rgb(255, 127, 80)\n",
+            "hsv         hsv(16, 69%, 100%)\n",
+            "hsl         hsl(16, 100%, 66%)\n",
+            "ansi 256    209\n",
+            "ansi 16     3\n",
+            "luminance   0.3702\n",
+            "name        coral\n",
+            "readable    black 8.4:1\n",
+        )
+    );
+    assert!(
+        termcolor(&["info", "#000080"]).ends_with("name        navy\nreadable    white 16.0:1\n")
+    );
+}
+
+#[test]
+fn table_is_colortable() {
+    let args = ["-H", "2", "-r", "0", "--depth", "16"];
+    assert_eq!(
+        termcolor(&[&["table"], &args[..]].concat()),
+        " FF0000  \n 00FFFF  \n"
+    );
+    let colored = "\x1b[101m\x1b[37m FF0000  \x1b[m\n\x1b[106m\x1b[30m 00FFFF  \x1b[m\n";
+    assert_eq!(
+        termcolor(&[&["--color", "always", "table"], &args[..]].concat()),
+        colored
+    );
+    assert_eq!(
+        termcolor(&[&["table"], &args[..], &["--color", "always"]].concat()),
+        colored
+    );
+}
+
+#[test]
+fn color_applies_to_every_subcommand() {
+    assert_eq!(
+        termcolor(&["--color", "always", "format", "-f", "red", "a"]),
+        "\x1b[31ma\x1b[m\n"
+    );
+    assert_eq!(
+        termcolor(&["format", "--color", "always", "-f", "red", "a"]),
+        "\x1b[31ma\x1b[m\n"
+    );
+    assert_eq!(
+        termcolor(&["--color", "never", "format", "--when", "always", "-f", "red", "a"]),
+        "a\n"
+    );
+    let info = termcolor(&["--color", "always", "info", "red"]);
+    assert!(info.starts_with("\x1b[48;2;255;0;0m "), "{info:?}");
+    let caps = termcolor(&["--color", "always", "caps"]);
+    assert!(
+        caps.starts_with("tty         \x1b[31mno\x1b[m\n"),
+        "{caps:?}"
+    );
+    assert_eq!(
+        termcolor(&["--color", "never", "convert", "red"]),
+        "#ff0000\n"
+    );
+}
+
+#[test]
+fn format_segments_share_one_line() {
+    assert_eq!(
+        termcolor(&["format", "-f", "red", "a", "--then", "-f", "blue", "b"]),
+        "ab\n"
+    );
+    assert_eq!(
+        termcolor(&["--color", "always", "format", "-f", "red", "a", "--then", "-f", "blue", "b"]),
+        "\x1b[31ma\x1b[m\x1b[34mb\x1b[m\n"
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_termcolor"))
+        .args(["convert", "red", "--then", "blue"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--then can only be used with format"));
+}
@@ -0,0 +1,84 @@
+//! Golden output of the binaries running on a pseudo-terminal, where they detect a terminal.
+#![cfg(unix)]
+
+mod common;
+
+use common::{command, pty::PtySession};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Everything the binary writes to the terminal until it exits successfully.
+fn run(binary: &str, args: &[&str]) -> Vec<u8> {
+    let session = PtySession::spawn(&mut command(binary, args), 80, 24).unwrap();
+    let (status, output) = session.wait(TIMEOUT).unwrap();
+    assert!(status.success(), "{binary} {args:?}: {status}");
+    output
+}
+
+#[test]
+fn printcolor_styles_text() {
+    assert_eq!(
+        run("printcolor", &["-f", "red", "-o", "bold", "hello"]),
+        b"\x1b[31;1mhello\x1b[m\r\n"
+    );
+    assert_eq!(
+        run("printcolor", &["-b", "#102030", "-n", "x"]),
+        b"\x1b[48;2;16;32;48mx\x1b[m"
+    );
+}
+
+#[test]
+fn printcolor_respects_no_color_on_a_terminal() {
+    let mut command = command("printcolor", &["-f", "red", "hello"]);
+    command.env("NO_COLOR", "1");
+    let session = PtySession::spawn(&mut command, 80, 24).unwrap();
+    let (_, output) = session.wait(TIMEOUT).unwrap();
+    assert_eq!(output, b"hello\r\n");
+}
+
+#[test]
+fn printcolor_streams_each_line_before_end_of_input() {
+    let mut session =
+        PtySession::spawn(&mut command("printcolor", &["-f", "green", "-"]), 80, 24).unwrap();
+    session.write(b"one\n").unwrap();
+    // Stdin is the terminal, so - asks to stream it. Echoed input comes first, and the styled
+    // line only appears before the end of input if it is flushed.
+    session.expect(TIMEOUT, b"\x1b[32mone\x1b[m").unwrap();
+    session.write(b"two\n").unwrap();
+    session.expect(TIMEOUT, b"\x1b[32mtwo\x1b[m").unwrap();
+    session.send_eof().unwrap();
+    let (status, output) = session.wait(TIMEOUT).unwrap();
+    assert!(status.success());
+    // The line ending of a line is written once the next one is read, after its echo
+    assert_eq!(
+        output,
+        b"one\r\n\x1b[32mone\x1b[mtwo\r\n\r\n\x1b[32mtwo\x1b[m\r\n"
+    );
+}
+
+#[test]
+fn colortable_grid() {
+    let expected = concat!(
+        "\x1b[48;2;128;0;0m\x1b[38;2;163;163;163m 800000  \x1b[m",
+        "\x1b[48;2;255;0;0m\x1b[38;2;187;187;187m FF0000  \x1b[m",
+        "\x1b[48;2;255;128;128m\x1b[38;2;2;2;2m FF8080  \x1b[m\r\n",
+        "\x1b[48;2;0;128;128m\x1b[38;2;212;212;212m 008080  \x1b[m",
+        "\x1b[48;2;0;255;255m\x1b[38;2;0;0;0m 00FFFF  \x1b[m",
+        "\x1b[48;2;128;255;255m\x1b[38;2;0;0;0m 80FFFF  \x1b[m\r\n",
+    );
+    let output = run("colortable", &["-H", "2", "-r", "1"]);
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+}
+
+#[test]
+fn colortable_depth_follows_the_terminal() {
+    let mut command = command("colortable", &["-H", "1", "-r", "0"]);
+    command
+        .env("TERM", "xterm-256color")
+        .env_remove("COLORTERM");
+    let session = PtySession::spawn(&mut command, 80, 24).unwrap();
+    let (_, output) = session.wait(TIMEOUT).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with("\x1b[48;5;196m"), "{output:?}");
+}